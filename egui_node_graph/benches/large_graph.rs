@@ -0,0 +1,222 @@
+//! Measures the time taken to draw a frame of very large graphs, without a
+//! window. Run it with `cargo bench -p egui_node_graph`.
+//!
+//! Each graph is drawn with all of its nodes laid out on every frame, as if
+//! nothing was culled, then with the culling of the nodes outside of the
+//! editor. Zoomed out graphs are drawn with whole nodes, then with the
+//! simplified boxes.
+
+use std::borrow::Cow;
+use std::time::{Duration, Instant};
+
+use egui::{vec2, CentralPanel, Color32, Context, DragValue, Pos2, RawInput, Rect, Ui};
+use egui_node_graph::*;
+
+#[cfg(feature = "persistence")]
+use serde::{Deserialize, Serialize};
+
+const SCREEN_SIZE: [f32; 2] = [1280.0, 720.0];
+const WARMUP_FRAMES: usize = 3;
+const FRAMES: usize = 20;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
+struct BenchType;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
+struct BenchValue(f32);
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
+struct BenchNode;
+
+#[derive(Clone, Copy, Debug)]
+struct BenchResponse;
+
+#[derive(Clone, Copy, Debug)]
+struct BenchTemplate;
+
+struct AllBenchTemplates;
+
+type BenchState = GraphEditorState<BenchNode, BenchType, BenchValue, BenchTemplate, ()>;
+
+impl UserResponseTrait for BenchResponse {}
+
+impl DataTypeTrait<()> for BenchType {
+    fn data_type_color(&self, _user_state: &mut ()) -> Color32 {
+        Color32::from_rgb(90, 160, 220)
+    }
+
+    fn name(&self) -> Cow<'_, str> {
+        Cow::Borrowed("value")
+    }
+}
+
+impl WidgetValueTrait for BenchValue {
+    type Response = BenchResponse;
+    type UserState = ();
+    type NodeData = BenchNode;
+
+    fn value_widget(
+        &mut self,
+        param_name: &str,
+        _node_id: NodeId,
+        ui: &mut Ui,
+        _user_state: &mut (),
+        _node_data: &BenchNode,
+        _kind: InputParamKind,
+    ) -> Vec<BenchResponse> {
+        ui.horizontal(|ui| {
+            ui.label(param_name);
+            ui.add(DragValue::new(&mut self.0));
+        });
+        Vec::new()
+    }
+}
+
+impl NodeDataTrait for BenchNode {
+    type Response = BenchResponse;
+    type UserState = ();
+    type DataType = BenchType;
+    type ValueType = BenchValue;
+
+    fn bottom_ui(
+        &self,
+        _ui: &mut Ui,
+        _node_id: NodeId,
+        _graph: &Graph<BenchNode, BenchType, BenchValue>,
+        _user_state: &mut (),
+    ) -> Vec<NodeResponse<BenchResponse, BenchNode>> {
+        Vec::new()
+    }
+}
+
+impl NodeTemplateTrait for BenchTemplate {
+    type NodeData = BenchNode;
+    type DataType = BenchType;
+    type ValueType = BenchValue;
+    type UserState = ();
+    type CategoryType = ();
+
+    fn node_finder_label(&self, _user_state: &mut ()) -> Cow<'_, str> {
+        Cow::Borrowed("Add")
+    }
+
+    fn node_graph_label(&self, _user_state: &mut ()) -> String {
+        "Add".into()
+    }
+
+    fn user_data(&self, _user_state: &mut ()) -> BenchNode {
+        BenchNode
+    }
+
+    fn build_node(
+        &self,
+        graph: &mut Graph<BenchNode, BenchType, BenchValue>,
+        _user_state: &mut (),
+        node_id: NodeId,
+    ) {
+        for name in ["a", "b"] {
+            graph.add_input_param(
+                node_id,
+                name.into(),
+                BenchType,
+                BenchValue(1.0),
+                InputParamKind::ConnectionOrConstant,
+                true,
+            );
+        }
+        graph.add_output_param(node_id, "out".into(), BenchType);
+    }
+}
+
+impl NodeTemplateIter for AllBenchTemplates {
+    type Item = BenchTemplate;
+
+    fn all_kinds(&self) -> Vec<BenchTemplate> {
+        vec![BenchTemplate]
+    }
+}
+
+/// A square grid of `node_count` nodes. Each node is connected to the node
+/// on its left and to the node above it.
+fn grid_graph(node_count: usize, zoom: f32) -> BenchState {
+    let mut state = BenchState::new(zoom);
+    let columns = (node_count as f32).sqrt().ceil() as usize;
+    let mut nodes = Vec::with_capacity(node_count);
+    for index in 0..node_count {
+        let node = state
+            .graph
+            .add_node("Add".into(), BenchNode, |graph, node_id| {
+                BenchTemplate.build_node(graph, &mut (), node_id)
+            });
+        let (column, row) = (index % columns, index / columns);
+        let position = (vec2(column as f32 * 250.0, row as f32 * 150.0) * zoom).to_pos2();
+        state.node_positions.insert(node, position);
+        state.node_order.push(node);
+        nodes.push(node);
+
+        let inputs: Vec<InputId> = state.graph[node].input_ids().collect();
+        let left = (column > 0).then(|| nodes[index - 1]);
+        let above = index.checked_sub(columns).map(|above| nodes[above]);
+        for (input, source) in inputs.into_iter().zip([left, above]) {
+            if let Some(source) = source {
+                let output = state.graph[source].output_ids().next().unwrap();
+                state.graph.add_connection(output, input);
+            }
+        }
+    }
+    state
+}
+
+/// The average time taken to draw and tessellate a frame.
+fn measure_frame(state: &mut BenchState, relayout_all: bool) -> Duration {
+    let ctx = Context::default();
+    let input = || RawInput {
+        screen_rect: Some(Rect::from_min_size(Pos2::ZERO, SCREEN_SIZE.into())),
+        ..Default::default()
+    };
+    let draw = |state: &mut BenchState| {
+        if relayout_all {
+            // Without a cached size, every node is laid out and painted.
+            state.node_sizes.clear();
+        }
+        let output = ctx.run(input(), |ctx| {
+            CentralPanel::default().show(ctx, |ui| {
+                let _ = state.draw_graph_editor(ui, AllBenchTemplates, &mut (), Vec::new());
+            });
+        });
+        ctx.tessellate(output.shapes);
+    };
+
+    for _ in 0..WARMUP_FRAMES {
+        draw(state);
+    }
+    let start = Instant::now();
+    for _ in 0..FRAMES {
+        draw(state);
+    }
+    start.elapsed() / FRAMES as u32
+}
+
+fn main() {
+    for node_count in [1_000, 5_000] {
+        for (zoom, description) in [(1.0, "zoom 1.0"), (0.25, "zoom 0.25")] {
+            let mut state = grid_graph(node_count, zoom);
+            state.style = Some(GraphEditorStyle {
+                simplified_zoom: 0.0,
+                ..GraphEditorStyle::dark()
+            });
+            let baseline = measure_frame(&mut state, true);
+            state.style = Some(GraphEditorStyle::dark());
+            let optimized = measure_frame(&mut state, false);
+            println!(
+                "{node_count:>5} nodes, {description:<9}: {:>8.2} ms/frame without culling, \
+                {:>8.2} ms/frame with culling and simplified nodes",
+                baseline.as_secs_f64() * 1000.0,
+                optimized.as_secs_f64() * 1000.0,
+            );
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use super::*;
+
+/// A mutation of a [`Graph`], recorded in its [`ChangeJournal`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GraphChange {
+    NodeAdded(NodeId),
+    /// The node was removed. The removal of its connections and parameters
+    /// is recorded before this.
+    NodeRemoved(NodeId),
+    NodeRenamed(NodeId),
+    InputAdded {
+        node: NodeId,
+        input: InputId,
+    },
+    InputRemoved {
+        node: NodeId,
+        input: InputId,
+    },
+    OutputAdded {
+        node: NodeId,
+        output: OutputId,
+    },
+    OutputRemoved {
+        node: NodeId,
+        output: OutputId,
+    },
+    Connected {
+        output: OutputId,
+        input: InputId,
+    },
+    Disconnected {
+        output: OutputId,
+        input: InputId,
+    },
+    /// The value of the input was changed with [`Graph::set_input_value`], or
+    /// by its widget in the editor.
+    ValueChanged(InputId),
+    /// The graph was replaced as a whole, for instance by an undo or a redo,
+    /// or repaired with [`Graph::repair`]. Anything in it may have changed.
+    Replaced,
+}
+
+/// The changes made to a [`Graph`] since they were last drained. Recording
+/// is disabled by default, enable it with [`ChangeJournal::set_enabled`] and
+/// call [`ChangeJournal::drain`] every frame.
+///
+/// Changes are recorded by the methods of the graph and by the editor.
+/// Modifying the public fields of the graph directly is not recorded, use
+/// [`ChangeJournal::record`] for that.
+#[derive(Clone, Debug, Default)]
+pub struct ChangeJournal {
+    enabled: bool,
+    changes: Vec<GraphChange>,
+}
+
+impl ChangeJournal {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Starts or stops recording changes. Disabling discards the changes
+    /// that were not drained.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.changes.clear();
+        }
+    }
+
+    /// Records a change, when recording is enabled.
+    pub fn record(&mut self, change: GraphChange) {
+        if self.enabled {
+            self.changes.push(change);
+        }
+    }
+
+    /// The changes not drained yet, in the order they were made.
+    pub fn changes(&self) -> &[GraphChange] {
+        &self.changes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Removes and returns the recorded changes, in the order they were made.
+    pub fn drain(&mut self) -> std::vec::Drain<'_, GraphChange> {
+        self.changes.drain(..)
+    }
+}
@@ -0,0 +1,404 @@
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+
+use super::*;
+
+#[cfg(feature = "persistence")]
+use serde::{Deserialize, Serialize};
+
+/// Bound required on the user types stored in a [`GraphClipboard`]. With the
+/// `persistence` feature, the clipboard is exchanged with the system clipboard
+/// as text, so the types must be serializable.
+#[cfg(feature = "persistence")]
+pub trait ClipboardData: Serialize + serde::de::DeserializeOwned {}
+#[cfg(feature = "persistence")]
+impl<T: Serialize + serde::de::DeserializeOwned> ClipboardData for T {}
+
+/// Bound required on the user types stored in a [`GraphClipboard`]. With the
+/// `persistence` feature, the clipboard is exchanged with the system clipboard
+/// as text, so the types must be serializable.
+#[cfg(not(feature = "persistence"))]
+pub trait ClipboardData {}
+#[cfg(not(feature = "persistence"))]
+impl<T> ClipboardData for T {}
+
+/// An input parameter of a [`ClipboardNode`].
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
+pub struct ClipboardInput<DataType, ValueType> {
+    pub name: String,
+    pub typ: DataType,
+    pub value: ValueType,
+    pub kind: InputParamKind,
+    #[cfg_attr(
+        feature = "persistence",
+        serde(default = "crate::graph::input_max_connections_default")
+    )]
+    pub max_connections: Option<NonZeroUsize>,
+    pub shown_inline: bool,
+}
+
+/// An output parameter of a [`ClipboardNode`].
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
+pub struct ClipboardOutput<DataType> {
+    pub name: String,
+    pub typ: DataType,
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub max_connections: Option<NonZeroUsize>,
+}
+
+/// A copied node. It does not reference any id of the graph it was copied
+/// from, so it can be pasted anywhere.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
+pub struct ClipboardNode<NodeData, DataType, ValueType> {
+    pub label: String,
+    pub user_data: NodeData,
+    /// The position of the node relative to the top-left corner of the copied
+    /// selection, at a zoom level of 1.0.
+    pub offset: egui::Vec2,
+    /// Whether the node was collapsed, and the size it was resized to at a
+    /// zoom level of 1.0.
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub view: NodeView,
+    pub inputs: Vec<ClipboardInput<DataType, ValueType>>,
+    pub outputs: Vec<ClipboardOutput<DataType>>,
+}
+
+/// A connection between two copied nodes. Nodes are referenced by their index
+/// in [`GraphClipboard::nodes`], and parameters by their index in the node.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
+pub struct ClipboardConnection<EdgeData = ()> {
+    pub output_node: usize,
+    pub output: usize,
+    pub input_node: usize,
+    pub input: usize,
+    /// The data attached to the connection, if any.
+    #[cfg_attr(feature = "persistence", serde(default = "Option::default"))]
+    pub data: Option<EdgeData>,
+}
+
+/// A set of copied nodes, with the values of their input parameters and the
+/// connections between them. Connections to nodes that were not copied are
+/// left out.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
+pub struct GraphClipboard<NodeData, DataType, ValueType, EdgeData = ()> {
+    pub nodes: Vec<ClipboardNode<NodeData, DataType, ValueType>>,
+    pub connections: Vec<ClipboardConnection<EdgeData>>,
+}
+
+impl<NodeData, DataType, ValueType, EdgeData>
+    GraphClipboard<NodeData, DataType, ValueType, EdgeData>
+where
+    NodeData: ClipboardData,
+    DataType: ClipboardData,
+    ValueType: ClipboardData,
+    EdgeData: ClipboardData,
+{
+    /// Encodes the clipboard as text, to be placed in the system clipboard.
+    /// Returns `None` when the `persistence` feature is disabled.
+    pub fn to_text(&self) -> Option<String> {
+        #[cfg(feature = "persistence")]
+        {
+            serde_json::to_string(self).ok()
+        }
+        #[cfg(not(feature = "persistence"))]
+        {
+            None
+        }
+    }
+
+    /// Decodes a clipboard encoded with [`GraphClipboard::to_text`]. Returns
+    /// `None` if the text is not a valid clipboard, or when the `persistence`
+    /// feature is disabled.
+    pub fn from_text(text: &str) -> Option<Self> {
+        #[cfg(feature = "persistence")]
+        {
+            serde_json::from_str(text).ok()
+        }
+        #[cfg(not(feature = "persistence"))]
+        {
+            let _ = text;
+            None
+        }
+    }
+}
+
+impl<NodeData, DataType, ValueType, NodeTemplate, UserState, EdgeData>
+    GraphEditorState<NodeData, DataType, ValueType, NodeTemplate, UserState, EdgeData>
+where
+    NodeData: Clone,
+    DataType: Clone,
+    ValueType: Clone,
+    EdgeData: Clone,
+{
+    /// Copies the given nodes, along with the connections between them. Ids
+    /// that are not in the graph are ignored.
+    pub fn copy_nodes(
+        &self,
+        nodes: &[NodeId],
+    ) -> GraphClipboard<NodeData, DataType, ValueType, EdgeData> {
+        let nodes: Vec<NodeId> = nodes
+            .iter()
+            .copied()
+            .filter(|id| self.graph.nodes.contains_key(*id))
+            .collect();
+        let position = |id: NodeId| self.node_positions.get(id).copied().unwrap_or_default();
+        let origin = nodes
+            .iter()
+            .map(|id| position(*id))
+            .reduce(|a, b| a.min(b))
+            .unwrap_or_default();
+
+        let mut inputs = HashMap::new();
+        let mut outputs = HashMap::new();
+        let copied_nodes = nodes
+            .iter()
+            .enumerate()
+            .map(|(node_index, id)| {
+                let node = &self.graph[*id];
+                let view = self.node_views.get(*id).copied().unwrap_or_default();
+                ClipboardNode {
+                    label: node.label.clone(),
+                    user_data: node.user_data.clone(),
+                    offset: (position(*id) - origin) / self.pan_zoom.zoom,
+                    view: NodeView {
+                        size: view.size.map(|size| size / self.pan_zoom.zoom),
+                        ..view
+                    },
+                    inputs: node
+                        .inputs
+                        .iter()
+                        .enumerate()
+                        .map(|(index, (name, input))| {
+                            inputs.insert(*input, (node_index, index));
+                            let param = &self.graph[*input];
+                            ClipboardInput {
+                                name: name.clone(),
+                                typ: param.typ.clone(),
+                                value: param.value.clone(),
+                                kind: param.kind,
+                                max_connections: param.max_connections,
+                                shown_inline: param.shown_inline,
+                            }
+                        })
+                        .collect(),
+                    outputs: node
+                        .outputs
+                        .iter()
+                        .enumerate()
+                        .map(|(index, (name, output))| {
+                            outputs.insert(*output, (node_index, index));
+                            let param = &self.graph[*output];
+                            ClipboardOutput {
+                                name: name.clone(),
+                                typ: param.typ.clone(),
+                                max_connections: param.max_connections,
+                            }
+                        })
+                        .collect(),
+                }
+            })
+            .collect();
+
+        let connections = self
+            .graph
+            .iter_connections()
+            .filter_map(|(input, output)| {
+                let input_node = *inputs.get(&input)?;
+                let output_node = *outputs.get(&output)?;
+                Some(ClipboardConnection {
+                    output_node: output_node.0,
+                    output: output_node.1,
+                    input_node: input_node.0,
+                    input: input_node.1,
+                    data: self.edge_data(output, input).cloned(),
+                })
+            })
+            .collect();
+
+        GraphClipboard {
+            nodes: copied_nodes,
+            connections,
+        }
+    }
+
+    /// Inserts the nodes of the clipboard in the graph with fresh ids, with
+    /// the top-left corner of the pasted selection at `position`. The pasted
+    /// nodes are added to the group currently being edited, and become the
+    /// selected nodes.
+    ///
+    /// Returns the ids of the new nodes, in the same order as
+    /// [`GraphClipboard::nodes`].
+    pub fn paste_nodes(
+        &mut self,
+        clipboard: &GraphClipboard<NodeData, DataType, ValueType, EdgeData>,
+        position: egui::Pos2,
+    ) -> Vec<NodeId> {
+        let mut new_inputs = Vec::with_capacity(clipboard.nodes.len());
+        let mut new_outputs = Vec::with_capacity(clipboard.nodes.len());
+        let new_nodes: Vec<NodeId> = clipboard
+            .nodes
+            .iter()
+            .map(|node| {
+                let node_id =
+                    self.graph
+                        .add_node(node.label.clone(), node.user_data.clone(), |_, _| {});
+                new_inputs.push(
+                    node.inputs
+                        .iter()
+                        .map(|input| {
+                            self.graph.add_wide_input_param(
+                                node_id,
+                                input.name.clone(),
+                                input.typ.clone(),
+                                input.value.clone(),
+                                input.kind,
+                                input.max_connections,
+                                input.shown_inline,
+                            )
+                        })
+                        .collect::<Vec<_>>(),
+                );
+                new_outputs.push(
+                    node.outputs
+                        .iter()
+                        .map(|output| {
+                            let id = self.graph.add_output_param(
+                                node_id,
+                                output.name.clone(),
+                                output.typ.clone(),
+                            );
+                            self.graph[id].max_connections = output.max_connections;
+                            id
+                        })
+                        .collect::<Vec<_>>(),
+                );
+                self.node_positions
+                    .insert(node_id, position + node.offset * self.pan_zoom.zoom);
+                if node.view != NodeView::default() {
+                    let view = NodeView {
+                        size: node.view.size.map(|size| size * self.pan_zoom.zoom),
+                        ..node.view
+                    };
+                    self.node_views.insert(node_id, view);
+                }
+                self.node_order.push(node_id);
+                self.add_to_current_group(node_id);
+                node_id
+            })
+            .collect();
+
+        for connection in &clipboard.connections {
+            let output = new_outputs
+                .get(connection.output_node)
+                .and_then(|outputs| outputs.get(connection.output));
+            let input = new_inputs
+                .get(connection.input_node)
+                .and_then(|inputs| inputs.get(connection.input));
+            if let (Some(output), Some(input)) = (output, input) {
+                self.graph.add_connection(*output, *input);
+                if let Some(data) = &connection.data {
+                    let _ = self.set_edge_data(*output, *input, data.clone());
+                }
+            }
+        }
+
+        self.selected_nodes = new_nodes.clone();
+        self.selected_groups.clear();
+        new_nodes
+    }
+
+    /// Copies the given nodes and pastes them slightly below and to the
+    /// right of the originals. Returns the ids of the new nodes.
+    pub fn duplicate_nodes(&mut self, nodes: &[NodeId]) -> Vec<NodeId> {
+        let clipboard = self.copy_nodes(nodes);
+        let position = nodes
+            .iter()
+            .filter_map(|node| self.node_positions.get(*node).copied())
+            .reduce(|a, b| a.min(b))
+            .unwrap_or_default();
+        self.paste_nodes(
+            &clipboard,
+            position + egui::Vec2::splat(20.0) * self.pan_zoom.zoom,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    #[test]
+    fn test_copy_paste() {
+        let mut state = TestState::<String>::default();
+        let (a, a_in, a_out) = add_placed_node(&mut state, egui::pos2(10.0, 10.0));
+        let (b, b_in, b_out) = add_placed_node(&mut state, egui::pos2(50.0, 30.0));
+        let (_, c_in, _) = add_placed_node(&mut state, egui::pos2(90.0, 10.0));
+        state.graph.add_connection(a_out, b_in);
+        state.graph.add_connection(b_out, c_in);
+        state.graph.add_connection(b_out, a_in);
+        state.graph.inputs[b_in].value = 42;
+        state.set_edge_data(a_out, b_in, "a to b".into()).unwrap();
+
+        let clipboard = state.copy_nodes(&[a, b]);
+        assert_eq!(clipboard.nodes.len(), 2);
+        // The connection to `c` is not copied.
+        assert_eq!(clipboard.connections.len(), 2);
+
+        let pasted = state.paste_nodes(&clipboard, egui::pos2(100.0, 100.0));
+        assert_eq!(state.graph.nodes.len(), 5);
+        assert_eq!(state.selected_nodes, pasted);
+        assert_eq!(state.node_positions[pasted[0]], egui::pos2(100.0, 100.0));
+        assert_eq!(state.node_positions[pasted[1]], egui::pos2(140.0, 120.0));
+
+        let new_a = &state.graph[pasted[0]];
+        let new_b = &state.graph[pasted[1]];
+        let (new_a_in, new_a_out) = (new_a.inputs[0].1, new_a.outputs[0].1);
+        let (new_b_in, new_b_out) = (new_b.inputs[0].1, new_b.outputs[0].1);
+        assert!(![a_in, b_in, c_in].contains(&new_a_in));
+        assert_eq!(state.graph.connection(new_b_in), Some(new_a_out));
+        assert_eq!(state.graph.connection(new_a_in), Some(new_b_out));
+        assert_eq!(state.graph[new_b_in].value, 42);
+        assert_eq!(state.graph[new_b_in].node, pasted[1]);
+        assert_eq!(
+            state.edge_data(new_a_out, new_b_in).map(String::as_str),
+            Some("a to b")
+        );
+        assert_eq!(state.edge_data(new_b_out, new_a_in), None);
+        // The original connections are untouched.
+        assert_eq!(state.graph.connection(c_in), Some(b_out));
+    }
+
+    #[test]
+    fn test_copy_node_views() {
+        let mut state = TestState::<String>::default();
+        let (a, _, _) = add_placed_node(&mut state, egui::pos2(10.0, 10.0));
+        let (b, _, _) = add_placed_node(&mut state, egui::pos2(50.0, 30.0));
+        state.pan_zoom.zoom = 2.0;
+        state.set_node_collapsed(a, true);
+        state.node_views.insert(
+            b,
+            NodeView {
+                collapsed: false,
+                size: Some(egui::vec2(300.0, 100.0)),
+            },
+        );
+
+        let clipboard = state.copy_nodes(&[a, b]);
+        assert_eq!(clipboard.nodes[1].view.size, Some(egui::vec2(150.0, 50.0)));
+
+        state.pan_zoom.zoom = 1.0;
+        let pasted = state.paste_nodes(&clipboard, egui::Pos2::ZERO);
+        assert!(state.is_node_collapsed(pasted[0]));
+        assert!(!state.is_node_collapsed(pasted[1]));
+        assert_eq!(
+            state.node_views[pasted[1]].size,
+            Some(egui::vec2(150.0, 50.0))
+        );
+    }
+}
@@ -0,0 +1,281 @@
+use crate::{
+    editor_ui::close_button, CommentId, GraphEditorState, GraphEditorStyle, GroupId, PanZoom,
+};
+
+use egui::*;
+
+/// The two kinds of [`Comment`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
+pub enum CommentKind {
+    /// A titled rectangle drawn beneath the nodes. Dragging its header or its
+    /// border moves everything inside it.
+    Frame,
+    /// A sticky note with a block of text.
+    Note,
+}
+
+/// A comment on the canvas. Comments don't interact with the graph, they are
+/// only used to annotate it.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
+pub struct Comment {
+    pub kind: CommentKind,
+    /// The title of a frame, or the contents of a note.
+    pub text: String,
+    /// The position of the top-left corner, in the same coordinates as the
+    /// node positions.
+    pub position: Pos2,
+    pub size: Vec2,
+    pub color: Color32,
+    /// The group the comment was created in. Comments are only shown while
+    /// editing that group.
+    pub group: Option<GroupId>,
+}
+
+impl Comment {
+    pub const MIN_SIZE: Vec2 = vec2(60.0, 40.0);
+
+    /// A comment colored from [`GraphEditorStyle::comment_colors`].
+    pub fn new(kind: CommentKind, position: Pos2, size: Vec2, style: &GraphEditorStyle) -> Self {
+        let (text, color) = match kind {
+            CommentKind::Frame => ("Comment", style.comment_colors[0]),
+            CommentKind::Note => ("", style.comment_colors[1]),
+        };
+        Self {
+            kind,
+            text: text.into(),
+            position,
+            size,
+            color,
+            group: None,
+        }
+    }
+
+    /// Switches to the next color in [`GraphEditorStyle::comment_colors`].
+    pub fn cycle_color(&mut self, style: &GraphEditorStyle) {
+        let colors = &style.comment_colors;
+        let next = colors
+            .iter()
+            .position(|color| *color == self.color)
+            .map_or(0, |index| (index + 1) % colors.len());
+        self.color = colors[next];
+    }
+}
+
+impl<NodeData, DataType, ValueType, NodeTemplate, UserState, EdgeData>
+    GraphEditorState<NodeData, DataType, ValueType, NodeTemplate, UserState, EdgeData>
+{
+    /// Adds a comment covering `rect`, in the same coordinates as the node
+    /// positions. It is shown in the group currently being edited.
+    pub fn add_comment(
+        &mut self,
+        kind: CommentKind,
+        rect: Rect,
+        style: &GraphEditorStyle,
+    ) -> CommentId {
+        let mut comment = Comment::new(kind, rect.min, rect.size().max(Comment::MIN_SIZE), style);
+        comment.group = self.current_group;
+        self.comments.insert(comment)
+    }
+
+    /// Removes a comment. The nodes inside a frame are left untouched.
+    pub fn remove_comment(&mut self, comment: CommentId) -> Option<Comment> {
+        self.selected_comments.retain(|id| *id != comment);
+        if self.editing_comment == Some(comment) {
+            self.editing_comment = None;
+        }
+        self.comments.remove(comment)
+    }
+}
+
+/// The interactions with a comment during a frame.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum CommentAction {
+    Select,
+    Move(Vec2),
+    Resize(Vec2),
+    StartEditing,
+    StopEditing,
+    Edited,
+    CycleColor,
+    Delete,
+}
+
+pub(crate) struct CommentWidget<'a> {
+    pub id: Id,
+    pub comment: &'a mut Comment,
+    /// Offset from node coordinates to screen coordinates.
+    pub pan: Vec2,
+    pub selected: bool,
+    pub editing: bool,
+    pub style: &'a GraphEditorStyle,
+}
+
+impl<'a> CommentWidget<'a> {
+    /// Draws the comment. Returns the rect it covers on screen, and the
+    /// interactions with it.
+    pub fn show(self, pan_zoom: &PanZoom, ui: &mut Ui) -> (Rect, Vec<CommentAction>) {
+        let mut actions = Vec::new();
+        let zoom = pan_zoom.zoom;
+        let rect = Rect::from_min_size(self.comment.position + self.pan, self.comment.size);
+        let header_rect = Rect::from_min_size(rect.min, vec2(rect.width(), 22.0 * zoom));
+        let body_rect = Rect::from_min_max(header_rect.left_bottom(), rect.max);
+        let rounding = Rounding::same(4.0 * zoom);
+        let color = self.comment.color;
+        let text_color = self.style.comment_text;
+
+        // Only the header and the border of a frame can be grabbed, so box
+        // selections can start inside it.
+        let body_response = match self.comment.kind {
+            CommentKind::Frame => {
+                let border = 6.0 * zoom;
+                [
+                    header_rect,
+                    Rect::from_min_max(body_rect.min, pos2(body_rect.min.x + border, rect.max.y)),
+                    Rect::from_min_max(pos2(body_rect.max.x - border, body_rect.min.y), rect.max),
+                    Rect::from_min_max(pos2(rect.min.x, rect.max.y - border), rect.max),
+                ]
+                .into_iter()
+                .enumerate()
+                .map(|(i, part)| ui.interact(part, self.id.with(i), Sense::click_and_drag()))
+                .reduce(|a, b| a | b)
+                .unwrap()
+            }
+            CommentKind::Note => ui.interact(rect, self.id, Sense::click_and_drag()),
+        };
+
+        let painter = ui.painter().with_clip_rect(rect.expand(2.0 * zoom));
+        let fill = match self.comment.kind {
+            CommentKind::Frame => color.linear_multiply(0.15),
+            CommentKind::Note => color.linear_multiply(0.85),
+        };
+        let stroke = if self.selected {
+            Stroke::new(
+                2.0 * self.style.selection_outline_width * zoom,
+                self.style.selection_outline,
+            )
+        } else {
+            Stroke::new(1.0 * zoom, color)
+        };
+        painter.rect(rect, rounding, fill, stroke);
+        painter.rect_filled(
+            header_rect,
+            Rounding {
+                nw: rounding.nw,
+                ne: rounding.ne,
+                ..Rounding::none()
+            },
+            color.linear_multiply(0.6),
+        );
+
+        // Text, edited in place after a double click
+        let text_rect = match self.comment.kind {
+            // Leave room for the header buttons
+            CommentKind::Frame => {
+                let rect = header_rect.shrink2(vec2(8.0, 2.0) * zoom);
+                Rect::from_min_max(
+                    rect.min,
+                    pos2(rect.max.x - 40.0 * zoom, rect.max.y).max(rect.min),
+                )
+            }
+            CommentKind::Note => body_rect.shrink(6.0 * zoom),
+        };
+        if self.editing {
+            let previous_text = self.comment.text.clone();
+            let text_edit = match self.comment.kind {
+                CommentKind::Frame => TextEdit::singleline(&mut self.comment.text)
+                    .font(TextStyle::Button)
+                    .frame(false),
+                CommentKind::Note => TextEdit::multiline(&mut self.comment.text).frame(false),
+            };
+            let response = ui.put(
+                text_rect,
+                text_edit
+                    .id(self.id.with("text"))
+                    .desired_width(text_rect.width()),
+            );
+            if !response.has_focus() && !response.lost_focus() {
+                response.request_focus();
+            }
+            if self.comment.text != previous_text {
+                actions.push(CommentAction::Edited);
+            }
+            if response.lost_focus() {
+                actions.push(CommentAction::StopEditing);
+            }
+        } else {
+            let text_style = match self.comment.kind {
+                CommentKind::Frame => TextStyle::Button,
+                CommentKind::Note => TextStyle::Body,
+            };
+            let galley = ui.painter().layout(
+                self.comment.text.clone(),
+                text_style.resolve(ui.style()),
+                text_color,
+                text_rect.width(),
+            );
+            painter
+                .with_clip_rect(text_rect)
+                .galley(text_rect.min, galley);
+        }
+
+        // Header buttons: delete, and a swatch to change the color
+        if close_button(
+            self.style,
+            pan_zoom,
+            ui,
+            header_rect.translate(vec2(0.0, -3.0 * zoom)),
+        )
+        .clicked()
+        {
+            actions.push(CommentAction::Delete);
+        }
+        let swatch_rect = Rect::from_center_size(
+            pos2(header_rect.right() - 32.0 * zoom, header_rect.center().y),
+            Vec2::splat(10.0 * zoom),
+        );
+        let swatch = ui.interact(swatch_rect, self.id.with("color"), Sense::click());
+        painter.circle(
+            swatch_rect.center(),
+            5.0 * zoom,
+            color,
+            Stroke::new(1.0 * zoom, text_color),
+        );
+        if swatch.on_hover_text("Change color").clicked() {
+            actions.push(CommentAction::CycleColor);
+        }
+
+        // Resize handle in the bottom-right corner
+        let handle_rect = Rect::from_min_max(rect.max - Vec2::splat(12.0 * zoom), rect.max);
+        let handle = ui.interact(handle_rect, self.id.with("resize"), Sense::drag());
+        let handle_color = if handle.hovered() || handle.dragged() {
+            text_color
+        } else {
+            color
+        };
+        for i in 1..=2 {
+            let offset = 4.0 * zoom * i as f32;
+            painter.line_segment(
+                [
+                    pos2(rect.max.x - offset, rect.max.y - 2.0 * zoom),
+                    pos2(rect.max.x - 2.0 * zoom, rect.max.y - offset),
+                ],
+                Stroke::new(1.0 * zoom, handle_color),
+            );
+        }
+        if handle.drag_delta().length_sq() > 0.0 {
+            actions.push(CommentAction::Resize(handle.drag_delta()));
+        } else if body_response.drag_delta().length_sq() > 0.0 {
+            actions.push(CommentAction::Move(body_response.drag_delta()));
+        }
+
+        if body_response.double_clicked() {
+            actions.push(CommentAction::StartEditing);
+        } else if body_response.clicked_by(PointerButton::Primary) {
+            actions.push(CommentAction::Select);
+        }
+
+        (rect, actions)
+    }
+}
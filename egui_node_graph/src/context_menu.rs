@@ -0,0 +1,104 @@
+use egui::*;
+
+use crate::{Graph, InputId, NodeDataTrait, NodeId, OutputId, WireSegment};
+
+/// What a context menu was opened on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ContextMenuTarget {
+    Node(NodeId),
+    /// The wire from `output` to `input`. `segment` is the part of the wire
+    /// that was clicked, when knots can be inserted in it. A wire segment
+    /// shared by several inputs is reported with the first of them.
+    Wire {
+        output: OutputId,
+        input: InputId,
+        segment: Option<WireSegment>,
+    },
+    /// The background of the editor.
+    Canvas,
+}
+
+/// A context menu of the editor, opened by right-clicking a node, a wire or
+/// the background. Its entries are the built-in ones followed by the entries
+/// added by the hooks of [`NodeDataTrait`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ContextMenu {
+    pub target: ContextMenuTarget,
+    /// The top-left corner of the menu, on screen.
+    pub position: Pos2,
+}
+
+/// A built-in entry of a [`ContextMenu`] chosen by the user.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ContextMenuAction {
+    DeleteNode(NodeId),
+    DuplicateNode(NodeId),
+    CollapseNode(NodeId, bool),
+    DisconnectNode(NodeId),
+    DeleteWire { output: OutputId, input: InputId },
+    InsertReroute(WireSegment),
+    AddNode,
+}
+
+impl ContextMenu {
+    /// Draws the entries of the menu. `pan` converts screen positions to
+    /// node positions, and `collapsed` is whether the target node is
+    /// collapsed. Returns the built-in entry that was clicked, if any, and
+    /// the responses of the clicked user entries.
+    pub(crate) fn show<NodeData>(
+        &self,
+        ui: &mut Ui,
+        graph: &Graph<NodeData, NodeData::DataType, NodeData::ValueType>,
+        pan: Vec2,
+        collapsed: bool,
+        user_state: &mut NodeData::UserState,
+    ) -> (Option<ContextMenuAction>, Vec<NodeData::Response>)
+    where
+        NodeData: NodeDataTrait,
+    {
+        let mut action = None;
+        let responses = match self.target {
+            ContextMenuTarget::Node(node) => {
+                let Some(node_data) = graph.nodes.get(node).map(|n| &n.user_data) else {
+                    return (None, Vec::new());
+                };
+                if ui.button("Duplicate").clicked() {
+                    action = Some(ContextMenuAction::DuplicateNode(node));
+                }
+                let label = if collapsed { "Expand" } else { "Collapse" };
+                if ui.button(label).clicked() {
+                    action = Some(ContextMenuAction::CollapseNode(node, !collapsed));
+                }
+                if ui.button("Disconnect all").clicked() {
+                    action = Some(ContextMenuAction::DisconnectNode(node));
+                }
+                if node_data.can_delete(node, graph, user_state) && ui.button("Delete").clicked() {
+                    action = Some(ContextMenuAction::DeleteNode(node));
+                }
+                node_data.node_context_menu(ui, node, graph, user_state)
+            }
+            ContextMenuTarget::Wire {
+                output,
+                input,
+                segment,
+            } => {
+                if let Some(segment) = segment {
+                    if ui.button("Insert reroute").clicked() {
+                        action = Some(ContextMenuAction::InsertReroute(segment));
+                    }
+                }
+                if ui.button("Delete").clicked() {
+                    action = Some(ContextMenuAction::DeleteWire { output, input });
+                }
+                NodeData::wire_context_menu(ui, output, input, graph, user_state)
+            }
+            ContextMenuTarget::Canvas => {
+                if ui.button("Add node…").clicked() {
+                    action = Some(ContextMenuAction::AddNode);
+                }
+                NodeData::canvas_context_menu(ui, self.position - pan, graph, user_state)
+            }
+        };
+        (action, responses)
+    }
+}
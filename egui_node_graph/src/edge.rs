@@ -0,0 +1,167 @@
+use egui::{Painter, Pos2, Stroke};
+
+use super::*;
+
+/// Custom data attached to the connections of a [`GraphEditorState`], such as
+/// a label, a color or a breakpoint. Connections have no data until it is
+/// set with [`GraphEditorState::set_edge_data`]. The default `()` is used
+/// when connections carry nothing.
+pub trait EdgeDataTrait<UserState>: Clone {
+    /// Draws the wire of a connection carrying this data. The default draws
+    /// the regular wire. Call [`EdgeWire::paint`] and add decorations around
+    /// it, or replace it entirely.
+    fn draw_wire(&self, wire: &EdgeWire, painter: &Painter, _user_state: &mut UserState) {
+        wire.paint(painter);
+    }
+}
+
+impl<UserState> EdgeDataTrait<UserState> for () {}
+
+/// The wire of a connection, as passed to [`EdgeDataTrait::draw_wire`].
+/// Wires going through reroute knots are drawn one segment at a time, and
+/// this is the segment reaching the input.
+#[derive(Clone, Copy, Debug)]
+pub struct EdgeWire<'a> {
+    pub output: OutputId,
+    pub input: InputId,
+    /// The ends of the wire on screen.
+    pub src_pos: Pos2,
+    pub dst_pos: Pos2,
+    /// The stroke of the regular wire, already highlighted or dimmed along
+    /// with the nodes, and scaled with the zoom.
+    pub stroke: Stroke,
+    pub zoom: f32,
+    pub style: &'a GraphEditorStyle,
+}
+
+impl EdgeWire<'_> {
+    /// The path of the wire on screen, as a polyline. Useful to place
+    /// decorations along it.
+    pub fn points(&self) -> Vec<Pos2> {
+        wire_points(self.style, self.zoom, self.src_pos, self.dst_pos)
+    }
+
+    /// Draws the regular wire.
+    pub fn paint(&self, painter: &Painter) {
+        paint_wire(
+            self.style,
+            self.zoom,
+            painter,
+            self.src_pos,
+            self.dst_pos,
+            self.stroke,
+        );
+    }
+}
+
+impl<NodeData, DataType, ValueType, NodeTemplate, UserState, EdgeData>
+    GraphEditorState<NodeData, DataType, ValueType, NodeTemplate, UserState, EdgeData>
+{
+    /// The data attached to the connection from `output` to `input`.
+    pub fn edge_data(&self, output: OutputId, input: InputId) -> Option<&EdgeData> {
+        self.edge_data
+            .get(input)?
+            .iter()
+            .find(|(o, _)| *o == output)
+            .map(|(_, data)| data)
+    }
+
+    pub fn edge_data_mut(&mut self, output: OutputId, input: InputId) -> Option<&mut EdgeData> {
+        self.edge_data
+            .get_mut(input)?
+            .iter_mut()
+            .find(|(o, _)| *o == output)
+            .map(|(_, data)| data)
+    }
+
+    /// Attaches data to the connection from `output` to `input`. Returns the
+    /// data it replaces, if any.
+    pub fn set_edge_data(
+        &mut self,
+        output: OutputId,
+        input: InputId,
+        data: EdgeData,
+    ) -> Result<Option<EdgeData>, EguiGraphError> {
+        if !self.graph.connections(input).contains(&output) {
+            return Err(EguiGraphError::NoConnection(output, input));
+        }
+        let previous = self.remove_edge_data(output, input);
+        if let Some(entry) = self.edge_data.entry(input) {
+            entry.or_default().push((output, data));
+        }
+        Ok(previous)
+    }
+
+    /// Detaches the data of the connection from `output` to `input`, and
+    /// returns it.
+    pub fn remove_edge_data(&mut self, output: OutputId, input: InputId) -> Option<EdgeData> {
+        let connections = self.edge_data.get_mut(input)?;
+        let index = connections.iter().position(|(o, _)| *o == output)?;
+        let (_, data) = connections.remove(index);
+        if connections.is_empty() {
+            self.edge_data.remove(input);
+        }
+        Some(data)
+    }
+
+    /// Removes the data of the connections that no longer exist. Called by
+    /// the editor every frame, so user code can freely modify the graph
+    /// connections.
+    pub fn prune_edge_data(&mut self) {
+        let graph = &self.graph;
+        self.edge_data.retain(|input, connections| {
+            connections.retain(|(output, _)| graph.connections(input).contains(output));
+            !connections.is_empty()
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    #[test]
+    fn test_edge_data() {
+        let mut state = TestState::<&str>::default();
+        let mut add_node = || {
+            let node = state.graph.add_node("node".into(), (), |_, _| {});
+            let input = state.graph.add_wide_input_param(
+                node,
+                "in".into(),
+                TestType::Data,
+                0,
+                InputParamKind::ConnectionOnly,
+                None,
+                true,
+            );
+            (
+                input,
+                state
+                    .graph
+                    .add_output_param(node, "out".into(), TestType::Data),
+            )
+        };
+        let (a_in, a_out) = add_node();
+        let (_, b_out) = add_node();
+
+        assert!(state.set_edge_data(a_out, a_in, "loop").is_err());
+        state.graph.add_connection(a_out, a_in);
+        state.graph.add_connection(b_out, a_in);
+        assert_eq!(state.set_edge_data(a_out, a_in, "loop").unwrap(), None);
+        assert_eq!(state.set_edge_data(b_out, a_in, "b").unwrap(), None);
+        assert_eq!(
+            state.set_edge_data(a_out, a_in, "self").unwrap(),
+            Some("loop")
+        );
+        assert_eq!(state.edge_data(a_out, a_in), Some(&"self"));
+        assert_eq!(state.edge_data(b_out, a_in), Some(&"b"));
+
+        // The data goes away with the connection
+        state.graph.remove_connection(a_in, a_out);
+        state.prune_edge_data();
+        assert_eq!(state.edge_data(a_out, a_in), None);
+        assert_eq!(state.remove_edge_data(b_out, a_in), Some("b"));
+        assert!(state.edge_data.is_empty());
+    }
+}
@@ -1,16 +1,17 @@
 use std::collections::HashSet;
 
-use crate::color_hex_utils::*;
-use crate::utils::ColorUtils;
-
 use super::*;
-use egui::epaint::{CubicBezierShape, RectShape};
+use egui::epaint::RectShape;
 use egui::*;
 
 pub type PortLocations = std::collections::HashMap<AnyParameterId, Pos2>;
 pub type NodeRects = std::collections::HashMap<NodeId, Rect>;
+type GroupRects = std::collections::HashMap<GroupId, Rect>;
+type CommentRects = std::collections::HashMap<CommentId, Rect>;
 
 const DISTANCE_TO_CONNECT: f32 = 10.0;
+/// The size of the cells of the spatial indices, at a zoom level of 1.
+const INDEX_CELL_SIZE: f32 = 100.0;
 
 /// Nodes communicate certain events to the parent graph when drawn. There is
 /// one special `User` variant which can be used by users as the return value
@@ -23,6 +24,11 @@ pub enum NodeResponse<UserResponse: UserResponseTrait, NodeData: NodeDataTrait>
         input: InputId,
         node_input: NodeId,
         node_output: NodeId,
+        /// Set when the connection was accepted by
+        /// [`DataTypeTrait::connection_compatibility`] with a
+        /// [`ConnectionCompatibility::Converted`] result. User code can use it
+        /// to insert the conversion when evaluating the graph.
+        conversion: Option<String>,
     },
     CreatedNode(NodeId),
     SelectNode(NodeId),
@@ -46,6 +52,22 @@ pub enum NodeResponse<UserResponse: UserResponseTrait, NodeData: NodeDataTrait>
         node: NodeId,
         drag_delta: Vec2,
     },
+    /// Emitted when the arrow in the title bar of a node is clicked, to
+    /// collapse the node to its title bar or expand it back.
+    CollapseNode {
+        node: NodeId,
+        collapsed: bool,
+    },
+    /// Emitted while the bottom-right corner of a node is dragged.
+    ResizeNode {
+        node: NodeId,
+        drag_delta: Vec2,
+    },
+    /// Emitted when the inline widget of an input parameter changes its value.
+    ValueChanged {
+        node: NodeId,
+        input: InputId,
+    },
     User(UserResponse),
 }
 
@@ -60,7 +82,7 @@ pub struct GraphResponse<UserResponse: UserResponseTrait, NodeData: NodeDataTrai
     /// finder is considered part of the graph editor, even when it floats
     /// outside the graph editor rect.
     pub cursor_in_editor: bool,
-    /// Is the mouse currently hovering the node finder?
+    /// Is the mouse currently hovering the node finder or a context menu?
     pub cursor_in_finder: bool,
 }
 impl<UserResponse: UserResponseTrait, NodeData: NodeDataTrait> Default
@@ -83,20 +105,35 @@ pub struct GraphNodeWidget<'a, NodeData, DataType, ValueType> {
     pub ongoing_drag: Option<(NodeId, AnyParameterId)>,
     pub selected: bool,
     pub pan: egui::Vec2,
+    pub style: &'a GraphEditorStyle,
+    pub view: NodeView,
+    /// The outputs with at least one connection, drawn filled.
+    pub connected_outputs: &'a HashSet<OutputId>,
 }
 
-impl<NodeData, DataType, ValueType, NodeTemplate, UserResponse, UserState, CategoryType>
-    GraphEditorState<NodeData, DataType, ValueType, NodeTemplate, UserState>
+impl<
+        NodeData,
+        DataType,
+        ValueType,
+        NodeTemplate,
+        UserResponse,
+        UserState,
+        CategoryType,
+        EdgeData,
+    > GraphEditorState<NodeData, DataType, ValueType, NodeTemplate, UserState, EdgeData>
 where
     NodeData: NodeDataTrait<
-        Response = UserResponse,
-        UserState = UserState,
-        DataType = DataType,
-        ValueType = ValueType,
-    >,
+            Response = UserResponse,
+            UserState = UserState,
+            DataType = DataType,
+            ValueType = ValueType,
+        > + Clone
+        + ClipboardData,
     UserResponse: UserResponseTrait,
-    ValueType:
-        WidgetValueTrait<Response = UserResponse, UserState = UserState, NodeData = NodeData>,
+    ValueType: WidgetValueTrait<Response = UserResponse, UserState = UserState, NodeData = NodeData>
+        + Clone
+        + PartialEq
+        + ClipboardData,
     NodeTemplate: NodeTemplateTrait<
         NodeData = NodeData,
         DataType = DataType,
@@ -104,8 +141,9 @@ where
         UserState = UserState,
         CategoryType = CategoryType,
     >,
-    DataType: DataTypeTrait<UserState>,
+    DataType: DataTypeTrait<UserState> + Clone + ClipboardData,
     CategoryType: CategoryTrait,
+    EdgeData: EdgeDataTrait<UserState> + ClipboardData,
 {
     #[must_use]
     pub fn draw_graph_editor(
@@ -115,6 +153,32 @@ where
         user_state: &mut UserState,
         prepend_responses: Vec<NodeResponse<UserResponse, NodeData>>,
     ) -> GraphResponse<UserResponse, NodeData> {
+        // Record the step performed during the last frame, now that user code
+        // had a chance to react to it.
+        self.commit_frame_undo_step();
+
+        if self.step_layout_animation(ui.input(|i| i.stable_dt)) {
+            ui.ctx().request_repaint();
+        }
+
+        // Text fields have their own undo, so shortcuts are only handled when
+        // no widget has keyboard focus.
+        if ui.memory(|mem| mem.focus().is_none()) {
+            let (undo, redo) = ui.input(|i| {
+                let command = i.modifiers.command;
+                let z = command && i.key_pressed(Key::Z);
+                (
+                    z && !i.modifiers.shift,
+                    (z && i.modifiers.shift) || (command && i.key_pressed(Key::Y)),
+                )
+            });
+            if undo {
+                self.undo();
+            } else if redo {
+                self.redo();
+            }
+        }
+
         let clip_rect = ui.clip_rect();
         // Zoom may have never taken place, so ensure we use parent style
         if !self.pan_zoom.started {
@@ -137,6 +201,10 @@ where
             self.draw_graph_editor_inside_zoom(ui, all_kinds, user_state, prepend_responses)
         });
 
+        if self.current_group.is_some() {
+            self.draw_breadcrumb(ui, clip_rect);
+        }
+
         graph_response
     }
 
@@ -169,6 +237,43 @@ where
             *node_pos = scaled_local_pos + half_size - self.pan_zoom.pan;
             // This way we can retain pan untouched when zooming :)
         }
+        // Nodes outside of the editor are drawn from their last known size
+        for size in self.node_sizes.values_mut() {
+            *size *= zoom_delta;
+        }
+        for ports in self.port_offsets.values_mut() {
+            for (_, offset) in ports {
+                *offset *= zoom_delta;
+            }
+        }
+        for view in self.node_views.values_mut() {
+            if let Some(size) = &mut view.size {
+                *size *= zoom_delta;
+            }
+        }
+        for group in self.groups.groups.values_mut() {
+            let local_pos = group.position.to_vec2() - half_size + self.pan_zoom.pan;
+            let scaled_local_pos = (local_pos * zoom_delta).to_pos2();
+            group.position = scaled_local_pos + half_size - self.pan_zoom.pan;
+        }
+        for comment in self.comments.values_mut() {
+            let local_pos = comment.position.to_vec2() - half_size + self.pan_zoom.pan;
+            let scaled_local_pos = (local_pos * zoom_delta).to_pos2();
+            comment.position = scaled_local_pos + half_size - self.pan_zoom.pan;
+            comment.size *= zoom_delta;
+        }
+        for knot in self.reroutes.knots.values_mut() {
+            let local_pos = knot.position.to_vec2() - half_size + self.pan_zoom.pan;
+            let scaled_local_pos = (local_pos * zoom_delta).to_pos2();
+            knot.position = scaled_local_pos + half_size - self.pan_zoom.pan;
+        }
+        if let Some(animation) = &mut self.layout_animation {
+            for pos in animation.from.values_mut().chain(animation.to.values_mut()) {
+                let local_pos = pos.to_vec2() - half_size + self.pan_zoom.pan;
+                let scaled_local_pos = (local_pos * zoom_delta).to_pos2();
+                *pos = scaled_local_pos + half_size - self.pan_zoom.pan;
+            }
+        }
     }
 
     fn draw_graph_editor_inside_zoom(
@@ -190,6 +295,9 @@ where
         let mut cursor_in_editor = resp.hovered();
         let mut cursor_in_finder = false;
 
+        let minimap_rect = self.minimap.rect(editor_rect);
+        let cursor_in_minimap = matches!(minimap_rect, Some(rect) if rect.contains(cursor_pos));
+
         // Gets filled with the node metrics as they are drawn
         let mut port_locations = PortLocations::new();
         let mut node_rects = NodeRects::new();
@@ -198,6 +306,33 @@ where
         // executed at the end of this function.
         let mut delayed_responses: Vec<NodeResponse<UserResponse, NodeData>> = prepend_responses;
 
+        // Offset from node coordinates to screen coordinates
+        let pan = self.pan_zoom.pan + editor_rect.min.to_vec2();
+
+        let style = self
+            .style
+            .unwrap_or_else(|| GraphEditorStyle::from_visuals(ui.visuals()));
+
+        // User code may have removed connections since the last frame.
+        self.prune_reroutes();
+        self.prune_edge_data();
+        self.update_group_ports();
+
+        // Like undo, clipboard shortcuts are left to the focused widget if any.
+        if ui.memory(|mem| mem.focus().is_none()) {
+            let paste_position = if resp.hovered() {
+                cursor_pos
+            } else {
+                editor_rect.center()
+            };
+            self.handle_clipboard_events(
+                ui,
+                paste_position - self.pan_zoom.pan - editor_rect.min.to_vec2(),
+                &mut delayed_responses,
+            );
+            self.handle_group_shortcuts(ui);
+        }
+
         // Used to detect when the background was clicked
         let mut click_on_background = false;
 
@@ -223,27 +358,247 @@ where
             drag_released_on_background = true;
         }
 
+        draw_grid(
+            ui.painter(),
+            editor_rect,
+            pan,
+            style.grid_spacing * self.pan_zoom.zoom,
+            style.grid_color,
+        );
+
+        /* Draw comments, beneath everything else */
+        let mut comment_rects = CommentRects::new();
+        let mut comment_actions = Vec::new();
+        // Frames are drawn below notes, and larger frames below smaller ones.
+        let mut visible_comments: Vec<(CommentId, CommentKind, f32)> = self
+            .comments
+            .iter()
+            .filter(|(_, comment)| comment.group == self.current_group)
+            .map(|(id, comment)| (id, comment.kind, comment.size.x * comment.size.y))
+            .collect();
+        visible_comments.sort_by(|a, b| {
+            let a_is_note = a.1 == CommentKind::Note;
+            let b_is_note = b.1 == CommentKind::Note;
+            a_is_note.cmp(&b_is_note).then(b.2.total_cmp(&a.2))
+        });
+        for (comment_id, _, _) in visible_comments {
+            let (rect, actions) = CommentWidget {
+                id: Id::new((comment_id, "comment")),
+                comment: &mut self.comments[comment_id],
+                pan: self.pan_zoom.pan + editor_rect.min.to_vec2(),
+                selected: self.selected_comments.contains(&comment_id),
+                editing: self.editing_comment == Some(comment_id),
+                style: &style,
+            }
+            .show(&self.pan_zoom, ui);
+            comment_rects.insert(comment_id, rect);
+            comment_actions.extend(actions.into_iter().map(|action| (comment_id, action)));
+        }
+
+        // Looking up the connections of each output port is too slow.
+        let connected_outputs: HashSet<OutputId> = self
+            .graph
+            .iter_connections()
+            .map(|(_, output)| output)
+            .collect();
+
+        /* Draw group nodes */
+        let mut group_rects = GroupRects::new();
+        let mut group_actions = Vec::new();
+        let visible_groups: Vec<GroupId> = self.groups.children(self.current_group).collect();
+        for group_id in visible_groups {
+            let (responses, actions) = GroupNodeWidget {
+                graph: &self.graph,
+                group_id,
+                group: &self.groups.groups[group_id],
+                port_locations: &mut port_locations,
+                group_rects: &mut group_rects,
+                ongoing_drag: self.connection_in_progress,
+                selected: self.selected_groups.contains(&group_id),
+                pan: self.pan_zoom.pan + editor_rect.min.to_vec2(),
+                style: &style,
+                connected_outputs: &connected_outputs,
+            }
+            .show(&self.pan_zoom, ui, user_state);
+
+            delayed_responses.extend(responses);
+            group_actions.extend(actions.into_iter().map(|action| (group_id, action)));
+        }
+
         /* Draw nodes */
+        // Nodes outside of the editor are culled: they keep the rect and the
+        // ports they had when they were last drawn. At low zoom levels, nodes
+        // are drawn as plain boxes. Nodes that were never drawn are always
+        // drawn whole once.
+        let visible_rect = ui
+            .clip_rect()
+            .expand(2.0 * style.port_radius * self.pan_zoom.zoom);
+        let simplified = self.pan_zoom.zoom < style.simplified_zoom;
         for node_id in self.node_order.iter().copied() {
-            let responses = GraphNodeWidget {
+            // Nodes inside a group are only drawn while editing that group
+            if self.groups.group_of(node_id) != self.current_group {
+                continue;
+            }
+            let position = self.node_positions[node_id] + pan;
+            let cached = self
+                .node_sizes
+                .get(node_id)
+                .copied()
+                .zip(self.port_offsets.get(node_id));
+            if let Some((size, ports)) = cached {
+                let rect = Rect::from_min_size(position, size);
+                if !rect.intersects(visible_rect) {
+                    node_rects.insert(node_id, rect);
+                    port_locations.extend(
+                        ports
+                            .iter()
+                            .map(|(port, offset)| (*port, position + *offset)),
+                    );
+                    continue;
+                }
+            }
+            let widget = GraphNodeWidget {
                 position: self.node_positions.get_mut(node_id).unwrap(),
                 graph: &mut self.graph,
                 port_locations: &mut port_locations,
                 node_rects: &mut node_rects,
                 node_id,
                 ongoing_drag: self.connection_in_progress,
-                selected: self
-                    .selected_nodes
-                    .iter()
-                    .any(|selected| *selected == node_id),
+                selected: self.selected_nodes.contains(&node_id),
                 pan: self.pan_zoom.pan + editor_rect.min.to_vec2(),
+                style: &style,
+                view: self.node_views.get(node_id).copied().unwrap_or_default(),
+                connected_outputs: &connected_outputs,
+            };
+            if let Some((size, ports)) = cached.filter(|_| simplified) {
+                let responses = widget.show_simplified(&self.pan_zoom, ui, user_state, size, ports);
+                delayed_responses.extend(responses);
+                continue;
             }
-            .show(&self.pan_zoom, ui, user_state);
+            let responses = widget.show(&self.pan_zoom, ui, user_state);
 
             // Actions executed later
             delayed_responses.extend(responses);
+
+            // Remember where the ports are, for when the node is culled
+            let node = &self.graph[node_id];
+            let ports = node
+                .input_ids()
+                .map(AnyParameterId::Input)
+                .chain(node.output_ids().map(AnyParameterId::Output))
+                .filter_map(|port| Some((port, *port_locations.get(&port)? - position)))
+                .collect();
+            self.port_offsets.insert(node_id, ports);
+        }
+
+        for (node_id, rect) in &node_rects {
+            self.node_sizes.insert(*node_id, rect.size());
+        }
+
+        // Spatial indices of the visible nodes and ports, to find the ones
+        // near the cursor without testing all of them. Nodes are inserted in
+        // drawing order, so the topmost node is found first.
+        let mut node_index = SpatialIndex::new(INDEX_CELL_SIZE * self.pan_zoom.zoom);
+        for node_id in &self.node_order {
+            match node_rects.get(node_id) {
+                Some(rect) if rect.intersects(visible_rect) => node_index.insert(*node_id, *rect),
+                _ => {}
+            }
+        }
+        let mut port_index = SpatialIndex::new(INDEX_CELL_SIZE * self.pan_zoom.zoom);
+        for (port, pos) in &port_locations {
+            if visible_rect.contains(*pos) {
+                port_index.insert(*port, Rect::from_min_max(*pos, *pos));
+            }
+        }
+
+        // A wire being dragged goes to the nearest port accepting it. When no
+        // port in reach accepts it, the nearest one tells why. Self-loops are
+        // never allowed, and the ports of collapsed nodes only show where
+        // their wires go.
+        let max_port_distance = DISTANCE_TO_CONNECT * self.pan_zoom.zoom;
+        let mut connection_target = None;
+        let origin = self.connection_in_progress;
+        let graph = &self.graph;
+        let reachable = |port: AnyParameterId| {
+            let node = match port {
+                AnyParameterId::Input(input) => graph[input].node,
+                AnyParameterId::Output(output) => graph[output].node,
+            };
+            !self.is_node_collapsed(node)
+                && !matches!(origin, Some((origin_node, _)) if origin_node == node)
+        };
+        if let Some((_, origin_port)) = origin {
+            connection_target = port_index
+                .nearest(cursor_pos, max_port_distance, |port| {
+                    reachable(port)
+                        && matches!(
+                            connection_compatibility(graph, origin_port, port, user_state),
+                            Some((_, _, compatibility)) if compatibility.is_allowed()
+                        )
+                })
+                .or_else(|| port_index.nearest(cursor_pos, max_port_distance, reachable))
+                .and_then(|(port, rect)| {
+                    let compatibility =
+                        connection_compatibility(graph, origin_port, port, user_state)?;
+                    Some((port, rect.center(), compatibility))
+                });
+        }
+        // Without a wire, the port under the cursor is highlighted.
+        let highlighted_port = match &connection_target {
+            Some((port, pos, (_, _, compatibility))) => {
+                Some((*port, *pos)).filter(|_| compatibility.is_allowed())
+            }
+            None if origin.is_none() => port_index
+                .nearest(cursor_pos, max_port_distance, reachable)
+                .map(|(port, rect)| (port, rect.center())),
+            None => None,
+        };
+        if let Some((port, pos)) = highlighted_port {
+            if let Ok(port_type) = graph.any_param_type(port) {
+                port_type.port_shape(user_state).paint(
+                    ui.painter(),
+                    pos,
+                    style.port_radius * self.pan_zoom.zoom,
+                    style.port_highlight,
+                    true,
+                );
+            }
+        }
+        if let Some((_, _, (output, input, compatibility))) = &connection_target {
+            let hint = match compatibility {
+                ConnectionCompatibility::Allowed => None,
+                ConnectionCompatibility::Converted(conversion) => Some(conversion),
+                ConnectionCompatibility::Rejected(reason) => Some(reason),
+            };
+            // Ports at their connection limit give up a connection.
+            let replaced = compatibility.is_allowed()
+                && !graph.replaced_connections(*output, *input).is_empty();
+            if hint.is_some() || replaced {
+                show_tooltip_at_pointer(ui.ctx(), Id::new("connection_compatibility"), |ui| {
+                    if let Some(hint) = hint {
+                        ui.label(hint);
+                    }
+                    if replaced {
+                        ui.label("Replaces an existing connection");
+                    }
+                });
+            }
+            if compatibility.is_allowed() && ui.input(|i| i.pointer.any_released()) {
+                delayed_responses.push(NodeResponse::ConnectEventEnded {
+                    output: *output,
+                    input: *input,
+                    node_input: graph[*input].node,
+                    node_output: graph[*output].node,
+                    conversion: compatibility.conversion().map(str::to_owned),
+                });
+            }
         }
 
+        // The node created by the node finder during the last frame, if any.
+        // It has been drawn by now.
+        let created_node = self.pending_splice.take();
+
         /* Draw the node finder, if open */
         let mut should_close_node_finder = false;
         if let Some(ref mut node_finder) = self.node_finder {
@@ -252,7 +607,14 @@ where
                 node_finder_area = node_finder_area.current_pos(pos);
             }
             node_finder_area.show(ui.ctx(), |ui| {
-                if let Some(node_kind) = node_finder.show(ui, all_kinds, user_state) {
+                if let Some(node_kind) = node_finder.show(
+                    ui,
+                    &style,
+                    all_kinds,
+                    &self.recent_templates,
+                    &self.graph,
+                    user_state,
+                ) {
                     let new_node = self.graph.add_node(
                         node_kind.node_graph_label(user_state),
                         node_kind.user_data(user_state),
@@ -265,9 +627,47 @@ where
                             - editor_rect.min.to_vec2(),
                     );
                     self.node_order.push(new_node);
+                    self.groups.set_group(new_node, self.current_group);
 
                     should_close_node_finder = true;
                     delayed_responses.push(NodeResponse::CreatedNode(new_node));
+
+                    let label = node_kind.node_finder_label(user_state).into_owned();
+                    self.recent_templates
+                        .retain(|template| template.node_finder_label(user_state) != label);
+                    self.recent_templates.insert(0, node_kind.clone());
+                    self.recent_templates.truncate(MAX_RECENT_TEMPLATES);
+
+                    // Connect the node to the wire the finder was opened from
+                    if let Some((_, port)) = node_finder.pending_connection {
+                        let connection = self.graph.any_param_type(port).ok().and_then(|typ| {
+                            find_compatible_port(&self.graph, new_node, port, typ, user_state)
+                        });
+                        let connection = match (port, connection) {
+                            (
+                                AnyParameterId::Output(output),
+                                Some((AnyParameterId::Input(input), compatibility)),
+                            )
+                            | (
+                                AnyParameterId::Input(input),
+                                Some((AnyParameterId::Output(output), compatibility)),
+                            ) => Some((output, input, compatibility)),
+                            _ => None,
+                        };
+                        if let Some((output, input, compatibility)) = connection {
+                            delayed_responses.push(NodeResponse::ConnectEventEnded {
+                                output,
+                                input,
+                                node_input: self.graph[input].node,
+                                node_output: self.graph[output].node,
+                                conversion: compatibility.conversion().map(str::to_owned),
+                            });
+                        }
+                    } else {
+                        // Created over a wire, the node is inserted in it
+                        // once its size is known.
+                        self.pending_splice = Some(new_node);
+                    }
                 }
                 let finder_rect = ui.min_rect();
                 // If the cursor is not in the main editor, check if the cursor is in the finder
@@ -282,99 +682,451 @@ where
             self.node_finder = None;
         }
 
+        /* Draw the context menu, if open */
+        let mut context_menu_action = None;
+        if let Some(menu) = self.context_menu {
+            let collapsed = match menu.target {
+                ContextMenuTarget::Node(node) => self.is_node_collapsed(node),
+                _ => false,
+            };
+            let menu_response = Area::new("context_menu")
+                .order(Order::Foreground)
+                .fixed_pos(menu.position)
+                .show(ui.ctx(), |ui| {
+                    Frame::menu(ui.style())
+                        .show(ui, |ui| {
+                            menu.show(ui, &self.graph, pan, collapsed, user_state)
+                        })
+                        .inner
+                });
+            let (action, responses) = menu_response.inner;
+            let menu_rect = menu_response.response.rect;
+            // Like the node finder, the menu is part of the editor.
+            if menu_rect.contains(cursor_pos) {
+                cursor_in_editor = true;
+                cursor_in_finder = true;
+            }
+            let pressed_outside =
+                ui.input(|i| i.pointer.any_pressed()) && !menu_rect.contains(cursor_pos);
+            if action.is_some()
+                || !responses.is_empty()
+                || pressed_outside
+                || ui.input(|i| i.key_pressed(Key::Escape))
+            {
+                self.context_menu = None;
+            }
+            delayed_responses.extend(responses.into_iter().map(NodeResponse::User));
+            context_menu_action = action.map(|action| (menu, action));
+        }
+
+        /* Handle the reroute knots. They are drawn after the connections. */
+        let knot_radius = 5.0 * self.pan_zoom.zoom;
+        let mut knot_positions = std::collections::HashMap::new();
+        let mut hovered_knot = None;
+        let mut removed_knot = None;
+        let visible_knots: Vec<RerouteId> = self
+            .reroutes
+            .knots
+            .iter()
+            .filter(|(_, knot)| knot.group == self.current_group)
+            .map(|(id, _)| id)
+            .collect();
+        for knot_id in visible_knots {
+            let knot = &mut self.reroutes.knots[knot_id];
+            let knot_rect =
+                Rect::from_center_size(knot.position + pan, Vec2::splat(knot_radius * 3.0));
+            let response = ui.interact(
+                knot_rect,
+                Id::new((knot_id, "reroute")),
+                Sense::click_and_drag(),
+            );
+            if response.hovered() || response.dragged() {
+                hovered_knot = Some(knot_id);
+            }
+            if response.double_clicked() {
+                removed_knot = Some(knot_id);
+            } else if response.drag_started() && ui.input(|i| i.modifiers.shift) {
+                // Shift-dragging a knot starts a new wire from it
+                if let Some(source) = self.graph.try_get_output(knot.source) {
+                    delayed_responses.push(NodeResponse::ConnectEventStarted(
+                        source.node,
+                        AnyParameterId::Output(knot.source),
+                    ));
+                    self.reroute_drag = Some(knot_id);
+                }
+            } else if response.dragged() && self.reroute_drag.is_none() {
+                knot.position += response.drag_delta();
+                self.history
+                    .defer("Move nodes", Some(UndoMergeKey::MoveNodes));
+            }
+            knot_positions.insert(knot_id, knot.position + pan);
+        }
+        if let Some(knot) = removed_knot {
+            self.remove_reroute(knot);
+            knot_positions.remove(&knot);
+            self.history.defer("Remove reroute", None);
+        }
+
         /* Draw connections */
-        if let Some((_, ref locator)) = self.connection_in_progress {
+        // The port a connection is dragged from may not be shown when it
+        // was disconnected from a node outside of the current group. Wires
+        // dragged from a knot start at the knot.
+        let dragged_port = self.connection_in_progress.and_then(|(_, locator)| {
+            let start_pos = match self.reroute_drag {
+                Some(knot) => knot_positions.get(&knot),
+                None => port_locations.get(&locator),
+            };
+            Some((locator, *start_pos?))
+        });
+        if let Some((ref locator, start_pos)) = dragged_port {
             let port_type = self.graph.any_param_type(*locator).unwrap();
             let connection_color = port_type.data_type_color(user_state);
-            let start_pos = port_locations[locator];
-
-            // Find a port to connect to
-            fn snap_to_ports<
-                NodeData,
-                UserState,
-                DataType: DataTypeTrait<UserState>,
-                ValueType,
-                Key: slotmap::Key + Into<AnyParameterId>,
-                Value,
-            >(
-                pan_zoom: &PanZoom,
-                graph: &Graph<NodeData, DataType, ValueType>,
-                port_type: &DataType,
-                ports: &SlotMap<Key, Value>,
-                port_locations: &PortLocations,
-                cursor_pos: Pos2,
-            ) -> Pos2 {
-                ports
-                    .iter()
-                    .find_map(|(port_id, _)| {
-                        let compatible_ports = graph
-                            .any_param_type(port_id.into())
-                            .map(|other| other == port_type)
-                            .unwrap_or(false);
-
-                        if compatible_ports {
-                            port_locations.get(&port_id.into()).and_then(|port_pos| {
-                                if port_pos.distance(cursor_pos) < DISTANCE_TO_CONNECT * pan_zoom.zoom {
-                                    Some(*port_pos)
-                                } else {
-                                    None
-                                }
-                            })
-                        } else {
-                            None
-                        }
-                    })
-                    .unwrap_or(cursor_pos)
-            }
+
+            // The wire snaps to the port it would connect to
+            let snapped_pos = match &connection_target {
+                Some((_, port_pos, (_, _, compatibility))) if compatibility.is_allowed() => {
+                    *port_pos
+                }
+                _ => cursor_pos,
+            };
 
             let (src_pos, dst_pos) = match locator {
-                AnyParameterId::Output(_) => (
-                    start_pos,
-                    snap_to_ports(
-                        &self.pan_zoom,
-                        &self.graph,
-                        port_type,
-                        &self.graph.inputs,
-                        &port_locations,
-                        cursor_pos,
-                    ),
-                ),
-                AnyParameterId::Input(_) => (
-                    snap_to_ports(
-                        &self.pan_zoom,
-                        &self.graph,
-                        port_type,
-                        &self.graph.outputs,
-                        &port_locations,
-                        cursor_pos,
-                    ),
-                    start_pos,
-                ),
+                AnyParameterId::Output(_) => (start_pos, snapped_pos),
+                AnyParameterId::Input(_) => (snapped_pos, start_pos),
             };
             draw_connection(
+                &style,
                 &self.pan_zoom,
                 ui.painter(),
                 src_pos,
                 dst_pos,
                 connection_color,
+                WireEmphasis::Normal,
             );
         }
 
-        for (input, output) in self.graph.iter_connections() {
+        // While the node finder is open for a dropped wire, the wire goes to
+        // the finder.
+        if let Some(NodeFinder {
+            pending_connection: Some((_, port)),
+            position: Some(finder_pos),
+            ..
+        }) = &self.node_finder
+        {
+            if let (Some(port_pos), Ok(port_type)) =
+                (port_locations.get(port), self.graph.any_param_type(*port))
+            {
+                let (src_pos, dst_pos) = match port {
+                    AnyParameterId::Output(_) => (*port_pos, *finder_pos),
+                    AnyParameterId::Input(_) => (*finder_pos, *port_pos),
+                };
+                draw_connection(
+                    &style,
+                    &self.pan_zoom,
+                    ui.painter(),
+                    src_pos,
+                    dst_pos,
+                    port_type.data_type_color(user_state),
+                    WireEmphasis::Normal,
+                );
+            }
+        }
+
+        // The wires of the selected nodes and of the node under the cursor
+        // are highlighted, and the other wires dimmed.
+        let hovered_node = node_index
+            .topmost_at(cursor_pos)
+            .filter(|_| cursor_in_editor && !cursor_in_finder);
+        let focused_nodes: HashSet<NodeId> = self
+            .selected_nodes
+            .iter()
+            .copied()
+            .chain(hovered_node)
+            .collect();
+        let mut focused_knots = HashSet::new();
+        for (input, _) in &self.reroutes.routes {
+            if focused_nodes.contains(&self.graph[input].node) {
+                focused_knots.extend(self.reroutes.route(input));
+            }
+        }
+        let flow_time = ui.input(|i| i.time);
+        let mut flow_drawn = false;
+
+        // Connections are drawn one segment at a time, each segment going
+        // from the previous knot (or the output) to a knot or an input.
+        let mut drawn_segments = Vec::new();
+        let segments: Vec<(WireSegment, OutputId)> = self
+            .graph
+            .iter_connections()
+            .map(|(input, output)| (WireSegment::Input(input), output))
+            .chain(
+                self.reroutes
+                    .knots
+                    .iter()
+                    .map(|(knot, reroute)| (WireSegment::Knot(knot), reroute.source)),
+            )
+            .collect();
+        for (segment, output) in segments {
             let port_type = self
                 .graph
                 .any_param_type(AnyParameterId::Output(output))
                 .unwrap();
             let connection_color = port_type.data_type_color(user_state);
-            let src_pos = port_locations[&AnyParameterId::Output(output)];
-            let dst_pos = port_locations[&AnyParameterId::Input(input)];
-            draw_connection(
-                &self.pan_zoom,
-                ui.painter(),
-                src_pos,
-                dst_pos,
-                connection_color,
-            );
+            // Only one of the wires going into an input can go through knots.
+            let rerouted = match segment {
+                WireSegment::Input(input) => self.rerouted_source(input) == Some(output),
+                WireSegment::Knot(_) => true,
+            };
+            let previous = self.reroutes.previous(segment).filter(|_| rerouted);
+            let src_pos = match previous {
+                Some(previous) => knot_positions.get(&previous),
+                None => port_locations.get(&AnyParameterId::Output(output)),
+            };
+            let dst_pos = match segment {
+                WireSegment::Knot(knot) => match knot_positions.get(&knot) {
+                    Some(pos) => Some(pos),
+                    // Knots of other groups are not shown.
+                    None => continue,
+                },
+                WireSegment::Input(input) => port_locations.get(&AnyParameterId::Input(input)),
+            };
+            // Connections to nodes outside the group being edited are drawn
+            // as a short stub leaving the visible port.
+            let stub = Vec2::X * 40.0 * self.pan_zoom.zoom;
+            let (src_pos, dst_pos, connection_color) = match (src_pos, dst_pos) {
+                (Some(src_pos), Some(dst_pos)) => (*src_pos, *dst_pos, connection_color),
+                (Some(src_pos), None) => (
+                    *src_pos,
+                    *src_pos + stub,
+                    connection_color.linear_multiply(0.4),
+                ),
+                (None, Some(dst_pos)) => (
+                    *dst_pos - stub,
+                    *dst_pos,
+                    connection_color.linear_multiply(0.4),
+                ),
+                (None, None) => continue,
+            };
+            // Wires outside of the editor are not drawn
+            if !wire_bounds(&style, self.pan_zoom.zoom, src_pos, dst_pos).intersects(visible_rect) {
+                continue;
+            }
+            let emphasis = if focused_nodes.is_empty() {
+                WireEmphasis::Normal
+            } else if focused_nodes.contains(&self.graph[output].node)
+                || match segment {
+                    WireSegment::Input(input) => focused_nodes.contains(&self.graph[input].node),
+                    WireSegment::Knot(knot) => focused_knots.contains(&knot),
+                }
+            {
+                WireEmphasis::Highlighted
+            } else {
+                WireEmphasis::Dimmed
+            };
+            let edge_data = match segment {
+                WireSegment::Input(input) => {
+                    self.edge_data(output, input).map(|data| (input, data))
+                }
+                WireSegment::Knot(_) => None,
+            };
+            match edge_data {
+                Some((input, data)) => {
+                    let wire = EdgeWire {
+                        output,
+                        input,
+                        src_pos,
+                        dst_pos,
+                        stroke: wire_stroke(&style, &self.pan_zoom, connection_color, emphasis),
+                        zoom: self.pan_zoom.zoom,
+                        style: &style,
+                    };
+                    data.draw_wire(&wire, ui.painter(), user_state);
+                }
+                None => draw_connection(
+                    &style,
+                    &self.pan_zoom,
+                    ui.painter(),
+                    src_pos,
+                    dst_pos,
+                    connection_color,
+                    emphasis,
+                ),
+            }
+            if emphasis != WireEmphasis::Dimmed && port_type.animated_flow(user_state) {
+                draw_connection_flow(
+                    &style,
+                    &self.pan_zoom,
+                    ui.painter(),
+                    src_pos,
+                    dst_pos,
+                    flow_time,
+                );
+                flow_drawn = true;
+            }
+            drawn_segments.push((segment, output, rerouted, src_pos, dst_pos));
+        }
+        if flow_drawn {
+            ui.ctx().request_repaint();
+        }
+
+        for (knot_id, knot_pos) in &knot_positions {
+            let source = self.reroutes.knots[*knot_id].source;
+            let color = self
+                .graph
+                .any_param_type(AnyParameterId::Output(source))
+                .unwrap()
+                .data_type_color(user_state);
+            let stroke = if hovered_knot == Some(*knot_id) {
+                Stroke::new(2.0 * self.pan_zoom.zoom, style.port_highlight)
+            } else {
+                Stroke::NONE
+            };
+            ui.painter().circle(*knot_pos, knot_radius, color, stroke);
+        }
+
+        // The drawn wire segment closest to the cursor, if near enough, along
+        // with its output and whether it goes through knots. Only the wires
+        // going through knots are considered when `rerouted_only` is set.
+        let zoom = self.pan_zoom.zoom;
+        let closest_segment = |rerouted_only: bool| {
+            let max_distance = DISTANCE_TO_CONNECT * zoom;
+            drawn_segments
+                .iter()
+                .filter(|(_, _, rerouted, _, _)| *rerouted || !rerouted_only)
+                .map(|(segment, output, rerouted, src_pos, dst_pos)| {
+                    let points = wire_points(&style, zoom, *src_pos, *dst_pos);
+                    let distance = distance_to_polyline(&points, cursor_pos);
+                    ((*segment, *output, *rerouted), distance)
+                })
+                .filter(|(_, distance)| *distance < max_distance)
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(closest, _)| closest)
+        };
+
+        // Double-clicking a wire adds a knot to it
+        let cursor_on_item = hovered_knot.is_some()
+            || cursor_in_minimap
+            || node_index.topmost_at(cursor_pos).is_some()
+            || group_rects.values().any(|rect| rect.contains(cursor_pos));
+        if cursor_in_editor
+            && !cursor_in_finder
+            && !cursor_on_item
+            && ui.input(|i| i.pointer.button_double_clicked(PointerButton::Primary))
+        {
+            if let Some((segment, _, _)) = closest_segment(true) {
+                if self.insert_reroute(segment, cursor_pos - pan).is_some() {
+                    self.history.defer("Add reroute", None);
+                    // The double click was meant for the wire, not for a
+                    // frame beneath it.
+                    comment_actions.retain(|(_, action)| *action != CommentAction::StartEditing);
+                }
+            }
+        }
+
+        for (group, action) in group_actions {
+            match action {
+                GroupAction::Select => {
+                    self.selected_nodes.clear();
+                    self.selected_comments.clear();
+                    self.selected_groups = vec![group];
+                }
+                GroupAction::Enter => self.enter_group(Some(group)),
+                GroupAction::Expand => {
+                    self.expand_group(group);
+                    self.history.defer("Expand group", None);
+                }
+                GroupAction::Delete => {
+                    // Deleting the nodes emits the usual responses.
+                    delayed_responses.extend(
+                        self.nodes_in_group(group)
+                            .into_iter()
+                            .map(NodeResponse::DeleteNodeUi),
+                    );
+                    self.remove_group_tree(group);
+                    self.history.defer("Delete group", None);
+                }
+                GroupAction::Move(drag_delta) => {
+                    self.drag_items(
+                        self.selected_groups.contains(&group),
+                        HashSet::new(),
+                        HashSet::from([group]),
+                        HashSet::new(),
+                        drag_delta,
+                    );
+                }
+            }
+        }
+
+        for (comment, action) in comment_actions {
+            match action {
+                CommentAction::Select => {
+                    self.selected_nodes.clear();
+                    self.selected_groups.clear();
+                    self.selected_comments = vec![comment];
+                }
+                CommentAction::Move(drag_delta) => {
+                    let mut nodes = HashSet::new();
+                    let mut groups = HashSet::new();
+                    let mut comments = HashSet::from([comment]);
+                    // Frames carry everything that is inside them
+                    if self.comments[comment].kind == CommentKind::Frame {
+                        let frame = comment_rects[&comment];
+                        let inside = |rect: &Rect| frame.contains_rect(*rect);
+                        nodes.extend(
+                            node_rects
+                                .iter()
+                                .filter(|(_, r)| inside(r))
+                                .map(|(id, _)| *id),
+                        );
+                        groups.extend(
+                            group_rects
+                                .iter()
+                                .filter(|(_, r)| inside(r))
+                                .map(|(id, _)| *id),
+                        );
+                        comments.extend(
+                            comment_rects
+                                .iter()
+                                .filter(|(_, r)| inside(r))
+                                .map(|(id, _)| *id),
+                        );
+                    }
+                    self.drag_items(
+                        self.selected_comments.contains(&comment),
+                        nodes,
+                        groups,
+                        comments,
+                        drag_delta,
+                    );
+                }
+                CommentAction::Resize(drag_delta) => {
+                    let size = &mut self.comments[comment].size;
+                    *size = (*size + drag_delta).max(Comment::MIN_SIZE * self.pan_zoom.zoom);
+                    self.history
+                        .defer("Resize comment", Some(UndoMergeKey::ResizeComment(comment)));
+                }
+                CommentAction::StartEditing => {
+                    self.editing_comment = Some(comment);
+                }
+                CommentAction::StopEditing => {
+                    if self.editing_comment == Some(comment) {
+                        self.editing_comment = None;
+                    }
+                    self.history.end_merge();
+                }
+                CommentAction::Edited => {
+                    self.history
+                        .defer("Edit comment", Some(UndoMergeKey::EditComment(comment)));
+                }
+                CommentAction::CycleColor => {
+                    self.comments[comment].cycle_color(&style);
+                    self.history.defer("Change comment color", None);
+                }
+                CommentAction::Delete => {
+                    self.remove_comment(comment);
+                    self.history.defer("Delete comment", None);
+                }
+            }
         }
 
         /* Handle responses from drawing nodes */
@@ -383,19 +1135,136 @@ where
         // are stored here to report them back to the user.
         let mut extra_responses: Vec<NodeResponse<UserResponse, NodeData>> = Vec::new();
 
+        if let Some((menu, action)) = context_menu_action {
+            match action {
+                ContextMenuAction::DeleteNode(node) => {
+                    delayed_responses.push(NodeResponse::DeleteNodeUi(node));
+                }
+                ContextMenuAction::DuplicateNode(node) => {
+                    self.history.defer("Duplicate", None);
+                    let new_nodes = self.duplicate_nodes(&[node]);
+                    delayed_responses.extend(new_nodes.into_iter().map(NodeResponse::CreatedNode));
+                }
+                ContextMenuAction::CollapseNode(node, collapsed) => {
+                    delayed_responses.push(NodeResponse::CollapseNode { node, collapsed });
+                }
+                ContextMenuAction::DisconnectNode(node) => {
+                    let connections: Vec<(InputId, OutputId)> = self
+                        .graph
+                        .iter_connections()
+                        .filter(|(input, output)| {
+                            self.graph[*input].node == node || self.graph[*output].node == node
+                        })
+                        .collect();
+                    for (input, output) in connections {
+                        self.graph.remove_connection(input, output);
+                        extra_responses.push(NodeResponse::DisconnectEvent { input, output });
+                    }
+                    self.history.defer("Disconnect", None);
+                }
+                ContextMenuAction::DeleteWire { output, input } => {
+                    if self.graph.remove_connection(input, output) {
+                        extra_responses.push(NodeResponse::DisconnectEvent { input, output });
+                        self.history.defer("Disconnect", None);
+                    }
+                }
+                ContextMenuAction::InsertReroute(segment) => {
+                    if self.insert_reroute(segment, menu.position - pan).is_some() {
+                        self.history.defer("Add reroute", None);
+                    }
+                }
+                ContextMenuAction::AddNode => {
+                    self.node_finder = Some(NodeFinder::new_at(menu.position));
+                }
+            }
+        }
+
+        // A node dropped on a wire, or created over one, is inserted in it.
+        // Only nodes without connections are, and not while dragging a
+        // selection of several nodes.
+        let dropped_node = self
+            .node_drag
+            .filter(|_| ui.input(|i| i.pointer.primary_released()))
+            .filter(|node| !self.selected_nodes.contains(node) || self.selected_nodes.len() == 1);
+        let splice = dropped_node
+            .or(created_node)
+            .filter(|node| {
+                !self.graph.iter_connections().any(|(input, output)| {
+                    self.graph[input].node == *node || self.graph[output].node == *node
+                })
+            })
+            .and_then(|node| Some((node, *node_rects.get(&node)?)))
+            .and_then(|(node, rect)| {
+                // The wire must cross the node near its center.
+                let max_distance = rect.width().min(rect.height()) / 2.0;
+                drawn_segments
+                    .iter()
+                    .filter_map(|(segment, output, _, src_pos, dst_pos)| {
+                        let input = self.reroutes.segment_input(*segment)?;
+                        let points = wire_points(&style, zoom, *src_pos, *dst_pos);
+                        let distance = distance_to_polyline(&points, rect.center());
+                        (distance < max_distance).then_some((*output, input, distance))
+                    })
+                    .min_by(|a, b| a.2.total_cmp(&b.2))
+                    .map(|(output, input, _)| (node, output, input))
+            });
+        if let Some((node, output, input)) = splice {
+            if let Some(spliced) = self.splice_node(node, output, input, user_state) {
+                extra_responses.extend(
+                    spliced
+                        .disconnected
+                        .into_iter()
+                        .map(|(input, output)| NodeResponse::DisconnectEvent { input, output }),
+                );
+                for (output, input, compatibility) in spliced.connected {
+                    extra_responses.push(NodeResponse::ConnectEventEnded {
+                        output,
+                        input,
+                        node_input: self.graph[input].node,
+                        node_output: self.graph[output].node,
+                        conversion: compatibility.conversion().map(str::to_owned),
+                    });
+                }
+                self.history.defer("Insert node", None);
+            }
+        }
+
         for response in delayed_responses.iter() {
             match response {
                 NodeResponse::ConnectEventStarted(node_id, port) => {
                     self.connection_in_progress = Some((*node_id, *port));
                 }
-                NodeResponse::ConnectEventEnded { input, output, node_input: _, node_output: _ } => {
-                    self.graph.add_connection(*output, *input)
+                NodeResponse::ConnectEventEnded { input, output, .. } => {
+                    // Connections over the limits of the ports are replaced.
+                    let replaced = self.graph.add_connection(*output, *input);
+                    extra_responses.extend(
+                        replaced
+                            .into_iter()
+                            .map(|(input, output)| NodeResponse::DisconnectEvent { input, output }),
+                    );
+                    if self.graph.connections(*input).len() == 1 {
+                        self.reroutes.routes.remove(*input);
+                    }
+                    // A wire dragged from a knot keeps going through it.
+                    if let Some(knot) = self.reroute_drag {
+                        if matches!(self.reroutes.knots.get(knot), Some(k) if k.source == *output) {
+                            self.reroutes.routes.insert(*input, knot);
+                        }
+                    }
+                    // A wire dragged away from an input keeps its data.
+                    if let Some(data) = self.edge_drag.take() {
+                        let _ = self.set_edge_data(*output, *input, data);
+                    }
+                    self.history.defer("Connect", None);
                 }
                 NodeResponse::CreatedNode(_) => {
                     //Convenience NodeResponse for users
+                    self.history.defer("Add node", None);
                 }
                 NodeResponse::SelectNode(node_id) => {
                     self.selected_nodes = Vec::from([*node_id]);
+                    self.selected_groups.clear();
+                    self.selected_comments.clear();
                 }
                 NodeResponse::DeleteNodeUi(node_id) => {
                     let (node, disc_events) = self.graph.remove_node(*node_id);
@@ -413,15 +1282,29 @@ where
                         node,
                     });
                     self.node_positions.remove(*node_id);
+                    self.node_sizes.remove(*node_id);
+                    self.node_views.remove(*node_id);
+                    self.port_offsets.remove(*node_id);
                     // Make sure to not leave references to old nodes hanging
                     self.selected_nodes.retain(|id| *id != *node_id);
                     self.node_order.retain(|id| *id != *node_id);
+                    self.groups.node_groups.remove(*node_id);
+                    self.history.defer("Delete node", None);
                 }
                 NodeResponse::DisconnectEvent { input, output } => {
                     let other_node = self.graph.get_output(*output).node;
-                    self.graph.remove_connection(*input);
+                    self.graph.remove_connection(*input, *output);
+                    self.edge_drag = self.remove_edge_data(*output, *input);
+                    // The wire being dragged keeps its knots until dropped.
+                    self.reroute_drag = self
+                        .reroutes
+                        .routes
+                        .get(*input)
+                        .copied()
+                        .filter(|knot| self.reroutes.knots[*knot].source == *output);
                     self.connection_in_progress =
                         Some((other_node, AnyParameterId::Output(*output)));
+                    self.history.defer("Disconnect", None);
                 }
                 NodeResponse::RaiseNode(node_id) => {
                     let old_pos = self
@@ -433,15 +1316,44 @@ where
                     self.node_order.push(*node_id);
                 }
                 NodeResponse::MoveNode { node, drag_delta } => {
-                    self.node_positions[*node] += *drag_delta;
+                    self.node_drag = Some(*node);
                     // Handle multi-node selection movement
-                    if self.selected_nodes.contains(node) && self.selected_nodes.len() > 1 {
-                        for n in self.selected_nodes.iter().copied() {
-                            if n != *node {
-                                self.node_positions[n] += *drag_delta;
-                            }
-                        }
+                    self.drag_items(
+                        self.selected_nodes.contains(node),
+                        HashSet::from([*node]),
+                        HashSet::new(),
+                        HashSet::new(),
+                        *drag_delta,
+                    );
+                }
+                NodeResponse::CollapseNode { node, collapsed } => {
+                    self.set_node_collapsed(*node, *collapsed);
+                    let label = if *collapsed {
+                        "Collapse node"
+                    } else {
+                        "Expand node"
+                    };
+                    self.history.defer(label, None);
+                }
+                NodeResponse::ResizeNode { node, drag_delta } => {
+                    let size = self
+                        .node_views
+                        .get(*node)
+                        .and_then(|view| view.size)
+                        .or_else(|| self.node_sizes.get(*node).copied())
+                        .unwrap_or_default();
+                    let min_size =
+                        Vec2::from(GraphNodeWidget::<NodeData, DataType, ValueType>::MIN_NODE_SIZE);
+                    if let Some(entry) = self.node_views.entry(*node) {
+                        entry.or_default().size =
+                            Some((size + *drag_delta).max(min_size * self.pan_zoom.zoom));
                     }
+                    self.history
+                        .defer("Resize node", Some(UndoMergeKey::ResizeNode(*node)));
+                }
+                NodeResponse::ValueChanged { input, .. } => {
+                    self.history
+                        .defer("Edit value", Some(UndoMergeKey::EditValue(*input)));
                 }
                 NodeResponse::User(_) => {
                     // These are handled by the user code.
@@ -452,27 +1364,80 @@ where
             }
         }
 
+        if ui.memory(|mem| mem.focus().is_none()) && cursor_in_editor && !cursor_in_finder {
+            self.handle_comment_shortcuts(
+                ui,
+                &node_rects,
+                &group_rects,
+                cursor_pos,
+                self.pan_zoom.pan + editor_rect.min.to_vec2(),
+                &style,
+            );
+        }
+
+        /* Draw the minimap, above the nodes */
+        if let Some(minimap_rect) = minimap_rect {
+            let nodes: Vec<(Rect, bool)> =
+                node_rects
+                    .iter()
+                    .map(|(id, rect)| (rect.translate(-pan), self.selected_nodes.contains(id)))
+                    .chain(group_rects.iter().map(|(id, rect)| {
+                        (rect.translate(-pan), self.selected_groups.contains(id))
+                    }))
+                    .collect();
+            let wires: Vec<(Pos2, Pos2, Color32)> = self
+                .graph
+                .iter_connections()
+                .filter_map(|(input, output)| {
+                    let src_pos = port_locations.get(&AnyParameterId::Output(output))?;
+                    let dst_pos = port_locations.get(&AnyParameterId::Input(input))?;
+                    let port_type = self
+                        .graph
+                        .any_param_type(AnyParameterId::Output(output))
+                        .ok()?;
+                    Some((
+                        *src_pos - pan,
+                        *dst_pos - pan,
+                        port_type.data_type_color(user_state),
+                    ))
+                })
+                .collect();
+            let viewport_center = MinimapWidget {
+                id: resp.id.with("minimap"),
+                rect: minimap_rect,
+                viewport: editor_rect.translate(-pan),
+                nodes: &nodes,
+                wires: &wires,
+                style: &style,
+            }
+            .show(ui);
+            if let Some(center) = viewport_center {
+                self.pan_zoom.pan = editor_rect.size() / 2.0 - center.to_vec2();
+            }
+        }
+
         // Handle box selection
         if let Some(box_start) = self.ongoing_box_selection {
             let selection_rect = Rect::from_two_pos(cursor_pos, box_start);
-            let bg_color = Color32::from_rgba_unmultiplied(200, 200, 200, 20);
-            let stroke_color = Color32::from_rgba_unmultiplied(200, 200, 200, 180);
             ui.painter().rect(
                 selection_rect,
                 2.0,
-                bg_color,
-                Stroke::new(3.0, stroke_color),
+                style.box_selection_fill,
+                Stroke::new(3.0, style.box_selection_stroke),
             );
 
-            self.selected_nodes = node_rects
+            self.selected_nodes = node_index.query(selection_rect);
+            self.selected_groups = group_rects
                 .into_iter()
-                .filter_map(|(node_id, rect)| {
-                    if selection_rect.intersects(rect) {
-                        Some(node_id)
-                    } else {
-                        None
-                    }
-                })
+                .filter(|(_, rect)| selection_rect.intersects(*rect))
+                .map(|(group_id, _)| group_id)
+                .collect();
+            // Comments are only selected when fully inside the box, since
+            // frames are usually large.
+            self.selected_comments = comment_rects
+                .into_iter()
+                .filter(|(_, rect)| selection_rect.contains_rect(*rect))
+                .map(|(comment_id, _)| comment_id)
                 .collect();
         }
 
@@ -486,12 +1451,56 @@ where
         // This locks the context, so don't hold on to it for too long.
         let mouse = &ui.ctx().input(|i| i.pointer.clone());
 
-        if mouse.any_released() && self.connection_in_progress.is_some() {
-            self.connection_in_progress = None;
+        if mouse.any_released() {
+            if let Some((node, port)) = self.connection_in_progress.take() {
+                self.reroute_drag = None;
+                self.edge_drag = None;
+                // Dropping a wire on the canvas opens the node finder, to
+                // create a node connected to it.
+                let connected = delayed_responses
+                    .iter()
+                    .any(|response| matches!(response, NodeResponse::ConnectEventEnded { .. }));
+                if mouse.primary_released()
+                    && !connected
+                    && cursor_in_editor
+                    && !cursor_in_finder
+                    && !cursor_on_item
+                {
+                    self.node_finder = Some(NodeFinder::new_for_connection(cursor_pos, node, port));
+                }
+            }
         }
 
-        if mouse.secondary_released() && cursor_in_editor && !cursor_in_finder {
-            self.node_finder = Some(NodeFinder::new_at(cursor_pos));
+        // Releasing the mouse ends a node drag or a value edit, so the next one
+        // becomes a separate undo step.
+        if mouse.any_released() {
+            self.node_drag = None;
+            self.history.end_merge();
+        }
+
+        // Right-clicking opens the context menu of what is under the cursor
+        if mouse.secondary_released() && cursor_in_editor && !cursor_in_finder && !cursor_in_minimap
+        {
+            let node = node_index.topmost_at(cursor_pos);
+            let target = if let Some(node) = node {
+                Some(ContextMenuTarget::Node(node))
+            } else if let Some((segment, output, rerouted)) = closest_segment(false) {
+                let input = self.reroutes.segment_input(segment);
+                input.map(|input| ContextMenuTarget::Wire {
+                    output,
+                    input,
+                    segment: rerouted.then_some(segment),
+                })
+            } else if !cursor_on_item {
+                Some(ContextMenuTarget::Canvas)
+            } else {
+                None
+            };
+            self.node_finder = None;
+            self.context_menu = target.map(|target| ContextMenu {
+                target,
+                position: cursor_pos,
+            });
         }
         if ui.ctx().input(|i| i.key_pressed(Key::Escape)) {
             self.node_finder = None;
@@ -505,7 +1514,11 @@ where
         // *or* if the the mouse clicks off the ui
         if click_on_background || (mouse.any_click() && !cursor_in_editor) {
             self.selected_nodes = Vec::new();
+            self.selected_groups = Vec::new();
+            self.selected_comments = Vec::new();
+            self.editing_comment = None;
             self.node_finder = None;
+            self.context_menu = None;
         }
 
         if drag_started_on_background && mouse.primary_down() {
@@ -521,36 +1534,394 @@ where
             cursor_in_finder,
         }
     }
+
+    /// Handles the copy, cut, paste and duplicate shortcuts. Pasted nodes are
+    /// placed at `paste_position`, in node coordinates.
+    fn handle_clipboard_events(
+        &mut self,
+        ui: &Ui,
+        paste_position: Pos2,
+        responses: &mut Vec<NodeResponse<UserResponse, NodeData>>,
+    ) {
+        let (copy, cut, paste, duplicate) = ui.input(|i| {
+            let mut copy = false;
+            let mut cut = false;
+            // The text of the system clipboard, or `Some(None)` when a paste
+            // shortcut came without it.
+            let mut paste: Option<Option<String>> = None;
+            for event in &i.events {
+                match event {
+                    Event::Copy => copy = true,
+                    Event::Cut => cut = true,
+                    Event::Paste(text) => paste = Some(Some(text.clone())),
+                    _ => {}
+                }
+            }
+            if paste.is_none() && i.modifiers.command && i.key_pressed(Key::V) {
+                paste = Some(None);
+            }
+            let duplicate = i.modifiers.command && i.key_pressed(Key::D);
+            (copy, cut, paste, duplicate)
+        });
+
+        if (copy || cut) && !self.selected_nodes.is_empty() {
+            let clipboard = self.copy_nodes(&self.selected_nodes);
+            if let Some(text) = clipboard.to_text() {
+                ui.output_mut(|o| o.copied_text = text);
+            }
+            self.clipboard = Some(clipboard);
+            if cut {
+                self.history.defer("Cut", None);
+                responses.extend(
+                    self.selected_nodes
+                        .iter()
+                        .map(|node| NodeResponse::DeleteNodeUi(*node)),
+                );
+            }
+        } else if let Some(text) = paste {
+            // Text copied from outside the editor is not pasted. The internal
+            // clipboard is only used when the system one gave nothing, or
+            // when the nodes could not be written to it as text.
+            let clipboard = match text {
+                Some(text) => GraphClipboard::from_text(&text).or_else(|| {
+                    self.clipboard
+                        .clone()
+                        .filter(|clipboard| clipboard.to_text().is_none())
+                }),
+                None => self.clipboard.clone(),
+            };
+            if let Some(clipboard) = clipboard {
+                self.history.defer("Paste", None);
+                let new_nodes = self.paste_nodes(&clipboard, paste_position);
+                responses.extend(new_nodes.into_iter().map(NodeResponse::CreatedNode));
+            }
+        } else if duplicate && !self.selected_nodes.is_empty() {
+            self.history.defer("Duplicate", None);
+            let new_nodes = self.duplicate_nodes(&self.selected_nodes.clone());
+            responses.extend(new_nodes.into_iter().map(NodeResponse::CreatedNode));
+        }
+    }
+
+    /// Moves the dragged nodes, groups and comments by `drag_delta`. When the
+    /// dragged item is selected, the rest of the selection moves with it.
+    fn drag_items(
+        &mut self,
+        dragged_is_selected: bool,
+        mut nodes: HashSet<NodeId>,
+        mut groups: HashSet<GroupId>,
+        mut comments: HashSet<CommentId>,
+        drag_delta: Vec2,
+    ) {
+        if dragged_is_selected {
+            nodes.extend(self.selected_nodes.iter().copied());
+            groups.extend(self.selected_groups.iter().copied());
+            comments.extend(self.selected_comments.iter().copied());
+        }
+        for node in nodes {
+            if let Some(position) = self.node_positions.get_mut(node) {
+                *position += drag_delta;
+            }
+        }
+        for group in groups {
+            if let Some(group) = self.groups.groups.get_mut(group) {
+                group.position += drag_delta;
+            }
+        }
+        for comment in comments {
+            if let Some(comment) = self.comments.get_mut(comment) {
+                comment.position += drag_delta;
+            }
+        }
+        self.history
+            .defer("Move nodes", Some(UndoMergeKey::MoveNodes));
+    }
+
+    /// Handles the shortcuts to add comments: C adds a frame around the
+    /// selection (or at the cursor when nothing is selected), and N adds a
+    /// sticky note at the cursor. `pan` converts node positions to screen
+    /// positions.
+    fn handle_comment_shortcuts(
+        &mut self,
+        ui: &Ui,
+        node_rects: &NodeRects,
+        group_rects: &GroupRects,
+        cursor_pos: Pos2,
+        pan: Vec2,
+        style: &GraphEditorStyle,
+    ) {
+        let (frame, note) = ui.input(|i| {
+            let no_modifiers = i.modifiers.is_none();
+            (
+                no_modifiers && i.key_pressed(Key::C),
+                no_modifiers && i.key_pressed(Key::N),
+            )
+        });
+        let zoom = self.pan_zoom.zoom;
+        let at_cursor = Rect::from_min_size(cursor_pos - pan, vec2(300.0, 200.0) * zoom);
+        if frame {
+            let selection = self
+                .selected_nodes
+                .iter()
+                .filter_map(|node| node_rects.get(node))
+                .chain(
+                    self.selected_groups
+                        .iter()
+                        .filter_map(|group| group_rects.get(group)),
+                )
+                .copied()
+                .reduce(|a, b| a.union(b));
+            let rect = match selection {
+                Some(rect) => Rect::from_min_max(
+                    rect.min - vec2(20.0, 40.0) * zoom,
+                    rect.max + Vec2::splat(20.0) * zoom,
+                )
+                .translate(-pan),
+                None => at_cursor,
+            };
+            let comment = self.add_comment(CommentKind::Frame, rect, style);
+            self.selected_comments = vec![comment];
+            self.history.defer("Add comment", None);
+        } else if note {
+            let rect = Rect::from_min_size(at_cursor.min, vec2(200.0, 120.0) * zoom);
+            let comment = self.add_comment(CommentKind::Note, rect, style);
+            self.editing_comment = Some(comment);
+            self.history.defer("Add comment", None);
+        }
+    }
+
+    /// Handles the shortcuts to collapse the selection to a group (Ctrl+G),
+    /// and to expand the selected groups (Ctrl+Shift+G).
+    fn handle_group_shortcuts(&mut self, ui: &Ui) {
+        let (collapse, expand) = ui.input(|i| {
+            let g = i.modifiers.command && i.key_pressed(Key::G);
+            (g && !i.modifiers.shift, g && i.modifiers.shift)
+        });
+        if collapse {
+            let (nodes, groups) = (self.selected_nodes.clone(), self.selected_groups.clone());
+            if self.collapse_to_group(&nodes, &groups, "Group").is_some() {
+                self.history.defer("Collapse to group", None);
+            }
+        } else if expand && !self.selected_groups.is_empty() {
+            for group in self.selected_groups.clone() {
+                self.expand_group(group);
+            }
+            self.history.defer("Expand group", None);
+        }
+    }
+
+    /// Draws the path from the root of the graph to the group being edited.
+    /// Clicking any of its parts goes back to it.
+    fn draw_breadcrumb(&mut self, ui: &mut Ui, editor_rect: Rect) {
+        let rect = Rect::from_min_size(
+            editor_rect.min + vec2(8.0, 8.0),
+            vec2(editor_rect.width() - 16.0, 24.0),
+        );
+        let mut target = None;
+        ui.allocate_ui_at_rect(rect, |ui| {
+            ui.horizontal(|ui| {
+                let path = self.groups.path(self.current_group);
+                if ui.button("Graph").clicked() {
+                    target = Some(None);
+                }
+                for (index, group) in path.iter().enumerate() {
+                    ui.label("›");
+                    let label = &self.groups.groups[*group].label;
+                    if index + 1 == path.len() {
+                        ui.strong(label);
+                    } else if ui.button(label).clicked() {
+                        target = Some(Some(*group));
+                    }
+                }
+            });
+        });
+        if let Some(group) = target {
+            self.enter_group(group);
+        }
+    }
+}
+
+/// Draws the background grid of the editor. `spacing` is in screen points and
+/// `pan` is the offset from node coordinates to screen coordinates, so the
+/// grid moves along with the nodes.
+fn draw_grid(painter: &Painter, rect: Rect, pan: Vec2, spacing: f32, color: Color32) {
+    // Too dense grids are not drawn when zoomed out.
+    if spacing < 4.0 || color.a() == 0 {
+        return;
+    }
+    let stroke = Stroke::new(1.0, color);
+    let mut x = rect.min.x + (pan.x - rect.min.x).rem_euclid(spacing);
+    while x < rect.max.x {
+        painter.vline(x, rect.y_range(), stroke);
+        x += spacing;
+    }
+    let mut y = rect.min.y + (pan.y - rect.min.y).rem_euclid(spacing);
+    while y < rect.max.y {
+        painter.hline(rect.x_range(), y, stroke);
+        y += spacing;
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum WireEmphasis {
+    Normal,
+    Highlighted,
+    Dimmed,
+}
+
+/// The stroke of a wire of the given color.
+fn wire_stroke(
+    style: &GraphEditorStyle,
+    pan_zoom: &PanZoom,
+    color: Color32,
+    emphasis: WireEmphasis,
+) -> Stroke {
+    let (width, color) = match emphasis {
+        WireEmphasis::Normal => (style.wire_width, color),
+        WireEmphasis::Highlighted => (style.highlighted_wire_width, color),
+        WireEmphasis::Dimmed => (
+            style.wire_width,
+            color.linear_multiply(style.dimmed_wire_opacity),
+        ),
+    };
+    Stroke::new(width * pan_zoom.zoom, color)
 }
 
 fn draw_connection(
+    style: &GraphEditorStyle,
     pan_zoom: &PanZoom,
     painter: &Painter,
     src_pos: Pos2,
     dst_pos: Pos2,
     color: Color32,
+    emphasis: WireEmphasis,
 ) {
-    let connection_stroke = egui::Stroke {
-        width: 5.0 * pan_zoom.zoom,
-        color,
+    let stroke = wire_stroke(style, pan_zoom, color, emphasis);
+    paint_wire(style, pan_zoom.zoom, painter, src_pos, dst_pos, stroke);
+}
+
+/// Draws the dashes moving along a wire of a datatype with
+/// [`DataTypeTrait::animated_flow`]. `time` is the time of the frame, in
+/// seconds.
+fn draw_connection_flow(
+    style: &GraphEditorStyle,
+    pan_zoom: &PanZoom,
+    painter: &Painter,
+    src_pos: Pos2,
+    dst_pos: Pos2,
+    time: f64,
+) {
+    let dash = style.flow_dash_length;
+    // Wrapping before converting to f32 keeps the offset precise as time grows.
+    let offset = (time * style.flow_speed as f64).rem_euclid(2.0 * dash as f64) as f32;
+    let stroke = Stroke::new(style.wire_width * 0.5 * pan_zoom.zoom, style.flow_color);
+    let points = wire_points(style, pan_zoom.zoom, src_pos, dst_pos);
+    for dash in dash_polyline(
+        &points,
+        dash * pan_zoom.zoom,
+        dash * pan_zoom.zoom,
+        offset * pan_zoom.zoom,
+    ) {
+        painter.add(Shape::line(dash, stroke));
+    }
+}
+
+/// Checks whether the ports `a` and `b` can be connected, in any order.
+/// Returns `None` when both ports are inputs, both are outputs, or either of
+/// them is not in the graph. Otherwise, returns the output and input ids along
+/// with the result of [`DataTypeTrait::connection_compatibility`]. Ports that
+/// are already connected to each other are rejected.
+fn connection_compatibility<NodeData, DataType, ValueType, UserState>(
+    graph: &Graph<NodeData, DataType, ValueType>,
+    a: AnyParameterId,
+    b: AnyParameterId,
+    user_state: &mut UserState,
+) -> Option<(OutputId, InputId, ConnectionCompatibility)>
+where
+    DataType: DataTypeTrait<UserState>,
+{
+    let (output, input) = match (a, b) {
+        (AnyParameterId::Output(output), AnyParameterId::Input(input))
+        | (AnyParameterId::Input(input), AnyParameterId::Output(output)) => (output, input),
+        _ => return None,
     };
-    
-    // let control_scale = ((dst_pos.x - src_pos.x) / 2.0).max(30.0);
-    // let src_control = src_pos + Vec2::X * control_scale;
-    // let dst_control = dst_pos - Vec2::X * control_scale;
-
-    let control_scale = 0.75;
-    let src_control = Pos2 { x: src_pos.x-(-(f32::abs(src_pos.x-dst_pos.x))*control_scale), y: src_pos.y };
-    let dst_control = Pos2 { x: dst_pos.x+(-(f32::abs(src_pos.x-dst_pos.x))*control_scale), y: dst_pos.y };
-
-    let bezier = CubicBezierShape::from_points_stroke(
-        [src_pos, src_control, dst_control, dst_pos],
-        false,
-        Color32::TRANSPARENT,
-        connection_stroke,
+    let output_type = &graph.try_get_output(output)?.typ;
+    let input_type = &graph.try_get_input(input)?.typ;
+    let compatibility = if graph.connections(input).contains(&output) {
+        ConnectionCompatibility::Rejected("Already connected".to_owned())
+    } else {
+        output_type.connection_compatibility(input_type, user_state)
+    };
+    Some((output, input, compatibility))
+}
+
+/// Draws a port of a node, and handles starting connections from it. The
+/// port location is added to `port_locations`. Connections are ended by the
+/// editor, on the nearest port accepting them.
+#[allow(clippy::too_many_arguments)]
+fn draw_port<NodeData, DataType, ValueType, UserResponse, UserState>(
+    style: &GraphEditorStyle,
+    pan_zoom: &PanZoom,
+    ui: &mut Ui,
+    graph: &Graph<NodeData, DataType, ValueType>,
+    node_id: NodeId,
+    user_state: &mut UserState,
+    port_pos: Pos2,
+    responses: &mut Vec<NodeResponse<UserResponse, NodeData>>,
+    param_id: AnyParameterId,
+    port_locations: &mut PortLocations,
+    ongoing_drag: Option<(NodeId, AnyParameterId)>,
+    is_connected_input: bool,
+    connected_outputs: &HashSet<OutputId>,
+) where
+    DataType: DataTypeTrait<UserState>,
+    UserResponse: UserResponseTrait,
+    NodeData: NodeDataTrait,
+{
+    let port_type = graph.any_param_type(param_id).unwrap();
+
+    let port_rect = Rect::from_center_size(
+        port_pos,
+        Vec2::splat(2.0 * style.port_radius) * pan_zoom.zoom,
+    );
+
+    let sense = if ongoing_drag.is_some() {
+        Sense::hover()
+    } else {
+        Sense::click_and_drag()
+    };
+
+    let resp = ui.allocate_rect(port_rect, sense);
+
+    let is_connected = match param_id {
+        AnyParameterId::Input(input) => graph.connection(input).is_some(),
+        AnyParameterId::Output(output) => connected_outputs.contains(&output),
+    };
+    port_type.port_shape(user_state).paint(
+        ui.painter(),
+        port_rect.center(),
+        style.port_radius * pan_zoom.zoom,
+        port_type.data_type_color(user_state),
+        is_connected || !style.hollow_unconnected_ports,
     );
 
-    painter.add(bezier);
+    if resp.drag_started() {
+        if is_connected_input {
+            // Inputs with several connections give up the last one.
+            let input = param_id.assume_input();
+            let corresp_output = *graph
+                .connections(input)
+                .last()
+                .expect("Connection data should be valid");
+            responses.push(NodeResponse::DisconnectEvent {
+                input: param_id.assume_input(),
+                output: corresp_output,
+            });
+        } else {
+            responses.push(NodeResponse::ConnectEventStarted(node_id, param_id));
+        }
+    }
+
+    port_locations.insert(param_id, port_rect.center());
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -566,11 +1937,14 @@ where
         ValueType = ValueType,
     >,
     UserResponse: UserResponseTrait,
-    ValueType:
-        WidgetValueTrait<Response = UserResponse, UserState = UserState, NodeData = NodeData>,
+    ValueType: WidgetValueTrait<Response = UserResponse, UserState = UserState, NodeData = NodeData>
+        + Clone
+        + PartialEq,
     DataType: DataTypeTrait<UserState>,
 {
     pub const MAX_NODE_SIZE: [f32; 2] = [200.0, 200.0];
+    /// The smallest size a node can be resized to, at a zoom level of 1.
+    pub const MIN_NODE_SIZE: [f32; 2] = [80.0, 40.0];
 
     pub fn show(
         self,
@@ -578,8 +1952,9 @@ where
         ui: &mut Ui,
         user_state: &mut UserState,
     ) -> Vec<NodeResponse<UserResponse, NodeData>> {
+        let max_size = self.view.size.unwrap_or_else(|| Self::MAX_NODE_SIZE.into());
         let mut child_ui = ui.child_ui_with_id_source(
-            Rect::from_min_size(*self.position + self.pan, Self::MAX_NODE_SIZE.into()),
+            Rect::from_min_size(*self.position + self.pan, max_size),
             Layout::default(),
             self.node_id,
         );
@@ -587,6 +1962,80 @@ where
         Self::show_graph_node(self, pan_zoom, &mut child_ui, user_state)
     }
 
+    /// Draws this node as a plain box of the given size, without its label and
+    /// widgets. The ports are placed at `port_offsets` from the node, where
+    /// they were when the whole node was last drawn.
+    pub fn show_simplified(
+        self,
+        pan_zoom: &PanZoom,
+        ui: &mut Ui,
+        user_state: &mut UserState,
+        size: Vec2,
+        port_offsets: &[(AnyParameterId, Vec2)],
+    ) -> Vec<NodeResponse<UserResponse, NodeData>> {
+        let mut responses = Vec::new();
+        let zoom = pan_zoom.zoom;
+        let rect = Rect::from_min_size(*self.position + self.pan, size);
+        // Same id as the whole node, so drags carry on across zoom levels.
+        let window_response = ui.interact(
+            rect,
+            Id::new((self.node_id, "window")),
+            Sense::click_and_drag(),
+        );
+
+        let titlebar_fill = self.graph[self.node_id]
+            .user_data
+            .titlebar_color(ui, self.node_id, self.graph, user_state)
+            .unwrap_or(self.style.titlebar_fill);
+        let rounding = Rounding::same(self.style.node_rounding * zoom);
+        let painter = ui.painter();
+        if self.selected {
+            painter.rect_filled(
+                rect.expand(self.style.selection_outline_width * zoom),
+                rounding,
+                self.style.selection_outline,
+            );
+        }
+        painter.rect_filled(rect, rounding, self.style.node_fill);
+        let titlebar_rect =
+            Rect::from_min_size(rect.min, vec2(rect.width(), rect.height().min(24.0 * zoom)));
+        painter.rect_filled(titlebar_rect, rounding, titlebar_fill);
+
+        for (port, offset) in port_offsets {
+            // The node may have lost some ports since it was last drawn.
+            if let Ok(port_type) = self.graph.any_param_type(*port) {
+                let port_pos = rect.min + *offset;
+                let is_connected = match *port {
+                    AnyParameterId::Input(input) => self.graph.connection(input).is_some(),
+                    AnyParameterId::Output(output) => self.connected_outputs.contains(&output),
+                };
+                port_type.port_shape(user_state).paint(
+                    painter,
+                    port_pos,
+                    self.style.port_radius * zoom,
+                    port_type.data_type_color(user_state),
+                    is_connected || !self.style.hollow_unconnected_ports,
+                );
+                self.port_locations.insert(*port, port_pos);
+            }
+        }
+        self.node_rects.insert(self.node_id, rect);
+
+        let drag_delta = window_response.drag_delta();
+        if drag_delta.length_sq() > 0.0 {
+            responses.push(NodeResponse::MoveNode {
+                node: self.node_id,
+                drag_delta,
+            });
+            responses.push(NodeResponse::RaiseNode(self.node_id));
+        } else if window_response.clicked_by(PointerButton::Primary) {
+            responses.push(NodeResponse::SelectNode(self.node_id));
+            responses.push(NodeResponse::RaiseNode(self.node_id));
+        }
+
+        responses
+    }
+
     /// Draws this node. Also fills in the list of port locations with all of its ports.
     /// Returns responses indicating multiple events.
     fn show_graph_node(
@@ -598,15 +2047,8 @@ where
         let margin = egui::vec2(15.0, 5.0) * pan_zoom.zoom;
         let mut responses = Vec::<NodeResponse<UserResponse, NodeData>>::new();
 
-        let background_color;
-        let text_color;
-        if ui.visuals().dark_mode {
-            background_color = color_from_hex("#3f3f3f").unwrap();
-            text_color = color_from_hex("#fefefe").unwrap();
-        } else {
-            background_color = color_from_hex("#ffffff").unwrap();
-            text_color = color_from_hex("#505050").unwrap();
-        }
+        let background_color = self.style.node_fill;
+        let text_color = self.style.node_text;
 
         ui.visuals_mut().widgets.noninteractive.fg_stroke = Stroke::new(2.0, text_color);
 
@@ -648,22 +2090,40 @@ where
 
         child_ui.vertical(|ui| {
             ui.horizontal(|ui| {
+                let (arrow_rect, arrow_response) =
+                    ui.allocate_exact_size(Vec2::splat(10.0 * pan_zoom.zoom), Sense::click());
+                let arrow_color = if arrow_response.hovered() {
+                    self.style.close_button_hovered
+                } else {
+                    self.style.close_button
+                };
+                paint_collapse_arrow(ui.painter(), arrow_rect, self.view.collapsed, arrow_color);
+                if arrow_response.clicked() {
+                    responses.push(NodeResponse::CollapseNode {
+                        node: self.node_id,
+                        collapsed: !self.view.collapsed,
+                    });
+                }
                 ui.add(Label::new(
                     RichText::new(&self.graph[self.node_id].label)
                         .text_style(TextStyle::Button)
                         .color(text_color),
                 ));
-                responses.extend(
-                    self.graph[self.node_id]
-                        .user_data
-                        .top_bar_ui(ui, self.node_id, self.graph, user_state)
-                        .into_iter(),
-                );
+                responses.extend(self.graph[self.node_id].user_data.top_bar_ui(
+                    ui,
+                    self.node_id,
+                    self.graph,
+                    user_state,
+                ));
                 ui.add_space(8.0 * pan_zoom.zoom); // The size of the little cross icon
             });
             ui.add_space(margin.y);
             title_height = ui.min_size().y;
 
+            if self.view.collapsed {
+                return;
+            }
+
             // First pass: Draw the inner fields. Compute port heights
             let inputs = self.graph[self.node_id].inputs.clone();
             for (param_name, param_id) in inputs {
@@ -677,6 +2137,7 @@ where
                     // dummy value. This requires `ValueType` to implement
                     // Default, but results in a totally safe alternative.
                     let mut value = std::mem::take(&mut self.graph[param_id].value);
+                    let previous_value = value.clone();
 
                     if self.graph.connection(param_id).is_some() {
                         let node_responses = value.value_widget_connected(
@@ -702,6 +2163,15 @@ where
                         responses.extend(node_responses.into_iter().map(NodeResponse::User));
                     }
 
+                    if value != previous_value {
+                        responses.push(NodeResponse::ValueChanged {
+                            node: self.node_id,
+                            input: param_id,
+                        });
+                        self.graph
+                            .journal
+                            .record(GraphChange::ValueChanged(param_id));
+                    }
                     self.graph[param_id].value = value;
 
                     self.graph[self.node_id].user_data.separator(
@@ -720,12 +2190,13 @@ where
             let outputs = self.graph[self.node_id].outputs.clone();
             for (param_name, param_id) in outputs {
                 let height_before = ui.min_rect().bottom();
-                responses.extend(
-                    self.graph[self.node_id]
-                        .user_data
-                        .output_ui(ui, self.node_id, self.graph, user_state, &param_name)
-                        .into_iter(),
-                );
+                responses.extend(self.graph[self.node_id].user_data.output_ui(
+                    ui,
+                    self.node_id,
+                    self.graph,
+                    user_state,
+                    &param_name,
+                ));
 
                 self.graph[self.node_id].user_data.separator(
                     ui,
@@ -739,18 +2210,28 @@ where
                 output_port_heights.push((height_before + height_after) / 2.0);
             }
 
-            responses.extend(
-                self.graph[self.node_id]
-                    .user_data
-                    .bottom_ui(ui, self.node_id, self.graph, user_state)
-                    .into_iter(),
-            );
+            responses.extend(self.graph[self.node_id].user_data.bottom_ui(
+                ui,
+                self.node_id,
+                self.graph,
+                user_state,
+            ));
         });
 
         // Second pass, iterate again to draw the ports. This happens outside
         // the child_ui because we want ports to overflow the node background.
 
-        let outer_rect = child_ui.min_rect().expand2(margin);
+        let mut outer_rect = child_ui.min_rect().expand2(margin);
+        if self.view.collapsed {
+            outer_rect.max.y = outer_rect.min.y + title_height + margin.y;
+        }
+        if let Some(size) = self.view.size {
+            let min_height = if self.view.collapsed { 0.0 } else { size.y };
+            outer_rect = outer_rect.union(Rect::from_min_size(
+                outer_rect.min,
+                vec2(size.x, min_height),
+            ));
+        }
         let port_left = outer_rect.left();
         let port_right = outer_rect.right();
 
@@ -760,97 +2241,60 @@ where
                 .insert_temp(child_ui.id(), OuterRectMemory(outer_rect))
         });
 
-        #[allow(clippy::too_many_arguments)]
-        fn draw_port<NodeData, DataType, ValueType, UserResponse, UserState>(
-            pan_zoom: &PanZoom,
-            ui: &mut Ui,
-            graph: &Graph<NodeData, DataType, ValueType>,
-            node_id: NodeId,
-            user_state: &mut UserState,
-            port_pos: Pos2,
-            responses: &mut Vec<NodeResponse<UserResponse, NodeData>>,
-            param_id: AnyParameterId,
-            port_locations: &mut PortLocations,
-            ongoing_drag: Option<(NodeId, AnyParameterId)>,
-            is_connected_input: bool,
-        ) where
-            DataType: DataTypeTrait<UserState>,
-            UserResponse: UserResponseTrait,
-            NodeData: NodeDataTrait,
-        {
-            let port_type = graph.any_param_type(param_id).unwrap();
-
-            let port_rect =
-                Rect::from_center_size(port_pos, egui::vec2(10.0, 10.0) * pan_zoom.zoom);
-
-            let sense = if ongoing_drag.is_some() {
-                Sense::hover()
-            } else {
-                Sense::click_and_drag()
-            };
-
-            let resp = ui.allocate_rect(port_rect, sense);
-
-            // Check if the distance between the port and the mouse is the distance to connect
-            let close_enough = if let Some(pointer_pos) = ui.ctx().pointer_hover_pos() {
-                port_rect.center().distance(pointer_pos) < DISTANCE_TO_CONNECT * pan_zoom.zoom
-            } else {
-                false
-            };
-
-            let port_color = if close_enough {
-                Color32::WHITE
-            } else {
-                port_type.data_type_color(user_state)
-            };
-            ui.painter().circle(
-                port_rect.center(),
-                5.0 * pan_zoom.zoom,
-                port_color,
-                Stroke::NONE,
-            );
-
-            if resp.drag_started() {
-                if is_connected_input {
-                    let input = param_id.assume_input();
-                    let corresp_output = graph
-                        .connection(input)
-                        .expect("Connection data should be valid");
-                    responses.push(NodeResponse::DisconnectEvent {
-                        input: param_id.assume_input(),
-                        output: corresp_output,
-                    });
-                } else {
-                    responses.push(NodeResponse::ConnectEventStarted(node_id, param_id));
+        // The wires of a collapsed node meet at a single port on each side
+        // of its title bar.
+        if self.view.collapsed {
+            let port_y = outer_rect.top() + (title_height + margin.y) / 2.0;
+            let node = &self.graph[self.node_id];
+            let inputs = node
+                .inputs
+                .iter()
+                .filter(|(_, input)| {
+                    !matches!(self.graph[*input].kind(), InputParamKind::ConstantOnly)
+                })
+                .map(|(_, input)| {
+                    (
+                        AnyParameterId::Input(*input),
+                        self.graph.connection(*input).is_some(),
+                    )
+                })
+                .collect::<Vec<_>>();
+            let outputs = node
+                .outputs
+                .iter()
+                .map(|(_, output)| {
+                    (
+                        AnyParameterId::Output(*output),
+                        self.connected_outputs.contains(output),
+                    )
+                })
+                .collect::<Vec<_>>();
+            for (port_pos, params) in [
+                (pos2(port_left, port_y), inputs),
+                (pos2(port_right, port_y), outputs),
+            ] {
+                if params.is_empty() {
+                    continue;
                 }
-            }
-
-            if let Some((origin_node, origin_param)) = ongoing_drag {
-                if origin_node != node_id {
-                    // Don't allow self-loops
-                    if graph.any_param_type(origin_param).unwrap() == port_type
-                        && close_enough
-                        && ui.input(|i| i.pointer.any_released())
-                    {
-                        match (param_id, origin_param) {
-                            (AnyParameterId::Input(input), AnyParameterId::Output(output))
-                            | (AnyParameterId::Output(output), AnyParameterId::Input(input)) => {
-                                responses.push(NodeResponse::ConnectEventEnded { input, output, node_input: origin_node, node_output: node_id });
-                            }
-                            _ => { /* Ignore in-in or out-out connections */ }
-                        }
-                    }
+                let is_connected = params.iter().any(|(_, connected)| *connected);
+                PortShape::Circle.paint(
+                    ui.painter(),
+                    port_pos,
+                    self.style.port_radius * pan_zoom.zoom,
+                    self.style.node_text,
+                    is_connected || !self.style.hollow_unconnected_ports,
+                );
+                for (param, _) in params {
+                    self.port_locations.insert(param, port_pos);
                 }
             }
-
-            port_locations.insert(param_id, port_rect.center());
         }
 
         // Input ports
         for ((_, param), port_height) in self.graph[self.node_id]
             .inputs
             .iter()
-            .zip(input_port_heights.into_iter())
+            .zip(input_port_heights)
         {
             let should_draw = match self.graph[*param].kind() {
                 InputParamKind::ConnectionOnly => true,
@@ -861,6 +2305,7 @@ where
             if should_draw {
                 let pos_left = pos2(port_left, port_height);
                 draw_port(
+                    self.style,
                     pan_zoom,
                     ui,
                     self.graph,
@@ -872,6 +2317,7 @@ where
                     self.port_locations,
                     self.ongoing_drag,
                     self.graph.connection(*param).is_some(),
+                    self.connected_outputs,
                 );
             }
         }
@@ -880,10 +2326,11 @@ where
         for ((_, param), port_height) in self.graph[self.node_id]
             .outputs
             .iter()
-            .zip(output_port_heights.into_iter())
+            .zip(output_port_heights)
         {
             let pos_right = pos2(port_right, port_height);
             draw_port(
+                self.style,
                 pan_zoom,
                 ui,
                 self.graph,
@@ -895,6 +2342,7 @@ where
                 self.port_locations,
                 self.ongoing_drag,
                 false,
+                self.connected_outputs,
             );
         }
 
@@ -902,8 +2350,8 @@ where
         // NOTE: This code is a bit more involved than it needs to be because egui
         // does not support drawing rectangles with asymmetrical round corners.
 
-        let (shape, outline) = {
-            let rounding_radius = 4.0 * pan_zoom.zoom;
+        let (shape, outline, node_rect) = {
+            let rounding_radius = self.style.node_rounding * pan_zoom.zoom;
             let rounding = Rounding::same(rounding_radius);
 
             let titlebar_height = title_height + margin.y;
@@ -915,7 +2363,7 @@ where
                 fill: self.graph[self.node_id]
                     .user_data
                     .titlebar_color(ui, self.node_id, self.graph, user_state)
-                    .unwrap_or_else(|| background_color.lighten(0.8)),
+                    .unwrap_or(self.style.titlebar_fill),
                 stroke: Stroke::NONE,
             });
 
@@ -941,12 +2389,19 @@ where
                 stroke: Stroke::NONE,
             });
 
-            let node_rect = titlebar_rect.union(body_rect).union(bottom_body_rect);
+            let (node_rect, shapes) = if self.view.collapsed {
+                (titlebar_rect, vec![titlebar])
+            } else {
+                (
+                    titlebar_rect.union(body_rect).union(bottom_body_rect),
+                    vec![titlebar, body, bottom_body],
+                )
+            };
             let outline = if self.selected {
                 Shape::Rect(RectShape {
-                    rect: node_rect.expand(1.0 * pan_zoom.zoom),
+                    rect: node_rect.expand(self.style.selection_outline_width * pan_zoom.zoom),
                     rounding,
-                    fill: Color32::WHITE.lighten(0.8),
+                    fill: self.style.selection_outline,
                     stroke: Stroke::NONE,
                 })
             } else {
@@ -956,7 +2411,7 @@ where
             // Take note of the node rect, so the editor can use it later to compute intersections.
             self.node_rects.insert(self.node_id, node_rect);
 
-            (Shape::Vec(vec![titlebar, body, bottom_body]), outline)
+            (Shape::Vec(shapes), outline, node_rect)
         };
 
         ui.painter().set(background_shape, shape);
@@ -971,13 +2426,47 @@ where
             user_state,
         );
 
-        if can_delete && Self::close_button(pan_zoom, ui, outer_rect).clicked() {
+        if can_delete && close_button(self.style, pan_zoom, ui, outer_rect).clicked() {
             responses.push(NodeResponse::DeleteNodeUi(self.node_id));
         };
 
+        // Resize handle in the bottom-right corner
+        let mut resize_delta = Vec2::ZERO;
+        if !self.view.collapsed {
+            let zoom = pan_zoom.zoom;
+            let handle_rect =
+                Rect::from_min_max(node_rect.max - Vec2::splat(12.0 * zoom), node_rect.max);
+            let handle = ui.interact(
+                handle_rect,
+                Id::new((self.node_id, "resize")),
+                Sense::drag(),
+            );
+            let handle_color = if handle.hovered() || handle.dragged() {
+                text_color
+            } else {
+                text_color.linear_multiply(0.3)
+            };
+            for i in 1..=2 {
+                let offset = 4.0 * zoom * i as f32;
+                ui.painter().line_segment(
+                    [
+                        pos2(node_rect.max.x - offset, node_rect.max.y - 2.0 * zoom),
+                        pos2(node_rect.max.x - 2.0 * zoom, node_rect.max.y - offset),
+                    ],
+                    Stroke::new(1.0 * zoom, handle_color),
+                );
+            }
+            resize_delta = handle.drag_delta();
+        }
+
         // Movement
         let drag_delta = window_response.drag_delta();
-        if drag_delta.length_sq() > 0.0 {
+        if resize_delta.length_sq() > 0.0 {
+            responses.push(NodeResponse::ResizeNode {
+                node: self.node_id,
+                drag_delta: resize_delta,
+            });
+        } else if drag_delta.length_sq() > 0.0 {
             responses.push(NodeResponse::MoveNode {
                 node: self.node_id,
                 drag_delta,
@@ -996,49 +2485,308 @@ where
 
         responses
     }
+}
 
-    fn close_button(pan_zoom: &PanZoom, ui: &mut Ui, node_rect: Rect) -> Response {
-        // Measurements
-        let margin = 8.0 * pan_zoom.zoom;
-        let size = 10.0 * pan_zoom.zoom;
-        let stroke_width = 2.0;
-        let offs = margin + size / 2.0;
-
-        let position = pos2(node_rect.right() - offs, node_rect.top() + offs);
-        let rect = Rect::from_center_size(position, vec2(size, size));
-        let resp = ui.allocate_rect(rect, Sense::click());
-
-        let dark_mode = ui.visuals().dark_mode;
-        let color = if resp.clicked() {
-            if dark_mode {
-                color_from_hex("#ffffff").unwrap()
-            } else {
-                color_from_hex("#000000").unwrap()
+/// Draws the arrow collapsing and expanding a node, pointing down while the
+/// node is expanded.
+fn paint_collapse_arrow(painter: &Painter, rect: Rect, collapsed: bool, color: Color32) {
+    let rect = rect.shrink(rect.width() * 0.15);
+    let points = if collapsed {
+        vec![rect.left_top(), rect.right_center(), rect.left_bottom()]
+    } else {
+        vec![rect.left_top(), rect.right_top(), rect.center_bottom()]
+    };
+    painter.add(Shape::convex_polygon(points, color, Stroke::NONE));
+}
+
+/// Draws the little cross icon at the top-right corner of a node.
+pub(crate) fn close_button(
+    style: &GraphEditorStyle,
+    pan_zoom: &PanZoom,
+    ui: &mut Ui,
+    node_rect: Rect,
+) -> Response {
+    // Measurements
+    let margin = 8.0 * pan_zoom.zoom;
+    let size = 10.0 * pan_zoom.zoom;
+    let stroke_width = 2.0;
+    let offs = margin + size / 2.0;
+
+    let position = pos2(node_rect.right() - offs, node_rect.top() + offs);
+    let rect = Rect::from_center_size(position, vec2(size, size));
+    let resp = ui.allocate_rect(rect, Sense::click());
+
+    let color = if resp.clicked() {
+        style.close_button_pressed
+    } else if resp.hovered() {
+        style.close_button_hovered
+    } else {
+        style.close_button
+    };
+    let stroke = Stroke {
+        width: stroke_width,
+        color,
+    };
+
+    ui.painter()
+        .line_segment([rect.left_top(), rect.right_bottom()], stroke);
+    ui.painter()
+        .line_segment([rect.right_top(), rect.left_bottom()], stroke);
+
+    resp
+}
+
+/// The actions performed on a group node while drawing it.
+#[derive(Clone, Copy, Debug)]
+enum GroupAction {
+    Select,
+    Enter,
+    Expand,
+    Delete,
+    Move(Vec2),
+}
+
+/// Draws a collapsed [`NodeGroup`]. Its ports are the ports of the nodes
+/// inside it, so connections to the group are drawn and edited like
+/// connections to those nodes.
+struct GroupNodeWidget<'a, NodeData, DataType, ValueType> {
+    graph: &'a Graph<NodeData, DataType, ValueType>,
+    group_id: GroupId,
+    group: &'a NodeGroup,
+    port_locations: &'a mut PortLocations,
+    group_rects: &'a mut GroupRects,
+    ongoing_drag: Option<(NodeId, AnyParameterId)>,
+    selected: bool,
+    pan: Vec2,
+    style: &'a GraphEditorStyle,
+    connected_outputs: &'a HashSet<OutputId>,
+}
+
+impl<'a, NodeData, DataType, ValueType> GroupNodeWidget<'a, NodeData, DataType, ValueType> {
+    fn show<UserResponse, UserState>(
+        self,
+        pan_zoom: &PanZoom,
+        ui: &mut Ui,
+        user_state: &mut UserState,
+    ) -> (Vec<NodeResponse<UserResponse, NodeData>>, Vec<GroupAction>)
+    where
+        DataType: DataTypeTrait<UserState>,
+        UserResponse: UserResponseTrait,
+        NodeData: NodeDataTrait,
+    {
+        let mut ui = ui.child_ui_with_id_source(
+            Rect::from_min_size(self.group.position + self.pan, vec2(200.0, 200.0)),
+            Layout::default(),
+            self.group_id,
+        );
+        let ui = &mut ui;
+
+        let margin = egui::vec2(15.0, 5.0) * pan_zoom.zoom;
+        let mut responses = Vec::new();
+        let mut actions = Vec::new();
+
+        let background_color = self.style.node_fill;
+        let text_color = self.style.node_text;
+        let titlebar_color = self.style.titlebar_fill;
+
+        ui.visuals_mut().widgets.noninteractive.fg_stroke = Stroke::new(2.0, text_color);
+
+        let outline_shape = ui.painter().add(Shape::Noop);
+        let background_shape = ui.painter().add(Shape::Noop);
+
+        let outer_rect_bounds = ui.available_rect_before_wrap();
+        let mut inner_rect = outer_rect_bounds.shrink2(margin);
+        inner_rect.max.x = inner_rect.max.x.max(inner_rect.min.x);
+        inner_rect.max.y = inner_rect.max.y.max(inner_rect.min.y);
+        let mut child_ui = ui.child_ui(inner_rect, *ui.layout());
+
+        let interaction_rect = ui
+            .ctx()
+            .memory_mut(|mem| {
+                mem.data
+                    .get_temp::<OuterRectMemory>(child_ui.id())
+                    .map(|stored| stored.0)
+            })
+            .unwrap_or(outer_rect_bounds);
+        let window_response = ui.interact(
+            interaction_rect,
+            Id::new((self.group_id, "group")),
+            Sense::click_and_drag(),
+        );
+
+        // Ports whose parameter was deleted are no longer shown.
+        let inputs: Vec<InputId> = self
+            .group
+            .inputs
+            .iter()
+            .copied()
+            .filter(|input| self.graph.inputs.contains_key(*input))
+            .collect();
+        let outputs: Vec<OutputId> = self
+            .group
+            .outputs
+            .iter()
+            .copied()
+            .filter(|output| self.graph.outputs.contains_key(*output))
+            .collect();
+        let graph = self.graph;
+        let port_label = |param: AnyParameterId| {
+            let node = match param {
+                AnyParameterId::Input(input) => &graph[graph[input].node],
+                AnyParameterId::Output(output) => &graph[graph[output].node],
+            };
+            let name = match param {
+                AnyParameterId::Input(input) => node
+                    .inputs
+                    .iter()
+                    .find(|(_, id)| *id == input)
+                    .map(|(name, _)| name.as_str()),
+                AnyParameterId::Output(output) => node
+                    .outputs
+                    .iter()
+                    .find(|(_, id)| *id == output)
+                    .map(|(name, _)| name.as_str()),
+            };
+            format!("{}: {}", node.label, name.unwrap_or_default())
+        };
+
+        let mut title_height = 0.0;
+        let mut input_port_heights = vec![];
+        let mut output_port_heights = vec![];
+        let mut expand_clicked = false;
+
+        child_ui.vertical(|ui| {
+            ui.horizontal(|ui| {
+                ui.add(Label::new(
+                    RichText::new(&self.group.label)
+                        .text_style(TextStyle::Button)
+                        .color(text_color),
+                ));
+                ui.add_space(8.0 * pan_zoom.zoom); // The size of the little cross icon
+            });
+            ui.add_space(margin.y);
+            title_height = ui.min_size().y;
+
+            for input in &inputs {
+                let height_before = ui.min_rect().bottom();
+                ui.label(port_label(AnyParameterId::Input(*input)));
+                let height_after = ui.min_rect().bottom();
+                input_port_heights.push((height_before + height_after) / 2.0);
             }
-        } else if resp.hovered() {
-            if dark_mode {
-                color_from_hex("#aaaaaa").unwrap()
-            } else {
-                color_from_hex("#222222").unwrap()
+            for output in &outputs {
+                let height_before = ui.min_rect().bottom();
+                ui.label(port_label(AnyParameterId::Output(*output)));
+                let height_after = ui.min_rect().bottom();
+                output_port_heights.push((height_before + height_after) / 2.0);
             }
+
+            ui.add_space(margin.y);
+            expand_clicked = ui.small_button("Expand").clicked();
+        });
+
+        let outer_rect = child_ui.min_rect().expand2(margin);
+        ui.ctx().memory_mut(|mem| {
+            mem.data
+                .insert_temp(child_ui.id(), OuterRectMemory(outer_rect))
+        });
+
+        for (input, port_height) in inputs.iter().zip(input_port_heights) {
+            draw_port(
+                self.style,
+                pan_zoom,
+                ui,
+                self.graph,
+                self.graph[*input].node,
+                user_state,
+                pos2(outer_rect.left(), port_height),
+                &mut responses,
+                AnyParameterId::Input(*input),
+                self.port_locations,
+                self.ongoing_drag,
+                self.graph.connection(*input).is_some(),
+                self.connected_outputs,
+            );
+        }
+        for (output, port_height) in outputs.iter().zip(output_port_heights) {
+            draw_port(
+                self.style,
+                pan_zoom,
+                ui,
+                self.graph,
+                self.graph[*output].node,
+                user_state,
+                pos2(outer_rect.right(), port_height),
+                &mut responses,
+                AnyParameterId::Output(*output),
+                self.port_locations,
+                self.ongoing_drag,
+                false,
+                self.connected_outputs,
+            );
+        }
+
+        // The double border tells group nodes apart from regular nodes.
+        let rounding = Rounding::same(self.style.node_rounding * pan_zoom.zoom);
+        let titlebar_rect = Rect::from_min_size(
+            outer_rect.min,
+            vec2(outer_rect.width(), title_height + margin.y),
+        );
+        let background = Shape::Vec(vec![
+            Shape::Rect(RectShape {
+                rect: outer_rect,
+                rounding,
+                fill: background_color,
+                stroke: Stroke::new(2.0 * pan_zoom.zoom, titlebar_color),
+            }),
+            Shape::Rect(RectShape {
+                rect: outer_rect.shrink(4.0 * pan_zoom.zoom),
+                rounding,
+                fill: Color32::TRANSPARENT,
+                stroke: Stroke::new(1.0 * pan_zoom.zoom, titlebar_color),
+            }),
+            Shape::Rect(RectShape {
+                rect: titlebar_rect,
+                rounding,
+                fill: titlebar_color,
+                stroke: Stroke::NONE,
+            }),
+        ]);
+        let outline = if self.selected {
+            Shape::Rect(RectShape {
+                rect: outer_rect.expand(self.style.selection_outline_width * pan_zoom.zoom),
+                rounding,
+                fill: self.style.selection_outline,
+                stroke: Stroke::NONE,
+            })
         } else {
-            #[allow(clippy::collapsible_else_if)]
-            if dark_mode {
-                color_from_hex("#aaaaaa").unwrap()
-            } else {
-                color_from_hex("#555555").unwrap()
-            }
-        };
-        let stroke = Stroke {
-            width: stroke_width,
-            color,
+            Shape::Noop
         };
+        ui.painter().set(background_shape, background);
+        ui.painter().set(outline_shape, outline);
+        self.group_rects.insert(self.group_id, outer_rect);
 
-        ui.painter()
-            .line_segment([rect.left_top(), rect.right_bottom()], stroke);
-        ui.painter()
-            .line_segment([rect.right_top(), rect.left_bottom()], stroke);
+        // --- Interaction ---
+
+        if close_button(self.style, pan_zoom, ui, outer_rect).clicked() {
+            actions.push(GroupAction::Delete);
+        }
+        if expand_clicked {
+            actions.push(GroupAction::Expand);
+        }
+
+        let drag_delta = window_response.drag_delta();
+        if drag_delta.length_sq() > 0.0 {
+            actions.push(GroupAction::Move(drag_delta));
+        }
+
+        if responses.is_empty() && actions.is_empty() {
+            if window_response.double_clicked() {
+                actions.push(GroupAction::Enter);
+            } else if window_response.clicked_by(PointerButton::Primary) {
+                actions.push(GroupAction::Select);
+            }
+        }
 
-        resp
+        (responses, actions)
     }
 }
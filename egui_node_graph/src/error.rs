@@ -10,4 +10,10 @@ pub enum EguiGraphError {
     
     #[error("Node {0:?} was not found in the graph.")]
     InvalidNodeId(NodeId),
+
+    #[error("The connection from {0:?} to {1:?} closes a cycle in the graph.")]
+    Cycle(OutputId, InputId),
+
+    #[error("There is no connection from {0:?} to {1:?}.")]
+    NoConnection(OutputId, InputId),
 }
@@ -1,3 +1,5 @@
+use std::num::NonZeroUsize;
+
 use super::*;
 
 #[cfg(feature = "persistence")]
@@ -35,6 +37,11 @@ fn shown_inline_default() -> bool {
     true
 }
 
+#[cfg(feature = "persistence")]
+pub(crate) fn input_max_connections_default() -> Option<NonZeroUsize> {
+    NonZeroUsize::new(1)
+}
+
 /// An input parameter. Input parameters are inside a node, and represent data
 /// that this node receives. Unlike their [`OutputParam`] counterparts, input
 /// parameters also display an inline widget which allows setting its "value".
@@ -58,6 +65,14 @@ pub struct InputParam<DataType, ValueType> {
     /// When true, the node is shown inline inside the node graph.
     #[cfg_attr(feature = "persistence", serde(default = "shown_inline_default"))]
     pub shown_inline: bool,
+    /// How many connections this input accepts, or `None` for no limit.
+    /// Inputs accept a single connection unless created with
+    /// [`Graph::add_wide_input_param`].
+    #[cfg_attr(
+        feature = "persistence",
+        serde(default = "input_max_connections_default")
+    )]
+    pub max_connections: Option<NonZeroUsize>,
 }
 
 /// An output parameter. Output parameters are inside a node, and represent the
@@ -71,6 +86,9 @@ pub struct OutputParam<DataType> {
     /// Back-reference to the node containing this parameter.
     pub node: NodeId,
     pub typ: DataType,
+    /// How many connections can leave this output, or `None` for no limit.
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub max_connections: Option<NonZeroUsize>,
 }
 
 /// The graph, containing nodes, input parameters and output parameters. Because
@@ -85,7 +103,45 @@ pub struct Graph<NodeData, DataType, ValueType> {
     pub inputs: SlotMap<InputId, InputParam<DataType, ValueType>>,
     /// The [`OutputParam`]s of the graph
     pub outputs: SlotMap<OutputId, OutputParam<DataType>>,
-    // Connects the input of a node, to the output of its predecessor that
-    // produces it
-    pub connections: SecondaryMap<InputId, OutputId>,
+    // Connects the input of a node, to the outputs of its predecessors that
+    // produce it, in the order they were connected
+    #[cfg_attr(
+        feature = "persistence",
+        serde(deserialize_with = "deserialize_connections")
+    )]
+    pub connections: SecondaryMap<InputId, SVec<OutputId>>,
+    /// The changes made to the graph, for user code to react to them. It is
+    /// not saved.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub journal: ChangeJournal,
+}
+
+/// Reads [`Graph::connections`]. Graphs saved before inputs could have several
+/// connections store a single output per input, which is read as a list of
+/// one. Binary formats only have the current layout.
+#[cfg(feature = "persistence")]
+fn deserialize_connections<'de, D>(
+    deserializer: D,
+) -> Result<SecondaryMap<InputId, SVec<OutputId>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Connections {
+        Single(OutputId),
+        Several(SVec<OutputId>),
+    }
+
+    if !deserializer.is_human_readable() {
+        return SecondaryMap::deserialize(deserializer);
+    }
+    let connections = SecondaryMap::<InputId, Connections>::deserialize(deserializer)?;
+    Ok(connections
+        .into_iter()
+        .map(|(input, outputs)| match outputs {
+            Connections::Single(output) => (input, std::iter::once(output).collect()),
+            Connections::Several(outputs) => (input, outputs),
+        })
+        .collect())
 }
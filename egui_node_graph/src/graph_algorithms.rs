@@ -0,0 +1,281 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use super::*;
+
+/// A single connection, seen from one of its two nodes. `node` is the node at
+/// the *other* end of the connection.
+#[derive(Clone, Copy, Debug)]
+struct Link {
+    output: OutputId,
+    input: InputId,
+    node: NodeId,
+}
+
+type Adjacency = HashMap<NodeId, Vec<Link>>;
+
+/// The direction in which connections are followed when walking the graph.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WalkDirection {
+    /// From inputs to the outputs that feed them.
+    Upstream,
+    /// From outputs to the inputs they feed.
+    Downstream,
+}
+
+/// Breadth-first iterator over all the nodes reachable from a starting node,
+/// following connections in a single [`WalkDirection`]. The starting node is
+/// not yielded, and every node is yielded at most once even if the graph
+/// contains cycles.
+///
+/// Created by [`Graph::upstream_nodes`] and [`Graph::downstream_nodes`].
+#[derive(Clone, Debug)]
+pub struct GraphWalk {
+    adjacency: Adjacency,
+    queue: VecDeque<NodeId>,
+    visited: HashSet<NodeId>,
+}
+
+impl GraphWalk {
+    fn new(adjacency: Adjacency, start: NodeId) -> Self {
+        let mut walk = Self {
+            adjacency,
+            queue: VecDeque::new(),
+            visited: HashSet::from([start]),
+        };
+        walk.enqueue_neighbours(start);
+        walk
+    }
+
+    fn enqueue_neighbours(&mut self, node: NodeId) {
+        for link in self.adjacency.get(&node).map(Vec::as_slice).unwrap_or(&[]) {
+            if self.visited.insert(link.node) {
+                self.queue.push_back(link.node);
+            }
+        }
+    }
+}
+
+impl Iterator for GraphWalk {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        let node = self.queue.pop_front()?;
+        self.enqueue_neighbours(node);
+        Some(node)
+    }
+}
+
+impl<NodeData, DataType, ValueType> Graph<NodeData, DataType, ValueType> {
+    /// Builds the adjacency lists of the graph in the given direction.
+    /// Connections referencing deleted parameters are skipped.
+    fn adjacency(&self, direction: WalkDirection) -> Adjacency {
+        let mut adjacency = Adjacency::new();
+        for (input, output) in self.iter_connections() {
+            let (Some(input_param), Some(output_param)) =
+                (self.try_get_input(input), self.try_get_output(output))
+            else {
+                continue;
+            };
+            let (from, to) = match direction {
+                WalkDirection::Downstream => (output_param.node, input_param.node),
+                WalkDirection::Upstream => (input_param.node, output_param.node),
+            };
+            adjacency.entry(from).or_default().push(Link {
+                output,
+                input,
+                node: to,
+            });
+        }
+        adjacency
+    }
+
+    /// Returns an iterator over every node that (transitively) feeds data into
+    /// `node_id`, closest nodes first.
+    pub fn upstream_nodes(&self, node_id: NodeId) -> GraphWalk {
+        GraphWalk::new(self.adjacency(WalkDirection::Upstream), node_id)
+    }
+
+    /// Returns an iterator over every node that (transitively) receives data
+    /// from `node_id`, closest nodes first.
+    pub fn downstream_nodes(&self, node_id: NodeId) -> GraphWalk {
+        GraphWalk::new(self.adjacency(WalkDirection::Downstream), node_id)
+    }
+
+    /// Returns whether `to` can be reached from `from` by following
+    /// connections downstream. A node is always reachable from itself.
+    pub fn is_reachable(&self, from: NodeId, to: NodeId) -> bool {
+        from == to || self.downstream_nodes(from).any(|node| node == to)
+    }
+
+    /// Returns all the nodes of the graph sorted so that every node comes
+    /// after all the nodes feeding its inputs.
+    ///
+    /// If the graph contains a cycle, an [`EguiGraphError::Cycle`] is returned
+    /// with the connection that closes it.
+    pub fn topological_order(&self) -> Result<Vec<NodeId>, EguiGraphError> {
+        let adjacency = self.adjacency(WalkDirection::Downstream);
+        let mut finished = HashSet::new();
+        let mut on_stack = HashSet::new();
+        let mut order = Vec::with_capacity(self.nodes.len());
+
+        for root in self.iter_nodes() {
+            if finished.contains(&root) {
+                continue;
+            }
+            // Iterative DFS. Each stack entry is a node and the index of the
+            // next outgoing link to explore.
+            let mut stack = vec![(root, 0)];
+            on_stack.insert(root);
+            while let Some(&(node, next_link)) = stack.last() {
+                let links = adjacency.get(&node).map(Vec::as_slice).unwrap_or(&[]);
+                if let Some(link) = links.get(next_link) {
+                    stack.last_mut().unwrap().1 += 1;
+                    if on_stack.contains(&link.node) {
+                        return Err(EguiGraphError::Cycle(link.output, link.input));
+                    }
+                    if !finished.contains(&link.node) {
+                        on_stack.insert(link.node);
+                        stack.push((link.node, 0));
+                    }
+                } else {
+                    stack.pop();
+                    on_stack.remove(&node);
+                    finished.insert(node);
+                    order.push(node);
+                }
+            }
+        }
+
+        order.reverse();
+        Ok(order)
+    }
+
+    /// Returns the strongly connected components of the graph. Every node
+    /// belongs to exactly one component, and a component with more than one
+    /// node (or a single node connected to itself) is a cycle.
+    ///
+    /// Components are returned in topological order: no component receives
+    /// data from a component that comes after it.
+    pub fn strongly_connected_components(&self) -> Vec<Vec<NodeId>> {
+        // Kosaraju's algorithm: nodes are first sorted by decreasing DFS
+        // finish time, and then each component is collected by walking the
+        // reversed graph in that order.
+        let downstream = self.adjacency(WalkDirection::Downstream);
+        let upstream = self.adjacency(WalkDirection::Upstream);
+
+        let mut visited = HashSet::new();
+        let mut finish_order = Vec::with_capacity(self.nodes.len());
+        for root in self.iter_nodes() {
+            if !visited.insert(root) {
+                continue;
+            }
+            let mut stack = vec![(root, 0)];
+            while let Some(&(node, next_link)) = stack.last() {
+                let links = downstream.get(&node).map(Vec::as_slice).unwrap_or(&[]);
+                if let Some(link) = links.get(next_link) {
+                    stack.last_mut().unwrap().1 += 1;
+                    if visited.insert(link.node) {
+                        stack.push((link.node, 0));
+                    }
+                } else {
+                    stack.pop();
+                    finish_order.push(node);
+                }
+            }
+        }
+
+        let mut assigned = HashSet::new();
+        let mut components = Vec::new();
+        for root in finish_order.into_iter().rev() {
+            if !assigned.insert(root) {
+                continue;
+            }
+            let mut component = vec![root];
+            let mut stack = vec![root];
+            while let Some(node) = stack.pop() {
+                for link in upstream.get(&node).map(Vec::as_slice).unwrap_or(&[]) {
+                    if assigned.insert(link.node) {
+                        component.push(link.node);
+                        stack.push(link.node);
+                    }
+                }
+            }
+            components.push(component);
+        }
+        components
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    #[test]
+    fn test_topological_order() {
+        let mut graph = TestGraph::new();
+        let (a, _, a_out) = add_node(&mut graph);
+        let (b, b_in, b_out) = add_node(&mut graph);
+        let (c, c_in, _) = add_node(&mut graph);
+        graph.add_connection(b_out, c_in);
+        graph.add_connection(a_out, b_in);
+
+        let order = graph.topological_order().unwrap();
+        let position = |node| order.iter().position(|n| *n == node).unwrap();
+        assert_eq!(order.len(), 3);
+        assert!(position(a) < position(b));
+        assert!(position(b) < position(c));
+    }
+
+    #[test]
+    fn test_cycle_detection() {
+        let mut graph = TestGraph::new();
+        let (_, a_in, a_out) = add_node(&mut graph);
+        let (_, b_in, b_out) = add_node(&mut graph);
+        graph.add_connection(a_out, b_in);
+        graph.add_connection(b_out, a_in);
+
+        match graph.topological_order() {
+            Err(EguiGraphError::Cycle(output, input)) => {
+                assert!((output, input) == (a_out, b_in) || (output, input) == (b_out, a_in));
+            }
+            other => panic!("Expected a cycle error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_strongly_connected_components() {
+        let mut graph = TestGraph::new();
+        let (a, a_in, a_out) = add_node(&mut graph);
+        let (b, b_in, b_out) = add_node(&mut graph);
+        let (c, c_in, _) = add_node(&mut graph);
+        graph.add_connection(a_out, b_in);
+        graph.add_connection(b_out, a_in);
+        graph.add_connection(b_out, c_in);
+
+        let mut components = graph.strongly_connected_components();
+        assert_eq!(components.len(), 2);
+        components[0].sort();
+        let mut cycle = vec![a, b];
+        cycle.sort();
+        assert_eq!(components[0], cycle);
+        assert_eq!(components[1], vec![c]);
+    }
+
+    #[test]
+    fn test_reachability() {
+        let mut graph = TestGraph::new();
+        let (a, _, a_out) = add_node(&mut graph);
+        let (b, b_in, b_out) = add_node(&mut graph);
+        let (c, c_in, _) = add_node(&mut graph);
+        let (d, _, _) = add_node(&mut graph);
+        graph.add_connection(a_out, b_in);
+        graph.add_connection(b_out, c_in);
+
+        assert!(graph.is_reachable(a, c));
+        assert!(!graph.is_reachable(c, a));
+        assert!(!graph.is_reachable(a, d));
+        assert_eq!(graph.downstream_nodes(a).collect::<Vec<_>>(), vec![b, c]);
+        assert_eq!(graph.upstream_nodes(c).collect::<Vec<_>>(), vec![b, a]);
+    }
+}
@@ -1,3 +1,5 @@
+use std::num::NonZeroUsize;
+
 use super::*;
 
 impl<NodeData, DataType, ValueType> Graph<NodeData, DataType, ValueType> {
@@ -7,12 +9,14 @@ impl<NodeData, DataType, ValueType> Graph<NodeData, DataType, ValueType> {
             inputs: SlotMap::default(),
             outputs: SlotMap::default(),
             connections: SecondaryMap::default(),
+            journal: ChangeJournal::default(),
         }
     }
 
     pub fn rename_node(&mut self, node_id: NodeId, label: String) -> Result<(), EguiGraphError> {
         self.nodes.get_mut(node_id).map_or(Err(EguiGraphError::InvalidNodeId(node_id)), |x| {
             x.label = label;
+            self.journal.record(GraphChange::NodeRenamed(node_id));
             Ok(())
         })
     }
@@ -33,6 +37,7 @@ impl<NodeData, DataType, ValueType> Graph<NodeData, DataType, ValueType> {
                 user_data,
             }
         });
+        self.journal.record(GraphChange::NodeAdded(node_id));
 
         f(self, node_id);
 
@@ -47,6 +52,30 @@ impl<NodeData, DataType, ValueType> Graph<NodeData, DataType, ValueType> {
         value: ValueType,
         kind: InputParamKind,
         shown_inline: bool,
+    ) -> InputId {
+        self.add_wide_input_param(
+            node_id,
+            name,
+            typ,
+            value,
+            kind,
+            NonZeroUsize::new(1),
+            shown_inline,
+        )
+    }
+
+    /// Like [`Graph::add_input_param`], but the input accepts up to
+    /// `max_connections` connections, or any number of them when `None`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_wide_input_param(
+        &mut self,
+        node_id: NodeId,
+        name: String,
+        typ: DataType,
+        value: ValueType,
+        kind: InputParamKind,
+        max_connections: Option<NonZeroUsize>,
+        shown_inline: bool,
     ) -> InputId {
         let input_id = self.inputs.insert_with_key(|input_id| InputParam {
             id: input_id,
@@ -55,8 +84,10 @@ impl<NodeData, DataType, ValueType> Graph<NodeData, DataType, ValueType> {
             kind,
             node: node_id,
             shown_inline,
+            max_connections,
         });
         self.nodes[node_id].inputs.push((name, input_id));
+        self.journal.record(GraphChange::InputAdded { node: node_id, input: input_id });
         input_id
     }
 
@@ -64,14 +95,27 @@ impl<NodeData, DataType, ValueType> Graph<NodeData, DataType, ValueType> {
         let node = self[param].node;
         self[node].inputs.retain(|(_, id)| *id != param);
         self.inputs.remove(param);
-        self.connections.retain(|i, _| i != param);
+        for output in self.connections.remove(param).unwrap_or_default() {
+            self.journal.record(GraphChange::Disconnected { output, input: param });
+        }
+        self.journal.record(GraphChange::InputRemoved { node, input: param });
     }
 
     pub fn remove_output_param(&mut self, param: OutputId) {
         let node = self[param].node;
         self[node].outputs.retain(|(_, id)| *id != param);
         self.outputs.remove(param);
-        self.connections.retain(|_, o| *o != param);
+        let journal = &mut self.journal;
+        self.connections.retain(|input, outputs| {
+            outputs.retain(|o| {
+                if *o == param {
+                    journal.record(GraphChange::Disconnected { output: param, input });
+                }
+                *o != param
+            });
+            !outputs.is_empty()
+        });
+        self.journal.record(GraphChange::OutputRemoved { node, output: param });
     }
 
     pub fn add_output_param(&mut self, node_id: NodeId, name: String, typ: DataType) -> OutputId {
@@ -79,8 +123,10 @@ impl<NodeData, DataType, ValueType> Graph<NodeData, DataType, ValueType> {
             id: output_id,
             node: node_id,
             typ,
+            max_connections: None,
         });
         self.nodes[node_id].outputs.push((name, output_id));
+        self.journal.record(GraphChange::OutputAdded { node: node_id, output: output_id });
         output_id
     }
 
@@ -96,11 +142,13 @@ impl<NodeData, DataType, ValueType> Graph<NodeData, DataType, ValueType> {
         let disconnect_events = self.remove_all_nodes_connections(node_id);
         
         let removed_node = self.nodes.remove(node_id).expect("Node should exist");
+        self.journal.record(GraphChange::NodeRemoved(node_id));
 
         (removed_node, disconnect_events)
     }
 
-    /// Removes any incoming or outgoing connections from the selected node.
+    /// Removes any incoming or outgoing connections from the selected node,
+    /// along with its parameters.
     ///
     /// This function returns the list of connections that has been removed.
     /// Note that one of the two ids in the pair (the one on `node_id`'s end)
@@ -108,44 +156,145 @@ impl<NodeData, DataType, ValueType> Graph<NodeData, DataType, ValueType> {
     pub fn remove_all_nodes_connections(&mut self, node_id: NodeId) -> Vec<(InputId, OutputId)> {
         let mut disconnect_events = vec![];
 
-        self.connections.retain(|i, o| {
-            if self.outputs[*o].node == node_id || self.inputs[i].node == node_id {
-                disconnect_events.push((i, *o));
-                false
-            } else {
-                true
-            }
+        self.connections.retain(|i, outputs| {
+            outputs.retain(|o| {
+                if self.outputs[*o].node == node_id || self.inputs[i].node == node_id {
+                    disconnect_events.push((i, *o));
+                    false
+                } else {
+                    true
+                }
+            });
+            !outputs.is_empty()
         });
 
         // NOTE: Collect is needed because we can't borrow the input ids while
         // we remove them inside the loop.
+        for (input, output) in &disconnect_events {
+            self.journal.record(GraphChange::Disconnected { output: *output, input: *input });
+        }
         for input in self[node_id].input_ids().collect::<SVec<_>>() {
             self.inputs.remove(input);
+            self.journal.record(GraphChange::InputRemoved { node: node_id, input });
         }
         for output in self[node_id].output_ids().collect::<SVec<_>>() {
             self.outputs.remove(output);
+            self.journal.record(GraphChange::OutputRemoved { node: node_id, output });
         }
         disconnect_events
     }
 
-    pub fn remove_connection(&mut self, input_id: InputId) -> Option<OutputId> {
-        self.connections.remove(input_id)
+    /// Removes the connection from `output_id` to `input_id`. Returns whether
+    /// it existed.
+    pub fn remove_connection(&mut self, input_id: InputId, output_id: OutputId) -> bool {
+        let Some(outputs) = self.connections.get_mut(input_id) else {
+            return false;
+        };
+        let count = outputs.len();
+        outputs.retain(|o| *o != output_id);
+        let removed = outputs.len() != count;
+        if outputs.is_empty() {
+            self.connections.remove(input_id);
+        }
+        if removed {
+            self.journal.record(GraphChange::Disconnected { output: output_id, input: input_id });
+        }
+        removed
+    }
+
+    /// Removes all the connections to `input_id`. Returns the outputs it was
+    /// connected to.
+    pub fn remove_connections(&mut self, input_id: InputId) -> SVec<OutputId> {
+        let outputs = self.connections.remove(input_id).unwrap_or_default();
+        for output in &outputs {
+            self.journal.record(GraphChange::Disconnected { output: *output, input: input_id });
+        }
+        outputs
+    }
+
+    /// Sets the value of `input`, recording a [`GraphChange::ValueChanged`].
+    pub fn set_input_value(&mut self, input: InputId, value: ValueType) -> Result<(), EguiGraphError> {
+        let param = self.inputs.get_mut(input)
+            .ok_or(EguiGraphError::InvalidParameterId(AnyParameterId::Input(input)))?;
+        param.value = value;
+        self.journal.record(GraphChange::ValueChanged(input));
+        Ok(())
     }
 
     pub fn iter_nodes(&self) -> impl Iterator<Item = NodeId> + '_ {
         self.nodes.iter().map(|(id, _)| id)
     }
 
-    pub fn add_connection(&mut self, output: OutputId, input: InputId) {
-        self.connections.insert(input, output);
+    /// Connects `output` to `input`. When either of them already has as many
+    /// connections as it accepts, connections are removed to make room: the
+    /// oldest ones of the input, and the first ones of the output. See
+    /// [`Graph::replaced_connections`].
+    ///
+    /// Returns the removed connections as input-output pairs.
+    pub fn add_connection(&mut self, output: OutputId, input: InputId) -> Vec<(InputId, OutputId)> {
+        if self.connections(input).contains(&output) {
+            return Vec::new();
+        }
+        let replaced = self.replaced_connections(output, input);
+        for (i, o) in &replaced {
+            self.remove_connection(*i, *o);
+        }
+        if let Some(entry) = self.connections.entry(input) {
+            entry.or_default().push(output);
+            self.journal.record(GraphChange::Connected { output, input });
+        }
+        replaced
+    }
+
+    /// The connections [`Graph::add_connection`] would remove to connect
+    /// `output` to `input` without going over their
+    /// [`InputParam::max_connections`] and [`OutputParam::max_connections`].
+    pub fn replaced_connections(
+        &self,
+        output: OutputId,
+        input: InputId,
+    ) -> Vec<(InputId, OutputId)> {
+        let mut replaced = Vec::new();
+        if self.connections(input).contains(&output) {
+            return replaced;
+        }
+        if let Some(max) = self.inputs.get(input).and_then(|i| i.max_connections) {
+            let outputs = self.connections(input);
+            let excess = (outputs.len() + 1).saturating_sub(max.get());
+            replaced.extend(outputs[..excess].iter().map(|o| (input, *o)));
+        }
+        if let Some(max) = self.outputs.get(output).and_then(|o| o.max_connections) {
+            let inputs: Vec<InputId> = self.output_connections(output).collect();
+            let excess = (inputs.len() + 1).saturating_sub(max.get());
+            replaced.extend(inputs[..excess].iter().map(|i| (*i, output)));
+        }
+        replaced
     }
 
     pub fn iter_connections(&self) -> impl Iterator<Item = (InputId, OutputId)> + '_ {
-        self.connections.iter().map(|(o, i)| (o, *i))
+        self.connections
+            .iter()
+            .flat_map(|(i, outputs)| outputs.iter().map(move |o| (i, *o)))
     }
 
+    /// The first output connected to `input`. Use [`Graph::connections`] for
+    /// inputs accepting several connections.
     pub fn connection(&self, input: InputId) -> Option<OutputId> {
-        self.connections.get(input).copied()
+        self.connections(input).first().copied()
+    }
+
+    /// The outputs connected to `input`, in the order they were connected.
+    pub fn connections(&self, input: InputId) -> &[OutputId] {
+        self.connections
+            .get(input)
+            .map_or(&[], |outputs| outputs.as_slice())
+    }
+
+    /// The inputs `output` is connected to.
+    pub fn output_connections(&self, output: OutputId) -> impl Iterator<Item = InputId> + '_ {
+        self.iter_connections()
+            .filter(move |(_, o)| *o == output)
+            .map(|(i, _)| i)
     }
 
     pub fn any_param_type(&self, param: AnyParameterId) -> Result<&DataType, EguiGraphError> {
@@ -232,3 +381,134 @@ impl<DataType, ValueType> InputParam<DataType, ValueType> {
         self.node
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_connection_limits() {
+        let mut graph = Graph::<(), (), ()>::new();
+        let add_node = |graph: &mut Graph<(), (), ()>, wide: bool| {
+            let node = graph.add_node("node".into(), (), |_, _| {});
+            let max = if wide { None } else { NonZeroUsize::new(1) };
+            let input = graph.add_wide_input_param(
+                node,
+                "in".into(),
+                (),
+                (),
+                InputParamKind::ConnectionOnly,
+                max,
+                true,
+            );
+            (input, graph.add_output_param(node, "out".into(), ()))
+        };
+        let (a_in, a_out) = add_node(&mut graph, false);
+        let (b_in, b_out) = add_node(&mut graph, true);
+        let (_, c_out) = add_node(&mut graph, false);
+
+        // Single inputs replace their connection
+        assert!(graph.add_connection(b_out, a_in).is_empty());
+        assert_eq!(graph.add_connection(c_out, a_in), vec![(a_in, b_out)]);
+        assert_eq!(graph.connections(a_in), &[c_out]);
+
+        // Wide inputs fan in
+        graph.add_connection(a_out, b_in);
+        graph.add_connection(c_out, b_in);
+        assert_eq!(graph.connections(b_in), &[a_out, c_out]);
+        assert_eq!(graph.connection(b_in), Some(a_out));
+        assert!(graph.add_connection(c_out, b_in).is_empty());
+        assert_eq!(graph.iter_connections().count(), 3);
+
+        // Limited outputs replace one of their connections
+        graph.outputs[c_out].max_connections = NonZeroUsize::new(1);
+        assert_eq!(graph.replaced_connections(c_out, b_in), vec![]);
+        let replaced = graph.add_connection(c_out, b_in);
+        assert!(replaced.is_empty());
+        graph.remove_connection(a_in, c_out);
+        assert_eq!(
+            graph.output_connections(c_out).collect::<Vec<_>>(),
+            vec![b_in]
+        );
+        let (d_in, _) = add_node(&mut graph, false);
+        assert_eq!(graph.add_connection(c_out, d_in), vec![(b_in, c_out)]);
+        assert_eq!(graph.connections(b_in), &[a_out]);
+
+        assert!(graph.remove_connection(b_in, a_out));
+        assert!(!graph.remove_connection(b_in, a_out));
+        assert!(graph.connections.get(b_in).is_none());
+    }
+
+    #[test]
+    fn test_change_journal() {
+        let mut graph = Graph::<(), (), u32>::new();
+        let a = graph.add_node("a".into(), (), |_, _| {});
+        // Nothing is recorded until the journal is enabled
+        assert!(graph.journal.is_empty());
+        graph.journal.set_enabled(true);
+
+        let a_out = graph.add_output_param(a, "out".into(), ());
+        let b = graph.add_node("b".into(), (), |graph, b| {
+            graph.add_input_param(b, "in".into(), (), 0, InputParamKind::ConnectionOrConstant, true);
+        });
+        let b_in = graph[b].get_input("in").unwrap();
+        graph.add_connection(a_out, b_in);
+        graph.set_input_value(b_in, 3).unwrap();
+        graph.rename_node(b, "c".into()).unwrap();
+        assert_eq!(
+            graph.journal.drain().collect::<Vec<_>>(),
+            vec![
+                GraphChange::OutputAdded { node: a, output: a_out },
+                GraphChange::NodeAdded(b),
+                GraphChange::InputAdded { node: b, input: b_in },
+                GraphChange::Connected { output: a_out, input: b_in },
+                GraphChange::ValueChanged(b_in),
+                GraphChange::NodeRenamed(b),
+            ]
+        );
+        assert!(graph.journal.is_empty());
+
+        // Removing a node reports everything removed with it
+        graph.remove_node(a);
+        assert_eq!(
+            graph.journal.changes(),
+            &[
+                GraphChange::Disconnected { output: a_out, input: b_in },
+                GraphChange::OutputRemoved { node: a, output: a_out },
+                GraphChange::NodeRemoved(a),
+            ]
+        );
+        // Failed mutations are not recorded
+        assert!(!graph.remove_connection(b_in, a_out));
+        assert!(graph.set_input_value(InputId::default(), 1).is_err());
+        assert_eq!(graph.journal.changes().len(), 3);
+
+        graph.journal.set_enabled(false);
+        assert!(graph.journal.is_empty());
+    }
+
+    #[cfg(feature = "persistence")]
+    #[test]
+    fn test_serialization() {
+        let mut graph = Graph::<String, u32, f32>::new();
+        let a = graph.add_node("a".into(), "data".into(), |_, _| {});
+        let a_out = graph.add_output_param(a, "out".into(), 1);
+        let b = graph.add_node("b".into(), "data".into(), |_, _| {});
+        let b_in = graph.add_input_param(
+            b,
+            "in".into(),
+            1,
+            0.5,
+            InputParamKind::ConnectionOrConstant,
+            true,
+        );
+        graph.add_connection(a_out, b_in);
+
+        let json = serde_json::to_string(&graph).unwrap();
+        let loaded: Graph<String, u32, f32> = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded[b].get_input("in").unwrap(), b_in);
+        assert_eq!(loaded[b_in].value, 0.5);
+        assert_eq!(loaded.connection(b_in), Some(a_out));
+        assert_eq!(loaded[a].user_data, "data");
+    }
+}
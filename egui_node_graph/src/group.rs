@@ -0,0 +1,405 @@
+use std::collections::HashSet;
+
+use super::*;
+
+#[cfg(feature = "persistence")]
+use serde::{Deserialize, Serialize};
+
+/// A set of nodes collapsed into a single group node. Groups only affect how
+/// the graph is displayed: the [`Graph`] itself stays flat, and the
+/// connections to the group node are connections to the nodes inside it.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
+pub struct NodeGroup {
+    pub label: String,
+    /// The group containing this group, or `None` if it is at the root of the
+    /// graph.
+    pub parent: Option<GroupId>,
+    /// The position of the group node, inside its parent.
+    pub position: egui::Pos2,
+    /// The input parameters of the nodes inside the group that are exposed as
+    /// ports of the group node. These are the inputs that received a
+    /// connection from outside the group, when it was collapsed or since. See
+    /// [`GraphEditorState::update_group_ports`].
+    pub inputs: Vec<InputId>,
+    /// The output parameters exposed as ports of the group node. See
+    /// [`NodeGroup::inputs`].
+    pub outputs: Vec<OutputId>,
+}
+
+/// The groups of a [`GraphEditorState`], and the group each node belongs to.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
+pub struct NodeGroups {
+    pub groups: SlotMap<GroupId, NodeGroup>,
+    /// The group directly containing each node. Nodes that are not in this
+    /// map are at the root of the graph.
+    pub node_groups: SecondaryMap<NodeId, GroupId>,
+}
+
+impl NodeGroups {
+    /// The group directly containing `node`, or `None` if it is at the root.
+    pub fn group_of(&self, node: NodeId) -> Option<GroupId> {
+        self.node_groups.get(node).copied()
+    }
+
+    /// Moves `node` to `group`, or to the root of the graph if `None`.
+    pub fn set_group(&mut self, node: NodeId, group: Option<GroupId>) {
+        match group {
+            Some(group) => {
+                self.node_groups.insert(node, group);
+            }
+            None => {
+                self.node_groups.remove(node);
+            }
+        }
+    }
+
+    /// Returns whether `group` is `ancestor` or is (transitively) inside it.
+    pub fn is_inside(&self, group: GroupId, ancestor: GroupId) -> bool {
+        let mut current = Some(group);
+        while let Some(group) = current {
+            if group == ancestor {
+                return true;
+            }
+            current = self.groups.get(group).and_then(|group| group.parent);
+        }
+        false
+    }
+
+    /// Returns whether `node` is (transitively) inside `group`.
+    pub fn node_is_inside(&self, node: NodeId, group: GroupId) -> bool {
+        matches!(self.group_of(node), Some(parent) if self.is_inside(parent, group))
+    }
+
+    /// Returns the groups directly inside `parent`, or at the root if `None`.
+    pub fn children(&self, parent: Option<GroupId>) -> impl Iterator<Item = GroupId> + '_ {
+        self.groups
+            .iter()
+            .filter(move |(_, group)| group.parent == parent)
+            .map(|(id, _)| id)
+    }
+
+    /// Returns the path from the root of the graph to `group`, both included.
+    pub fn path(&self, group: Option<GroupId>) -> Vec<GroupId> {
+        let mut path = Vec::new();
+        let mut current = group;
+        while let Some(group) = current {
+            path.push(group);
+            current = self.groups.get(group).and_then(|group| group.parent);
+        }
+        path.reverse();
+        path
+    }
+}
+
+impl<NodeData, DataType, ValueType, NodeTemplate, UserState, EdgeData>
+    GraphEditorState<NodeData, DataType, ValueType, NodeTemplate, UserState, EdgeData>
+{
+    /// Returns whether `node` is shown in the group currently being edited.
+    pub fn is_node_visible(&self, node: NodeId) -> bool {
+        self.groups.group_of(node) == self.current_group
+    }
+
+    /// Adds `node` to the group currently being edited. Called by the editor
+    /// for the nodes it creates.
+    pub fn add_to_current_group(&mut self, node: NodeId) {
+        self.groups.set_group(node, self.current_group);
+    }
+
+    /// Starts editing the contents of `group`, or the root of the graph if
+    /// `None`. The selection is cleared.
+    pub fn enter_group(&mut self, group: Option<GroupId>) {
+        if let Some(id) = group {
+            if !self.groups.groups.contains_key(id) {
+                return;
+            }
+        }
+        self.current_group = group;
+        self.selected_nodes.clear();
+        self.selected_groups.clear();
+        self.connection_in_progress = None;
+    }
+
+    /// All the nodes (transitively) inside `group`.
+    pub fn nodes_in_group(&self, group: GroupId) -> Vec<NodeId> {
+        self.graph
+            .iter_nodes()
+            .filter(|node| self.groups.node_is_inside(*node, group))
+            .collect()
+    }
+
+    /// Replaces the given nodes and groups with a single group node, placed at
+    /// their top-left corner. Nodes and groups that are not shown in the group
+    /// currently being edited are ignored.
+    ///
+    /// The ports of the group node are the parameters of the collapsed nodes
+    /// that are connected to nodes outside the selection. Returns `None` if
+    /// there was nothing to collapse.
+    pub fn collapse_to_group(
+        &mut self,
+        nodes: &[NodeId],
+        groups: &[GroupId],
+        label: impl Into<String>,
+    ) -> Option<GroupId> {
+        let nodes: Vec<NodeId> = nodes
+            .iter()
+            .copied()
+            .filter(|node| self.graph.nodes.contains_key(*node) && self.is_node_visible(*node))
+            .collect();
+        let groups: Vec<GroupId> = groups
+            .iter()
+            .copied()
+            .filter(|group| {
+                matches!(
+                    self.groups.groups.get(*group),
+                    Some(group) if group.parent == self.current_group
+                )
+            })
+            .collect();
+        if nodes.is_empty() && groups.is_empty() {
+            return None;
+        }
+
+        let mut inner_nodes = nodes.clone();
+        for group in &groups {
+            inner_nodes.extend(self.nodes_in_group(*group));
+        }
+        let inside: HashSet<NodeId> = inner_nodes.iter().copied().collect();
+
+        // Parameters exposed as ports, in the order of the nodes.
+        let mut inputs = Vec::new();
+        let mut outputs = Vec::new();
+        for node in &inner_nodes {
+            for input in self.graph[*node].input_ids() {
+                let crosses = self.graph.connections(input).iter().any(|output| {
+                    matches!(
+                        self.graph.try_get_output(*output),
+                        Some(output) if !inside.contains(&output.node)
+                    )
+                });
+                if crosses {
+                    inputs.push(input);
+                }
+            }
+            for output in self.graph[*node].output_ids() {
+                let crosses = self.graph.iter_connections().any(|(input, o)| {
+                    o == output
+                        && matches!(
+                            self.graph.try_get_input(input),
+                            Some(input) if !inside.contains(&input.node)
+                        )
+                });
+                if crosses {
+                    outputs.push(output);
+                }
+            }
+        }
+
+        let position = nodes
+            .iter()
+            .filter_map(|node| self.node_positions.get(*node).copied())
+            .chain(
+                groups
+                    .iter()
+                    .map(|group| self.groups.groups[*group].position),
+            )
+            .reduce(|a, b| a.min(b))
+            .unwrap_or_default();
+
+        let new_group = self.groups.groups.insert(NodeGroup {
+            label: label.into(),
+            parent: self.current_group,
+            position,
+            inputs,
+            outputs,
+        });
+        for node in &nodes {
+            self.groups.set_group(*node, Some(new_group));
+        }
+        for group in &groups {
+            self.groups.groups[*group].parent = Some(new_group);
+        }
+
+        self.selected_nodes.clear();
+        self.selected_groups = vec![new_group];
+        Some(new_group)
+    }
+
+    /// Removes `group`, moving the nodes and groups it contains to its parent.
+    /// Returns the nodes that were directly inside the group.
+    pub fn expand_group(&mut self, group: GroupId) -> Vec<NodeId> {
+        let Some(removed) = self.groups.groups.remove(group) else {
+            return Vec::new();
+        };
+        let nodes: Vec<NodeId> = self
+            .groups
+            .node_groups
+            .iter()
+            .filter(|(_, g)| **g == group)
+            .map(|(node, _)| node)
+            .collect();
+        for node in &nodes {
+            self.groups.set_group(*node, removed.parent);
+        }
+        for child in self.groups.groups.values_mut() {
+            if child.parent == Some(group) {
+                child.parent = removed.parent;
+            }
+        }
+        for comment in self.comments.values_mut() {
+            if comment.group == Some(group) {
+                comment.group = removed.parent;
+            }
+        }
+
+        if self.current_group == Some(group) {
+            self.current_group = removed.parent;
+        }
+        self.selected_groups.retain(|g| *g != group);
+        if self.current_group == removed.parent {
+            self.selected_nodes = nodes.clone();
+        }
+        nodes
+    }
+
+    /// Exposes the parameters connected across the boundary of a group as
+    /// ports of its group node, and removes the ports of deleted parameters.
+    /// Called by the editor every frame, so the connections made after a
+    /// group was collapsed also show on it.
+    pub fn update_group_ports(&mut self) {
+        let graph = &self.graph;
+        let groups = &mut self.groups;
+        for group in groups.groups.values_mut() {
+            group
+                .inputs
+                .retain(|input| graph.inputs.contains_key(*input));
+            group
+                .outputs
+                .retain(|output| graph.outputs.contains_key(*output));
+        }
+        for (input, output) in graph.iter_connections() {
+            let (Some(input_param), Some(output_param)) =
+                (graph.try_get_input(input), graph.try_get_output(output))
+            else {
+                continue;
+            };
+            let input_path = groups.path(groups.group_of(input_param.node));
+            let output_path = groups.path(groups.group_of(output_param.node));
+            for group in input_path.iter().filter(|g| !output_path.contains(g)) {
+                let inputs = &mut groups.groups[*group].inputs;
+                if !inputs.contains(&input) {
+                    inputs.push(input);
+                }
+            }
+            for group in output_path.iter().filter(|g| !input_path.contains(g)) {
+                let outputs = &mut groups.groups[*group].outputs;
+                if !outputs.contains(&output) {
+                    outputs.push(output);
+                }
+            }
+        }
+    }
+
+    /// Removes `group`, every group inside it and their comments, without
+    /// touching the nodes. Used when the contents of a group are deleted.
+    pub(crate) fn remove_group_tree(&mut self, group: GroupId) {
+        let groups = &self.groups;
+        let removed: Vec<GroupId> = groups
+            .groups
+            .keys()
+            .filter(|g| groups.is_inside(*g, group))
+            .collect();
+        for group in &removed {
+            self.groups.groups.remove(*group);
+        }
+        self.groups.node_groups.retain(|_, g| !removed.contains(g));
+        self.comments
+            .retain(|_, comment| !matches!(comment.group, Some(g) if removed.contains(&g)));
+        self.selected_groups.retain(|g| !removed.contains(g));
+        if let Some(current) = self.current_group {
+            if !self.groups.groups.contains_key(current) {
+                self.current_group = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::*;
+
+    #[test]
+    fn test_collapse_and_expand() {
+        let mut state = TestState::<&str>::default();
+        let (a, _, a_out) = add_placed_node(&mut state, egui::pos2(0.0, 0.0));
+        let (b, b_in, b_out) = add_placed_node(&mut state, egui::pos2(100.0, 0.0));
+        let (c, c_in, c_out) = add_placed_node(&mut state, egui::pos2(200.0, 0.0));
+        let (d, d_in, _) = add_placed_node(&mut state, egui::pos2(300.0, 0.0));
+        state.graph.add_connection(a_out, b_in);
+        state.graph.add_connection(b_out, c_in);
+        state.graph.add_connection(c_out, d_in);
+
+        let group = state.collapse_to_group(&[b, c], &[], "Group").unwrap();
+        let node_group = &state.groups.groups[group];
+        assert_eq!(node_group.inputs, vec![b_in]);
+        assert_eq!(node_group.outputs, vec![c_out]);
+        assert_eq!(node_group.position, egui::pos2(100.0, 0.0));
+        assert!(state.is_node_visible(a) && state.is_node_visible(d));
+        assert!(!state.is_node_visible(b) && !state.is_node_visible(c));
+
+        // Collapsing a group again nests it.
+        let outer = state.collapse_to_group(&[d], &[group], "Outer").unwrap();
+        assert_eq!(state.groups.groups[group].parent, Some(outer));
+        assert_eq!(state.groups.path(Some(group)), vec![outer, group]);
+        assert_eq!(state.nodes_in_group(outer).len(), 3);
+        assert_eq!(state.groups.groups[outer].inputs, vec![b_in]);
+        assert!(state.groups.groups[outer].outputs.is_empty());
+
+        state.enter_group(Some(outer));
+        assert!(state.is_node_visible(d));
+        state.expand_group(outer);
+        assert_eq!(state.current_group, None);
+        assert_eq!(state.groups.groups[group].parent, None);
+
+        // Connections made after collapsing the group are exposed too.
+        let (e, e_in, _) = add_placed_node(&mut state, egui::pos2(400.0, 0.0));
+        state.groups.set_group(e, Some(group));
+        state.graph.add_connection(a_out, e_in);
+        state.update_group_ports();
+        assert_eq!(state.groups.groups[group].inputs, vec![b_in, e_in]);
+        assert_eq!(state.groups.groups[group].outputs, vec![c_out]);
+
+        assert_eq!(state.expand_group(group), vec![b, c, e]);
+        assert!(state.groups.groups.is_empty());
+        assert!(state
+            .graph
+            .iter_nodes()
+            .all(|node| state.is_node_visible(node)));
+    }
+
+    #[test]
+    fn test_groups_keep_edge_data() {
+        let mut state = TestState::<&str>::default();
+        let (_, _, a_out) = add_placed_node(&mut state, egui::pos2(0.0, 0.0));
+        let (b, b_in, b_out) = add_placed_node(&mut state, egui::pos2(100.0, 0.0));
+        let (c, c_in, _) = add_placed_node(&mut state, egui::pos2(200.0, 0.0));
+        state.graph.add_connection(a_out, b_in);
+        state.graph.add_connection(b_out, c_in);
+        state.set_edge_data(a_out, b_in, "into the group").unwrap();
+        state
+            .set_edge_data(b_out, c_in, "inside the group")
+            .unwrap();
+
+        // The connections to the group node are those of the nodes inside it.
+        let group = state.collapse_to_group(&[b, c], &[], "Group").unwrap();
+        state.prune_edge_data();
+        assert_eq!(state.edge_data(a_out, b_in), Some(&"into the group"));
+        assert_eq!(state.edge_data(b_out, c_in), Some(&"inside the group"));
+
+        state.expand_group(group);
+        state.prune_edge_data();
+        assert_eq!(state.edge_data(a_out, b_in), Some(&"into the group"));
+        assert_eq!(state.edge_data(b_out, c_in), Some(&"inside the group"));
+    }
+}
@@ -0,0 +1,683 @@
+use std::collections::{HashMap, HashSet};
+
+use super::*;
+use egui::{pos2, vec2, Pos2, Rect, Vec2};
+
+#[cfg(feature = "persistence")]
+use serde::{Deserialize, Serialize};
+
+/// The size assumed for nodes that have not been drawn yet, at zoom 1.
+const DEFAULT_NODE_SIZE: Vec2 = vec2(200.0, 120.0);
+
+/// The algorithms available to [`GraphEditorState::auto_layout`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
+pub enum LayoutStyle {
+    /// Nodes are placed in columns following the data flow from left to
+    /// right, ordered to reduce wire crossings.
+    Layered,
+    /// The nodes with execution ports (see [`DataTypeTrait::is_execution`])
+    /// are laid out as a spine following the execution flow, and the nodes
+    /// computing their data hang below them.
+    ExecutionSpine,
+}
+
+/// Configuration of [`GraphEditorState::auto_layout`].
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
+pub struct LayoutSettings {
+    pub style: LayoutStyle,
+    /// Horizontal space between two columns of nodes, at zoom 1.
+    pub layer_spacing: f32,
+    /// Vertical space between two nodes of the same column, at zoom 1.
+    pub node_spacing: f32,
+    /// Number of sweeps through the columns to reduce wire crossings.
+    pub crossing_sweeps: usize,
+    /// Duration of the transition to the new positions, in seconds. The
+    /// nodes are moved at once when zero.
+    pub animation_duration: f32,
+}
+
+impl Default for LayoutSettings {
+    fn default() -> Self {
+        Self {
+            style: LayoutStyle::Layered,
+            layer_spacing: 80.0,
+            node_spacing: 30.0,
+            crossing_sweeps: 8,
+            animation_duration: 0.3,
+        }
+    }
+}
+
+/// An ongoing transition of the nodes to the positions computed by
+/// [`GraphEditorState::auto_layout`].
+#[derive(Clone, Debug, Default)]
+pub struct LayoutAnimation {
+    pub from: HashMap<NodeId, Pos2>,
+    pub to: HashMap<NodeId, Pos2>,
+    pub elapsed: f32,
+    pub duration: f32,
+}
+
+impl<NodeData, DataType, ValueType, NodeTemplate, UserState, EdgeData>
+    GraphEditorState<NodeData, DataType, ValueType, NodeTemplate, UserState, EdgeData>
+where
+    DataType: DataTypeTrait<UserState>,
+{
+    /// Computes new positions for the given nodes. Nodes that are not shown
+    /// in the group currently being edited are ignored. The laid out nodes
+    /// keep the top-left corner of their bounding box, and are moved there
+    /// with an animation unless [`LayoutSettings::animation_duration`] is
+    /// zero.
+    ///
+    /// The spacing between nodes is computed from their size when they were
+    /// last drawn.
+    pub fn auto_layout(
+        &mut self,
+        nodes: &[NodeId],
+        settings: &LayoutSettings,
+        user_state: &mut UserState,
+    ) {
+        let mut seen = HashSet::new();
+        let mut nodes: Vec<NodeId> = nodes
+            .iter()
+            .copied()
+            .filter(|node| {
+                self.node_positions.contains_key(*node)
+                    && self.is_node_visible(*node)
+                    && seen.insert(*node)
+            })
+            .collect();
+        if nodes.is_empty() {
+            return;
+        }
+        // The current vertical order is the starting point of the crossing
+        // reduction, so laying out a graph twice gives the same result.
+        let position = |node: &NodeId| self.node_positions[*node];
+        nodes.sort_by(|a, b| {
+            let (a, b) = (position(a), position(b));
+            a.y.total_cmp(&b.y).then(a.x.total_cmp(&b.x))
+        });
+
+        let zoom = self.pan_zoom.zoom;
+        let sizes: HashMap<NodeId, Vec2> = nodes
+            .iter()
+            .map(|node| {
+                let size = self.node_sizes.get(*node).copied();
+                (*node, size.unwrap_or(DEFAULT_NODE_SIZE * zoom))
+            })
+            .collect();
+        let spacing = vec2(settings.layer_spacing, settings.node_spacing) * zoom;
+        let layout = match settings.style {
+            LayoutStyle::Layered => {
+                let edges = self.layout_edges(&nodes, |_| true);
+                layered_layout(&nodes, &edges, &sizes, spacing, settings.crossing_sweeps)
+            }
+            LayoutStyle::ExecutionSpine => {
+                self.spine_layout(&nodes, &sizes, spacing, settings, user_state)
+            }
+        };
+
+        let origin = nodes
+            .iter()
+            .map(position)
+            .reduce(|a, b| a.min(b))
+            .unwrap_or_default();
+        let to: HashMap<NodeId, Pos2> = layout
+            .into_iter()
+            .map(|(node, pos)| (node, pos + origin.to_vec2()))
+            .collect();
+        if settings.animation_duration > 0.0 {
+            self.layout_animation = Some(LayoutAnimation {
+                from: nodes.iter().map(|node| (*node, position(node))).collect(),
+                to,
+                elapsed: 0.0,
+                duration: settings.animation_duration,
+            });
+        } else {
+            for (node, pos) in to {
+                self.node_positions[node] = pos;
+            }
+            self.history.defer("Auto layout", None);
+        }
+    }
+
+    /// Lays out all the nodes shown in the group currently being edited.
+    /// See [`GraphEditorState::auto_layout`].
+    pub fn auto_layout_all(&mut self, settings: &LayoutSettings, user_state: &mut UserState) {
+        let nodes: Vec<NodeId> = self.graph.iter_nodes().collect();
+        self.auto_layout(&nodes, settings, user_state);
+    }
+
+    /// Lays out the selected nodes. See [`GraphEditorState::auto_layout`].
+    pub fn auto_layout_selection(&mut self, settings: &LayoutSettings, user_state: &mut UserState) {
+        let nodes = self.selected_nodes.clone();
+        self.auto_layout(&nodes, settings, user_state);
+    }
+
+    /// The connections between `nodes` whose type passes `filter`, as pairs
+    /// of the node feeding data and the node receiving it.
+    fn layout_edges(
+        &self,
+        nodes: &[NodeId],
+        mut filter: impl FnMut(&DataType) -> bool,
+    ) -> Vec<(NodeId, NodeId)> {
+        let nodes: HashSet<NodeId> = nodes.iter().copied().collect();
+        self.graph
+            .iter_connections()
+            .filter_map(|(input, output)| {
+                let output = self.graph.try_get_output(output)?;
+                let input = self.graph.try_get_input(input)?;
+                let inside = nodes.contains(&output.node) && nodes.contains(&input.node);
+                (inside && filter(&output.typ)).then_some((output.node, input.node))
+            })
+            .collect()
+    }
+
+    /// Lays out the nodes with execution ports with [`layered_layout`], and
+    /// places the nodes feeding each of them in a block below it.
+    fn spine_layout(
+        &self,
+        nodes: &[NodeId],
+        sizes: &HashMap<NodeId, Vec2>,
+        spacing: Vec2,
+        settings: &LayoutSettings,
+        user_state: &mut UserState,
+    ) -> HashMap<NodeId, Pos2> {
+        let (spine, data): (Vec<NodeId>, Vec<NodeId>) = nodes.iter().copied().partition(|node| {
+            let node = &self.graph[*node];
+            node.input_ids()
+                .map(|input| &self.graph[input].typ)
+                .chain(node.output_ids().map(|output| &self.graph[output].typ))
+                .any(|typ| typ.is_execution(user_state))
+        });
+        let execution_edges = self.layout_edges(&spine, |typ| typ.is_execution(user_state));
+        let data_edges = self.layout_edges(nodes, |typ| !typ.is_execution(user_state));
+        let mut layout = layered_layout(
+            &spine,
+            &execution_edges,
+            sizes,
+            spacing,
+            settings.crossing_sweeps,
+        );
+        let node_rect = |layout: &HashMap<NodeId, Pos2>, node: NodeId| {
+            Rect::from_min_size(layout[&node], sizes[&node])
+        };
+        let spine_bottom = spine
+            .iter()
+            .map(|node| node_rect(&layout, *node).bottom())
+            .fold(-spacing.y, f32::max);
+
+        // Each spine node, from left to right, takes the data nodes that
+        // (transitively) feed it and were not taken yet.
+        let mut consumers = spine.clone();
+        consumers.sort_by(|a, b| layout[a].x.total_cmp(&layout[b].x));
+        let mut upstream: HashMap<NodeId, Vec<NodeId>> = HashMap::new();
+        for (from, to) in &data_edges {
+            upstream.entry(*to).or_default().push(*from);
+        }
+        let data_set: HashSet<NodeId> = data.iter().copied().collect();
+        let mut taken = HashSet::new();
+        let mut blocks = Vec::new();
+        for consumer in consumers {
+            let mut block = Vec::new();
+            let mut stack = vec![consumer];
+            while let Some(node) = stack.pop() {
+                for feeder in upstream.get(&node).map(Vec::as_slice).unwrap_or(&[]) {
+                    if data_set.contains(feeder) && taken.insert(*feeder) {
+                        block.push(*feeder);
+                        stack.push(*feeder);
+                    }
+                }
+            }
+            if !block.is_empty() {
+                blocks.push((Some(consumer), block));
+            }
+        }
+        let unused: Vec<NodeId> = data
+            .iter()
+            .copied()
+            .filter(|node| !taken.contains(node))
+            .collect();
+        if !unused.is_empty() {
+            blocks.push((None, unused));
+        }
+
+        // Blocks are placed below the spine, right-aligned with their
+        // consumer, and pushed down when they overlap a previous block.
+        let mut placed: Vec<Rect> = Vec::new();
+        for (consumer, mut block) in blocks {
+            block.sort_by_key(|node| nodes.iter().position(|n| n == node));
+            let block_layout = layered_layout(
+                &block,
+                &data_edges,
+                sizes,
+                spacing,
+                settings.crossing_sweeps,
+            );
+            let size = block
+                .iter()
+                .map(|node| node_rect(&block_layout, *node))
+                .reduce(|a, b| a.union(b))
+                .map_or(Vec2::ZERO, |rect| rect.max.to_vec2());
+            let right = match consumer {
+                Some(consumer) => node_rect(&layout, consumer).right(),
+                None => size.x,
+            };
+            let mut rect = Rect::from_min_size(
+                pos2((right - size.x).max(0.0), spine_bottom + spacing.y),
+                size,
+            );
+            while let Some(other) = placed
+                .iter()
+                .find(|other| other.expand(spacing.y / 2.0).intersects(rect))
+            {
+                rect = rect.translate(vec2(0.0, other.bottom() + spacing.y - rect.top()));
+            }
+            placed.push(rect);
+            for (node, pos) in block_layout {
+                layout.insert(node, pos + rect.min.to_vec2());
+            }
+        }
+        layout
+    }
+
+    /// Advances the animation started by [`GraphEditorState::auto_layout`].
+    /// Returns whether it is still running.
+    pub(crate) fn step_layout_animation(&mut self, dt: f32) -> bool {
+        let Some(animation) = &mut self.layout_animation else {
+            return false;
+        };
+        animation.elapsed += dt;
+        let t = (animation.elapsed / animation.duration).clamp(0.0, 1.0);
+        let eased = t * t * (3.0 - 2.0 * t);
+        for (node, to) in &animation.to {
+            if let (Some(position), Some(from)) =
+                (self.node_positions.get_mut(*node), animation.from.get(node))
+            {
+                *position = from.lerp(*to, eased);
+            }
+        }
+        // All the frames of the animation are recorded as a single step.
+        self.history
+            .defer("Auto layout", Some(UndoMergeKey::AutoLayout));
+        if t >= 1.0 {
+            self.layout_animation = None;
+            self.history.end_merge();
+            false
+        } else {
+            true
+        }
+    }
+}
+
+/// Computes a layered (Sugiyama-style) layout of `nodes`, with the top-left
+/// corner at the origin. `edges` go from the node feeding data to the node
+/// receiving it, and edges to other nodes are ignored. The order of `nodes`
+/// is the initial vertical order within each column.
+fn layered_layout(
+    nodes: &[NodeId],
+    edges: &[(NodeId, NodeId)],
+    sizes: &HashMap<NodeId, Vec2>,
+    spacing: Vec2,
+    crossing_sweeps: usize,
+) -> HashMap<NodeId, Pos2> {
+    let index: HashMap<NodeId, usize> = nodes.iter().enumerate().map(|(i, n)| (*n, i)).collect();
+    let mut successors = vec![Vec::new(); nodes.len()];
+    for (from, to) in edges {
+        if let (Some(&from), Some(&to)) = (index.get(from), index.get(to)) {
+            if from != to && !successors[from].contains(&to) {
+                successors[from].push(to);
+            }
+        }
+    }
+    let (successors, order) = remove_cycles(successors);
+
+    // Columns: longest path from the sources, then every node is pulled
+    // right next to its closest consumer.
+    let mut layers = vec![0; nodes.len()];
+    for &node in &order {
+        for &next in &successors[node] {
+            layers[next] = layers[next].max(layers[node] + 1);
+        }
+    }
+    for &node in order.iter().rev() {
+        if let Some(closest) = successors[node].iter().map(|next| layers[*next]).min() {
+            layers[node] = closest - 1;
+        }
+    }
+
+    // Edges spanning several columns go through dummy items, one in each
+    // column they cross, so crossings can be counted between neighbours.
+    let mut item_layers = layers.clone();
+    let mut down: Vec<Vec<usize>> = vec![Vec::new(); nodes.len()];
+    for node in 0..nodes.len() {
+        for &next in &successors[node] {
+            let mut previous = node;
+            for layer in layers[node] + 1..layers[next] {
+                let dummy = item_layers.len();
+                item_layers.push(layer);
+                down.push(Vec::new());
+                down[previous].push(dummy);
+                previous = dummy;
+            }
+            down[previous].push(next);
+        }
+    }
+    let mut up: Vec<Vec<usize>> = vec![Vec::new(); item_layers.len()];
+    for (item, nexts) in down.iter().enumerate() {
+        for &next in nexts {
+            up[next].push(item);
+        }
+    }
+    let layer_count = item_layers.iter().max().map_or(0, |max| max + 1);
+    let mut columns: Vec<Vec<usize>> = vec![Vec::new(); layer_count];
+    for (item, layer) in item_layers.iter().enumerate() {
+        columns[*layer].push(item);
+    }
+
+    // Crossing reduction: alternate downward and upward barycenter sweeps,
+    // keeping the best ordering found.
+    let mut best = columns.clone();
+    let mut best_crossings = count_crossings(&columns, &down);
+    for sweep in 0..crossing_sweeps {
+        if best_crossings == 0 {
+            break;
+        }
+        if sweep % 2 == 0 {
+            for layer in 1..layer_count {
+                order_by_barycenter(&mut columns, layer, layer - 1, &up);
+            }
+        } else {
+            for layer in (0..layer_count.saturating_sub(1)).rev() {
+                order_by_barycenter(&mut columns, layer, layer + 1, &down);
+            }
+        }
+        let crossings = count_crossings(&columns, &down);
+        if crossings < best_crossings {
+            best = columns.clone();
+            best_crossings = crossings;
+        }
+    }
+    let columns = best;
+
+    // Horizontal coordinates: each column is as wide as its widest node.
+    let item_size = |item: usize| match nodes.get(item) {
+        Some(node) => sizes.get(node).copied().unwrap_or(DEFAULT_NODE_SIZE),
+        None => Vec2::ZERO,
+    };
+    let mut column_x = Vec::with_capacity(layer_count);
+    let mut x = 0.0;
+    for column in &columns {
+        column_x.push(x);
+        let width = column
+            .iter()
+            .map(|item| item_size(*item).x)
+            .fold(0.0, f32::max);
+        x += width + spacing.x;
+    }
+
+    // Vertical coordinates: stack each column, then align items with their
+    // neighbours in a forward and a backward pass.
+    let mut centers = vec![0.0; item_layers.len()];
+    for column in &columns {
+        place_column(column, &mut centers, item_size, spacing.y, |_| None);
+    }
+    let passes = (1..layer_count).map(|layer| (layer, &up)).chain(
+        (0..layer_count.saturating_sub(1))
+            .rev()
+            .map(|layer| (layer, &down)),
+    );
+    for (layer, neighbours) in passes {
+        let previous = centers.clone();
+        place_column(
+            &columns[layer],
+            &mut centers,
+            item_size,
+            spacing.y,
+            |item| {
+                let neighbours = &neighbours[item];
+                (!neighbours.is_empty()).then(|| {
+                    neighbours.iter().map(|n| previous[*n]).sum::<f32>() / neighbours.len() as f32
+                })
+            },
+        );
+    }
+
+    let top = (0..nodes.len())
+        .map(|item| centers[item] - item_size(item).y / 2.0)
+        .fold(f32::INFINITY, f32::min);
+    nodes
+        .iter()
+        .enumerate()
+        .map(|(item, node)| {
+            let y = centers[item] - item_size(item).y / 2.0 - top;
+            (*node, pos2(column_x[item_layers[item]], y))
+        })
+        .collect()
+}
+
+/// Removes the edges closing a cycle, found with a depth-first search.
+/// Returns the remaining edges and a topological order of the nodes.
+fn remove_cycles(mut successors: Vec<Vec<usize>>) -> (Vec<Vec<usize>>, Vec<usize>) {
+    let mut finished = vec![false; successors.len()];
+    let mut on_stack = vec![false; successors.len()];
+    let mut order = Vec::with_capacity(successors.len());
+    for root in 0..successors.len() {
+        if finished[root] {
+            continue;
+        }
+        let mut stack = vec![(root, 0)];
+        on_stack[root] = true;
+        while let Some(&(node, next)) = stack.last() {
+            if let Some(&child) = successors[node].get(next) {
+                if on_stack[child] {
+                    successors[node].remove(next);
+                    continue;
+                }
+                stack.last_mut().unwrap().1 += 1;
+                if !finished[child] {
+                    on_stack[child] = true;
+                    stack.push((child, 0));
+                }
+            } else {
+                stack.pop();
+                on_stack[node] = false;
+                finished[node] = true;
+                order.push(node);
+            }
+        }
+    }
+    order.reverse();
+    (successors, order)
+}
+
+/// Sorts the items of `columns[layer]` by the mean position of their
+/// neighbours in `columns[fixed]`. Items without neighbours keep their place.
+fn order_by_barycenter(
+    columns: &mut [Vec<usize>],
+    layer: usize,
+    fixed: usize,
+    neighbours: &[Vec<usize>],
+) {
+    let positions: HashMap<usize, usize> = columns[fixed]
+        .iter()
+        .enumerate()
+        .map(|(position, item)| (*item, position))
+        .collect();
+    let mut keyed: Vec<(f32, usize)> = columns[layer]
+        .iter()
+        .enumerate()
+        .map(|(current, item)| {
+            let fixed: Vec<usize> = neighbours[*item]
+                .iter()
+                .filter_map(|n| positions.get(n).copied())
+                .collect();
+            let barycenter = if fixed.is_empty() {
+                current as f32
+            } else {
+                fixed.iter().sum::<usize>() as f32 / fixed.len() as f32
+            };
+            (barycenter, *item)
+        })
+        .collect();
+    keyed.sort_by(|a, b| a.0.total_cmp(&b.0));
+    columns[layer] = keyed.into_iter().map(|(_, item)| item).collect();
+}
+
+/// The number of edge crossings between neighbouring columns.
+fn count_crossings(columns: &[Vec<usize>], down: &[Vec<usize>]) -> usize {
+    let mut crossings = 0;
+    for pair in columns.windows(2) {
+        let positions: HashMap<usize, usize> = pair[1]
+            .iter()
+            .enumerate()
+            .map(|(p, item)| (*item, p))
+            .collect();
+        let edges: Vec<(usize, usize)> = pair[0]
+            .iter()
+            .enumerate()
+            .flat_map(|(p, item)| down[*item].iter().map(move |next| (p, *next)))
+            .filter_map(|(p, next)| Some((p, *positions.get(&next)?)))
+            .collect();
+        for (i, a) in edges.iter().enumerate() {
+            for b in &edges[i + 1..] {
+                if (a.0 < b.0 && a.1 > b.1) || (a.0 > b.0 && a.1 < b.1) {
+                    crossings += 1;
+                }
+            }
+        }
+    }
+    crossings
+}
+
+/// Places the items of a column top to bottom, as close as possible to their
+/// `target` center (or right after the previous item when `None`), without
+/// overlapping.
+fn place_column(
+    column: &[usize],
+    centers: &mut [f32],
+    item_size: impl Fn(usize) -> Vec2,
+    spacing: f32,
+    target: impl Fn(usize) -> Option<f32>,
+) {
+    let mut bottom = f32::NEG_INFINITY;
+    for &item in column {
+        let half_height = item_size(item).y / 2.0;
+        let min_center = if bottom.is_finite() {
+            bottom + spacing + half_height
+        } else {
+            half_height
+        };
+        let center = match target(item) {
+            Some(target) if bottom.is_finite() => target.max(min_center),
+            Some(target) => target,
+            None => min_center,
+        };
+        centers[item] = center;
+        bottom = center + half_height;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    /// Adds a node of 100 by 50 points at the given height.
+    fn add_node(state: &mut TestState<()>, y: f32) -> (NodeId, InputId, OutputId) {
+        let ports = add_placed_node(state, egui::pos2(0.0, y));
+        state.node_sizes.insert(ports.0, vec2(100.0, 50.0));
+        ports
+    }
+
+    #[test]
+    fn test_layered_layout() {
+        let mut state = TestState::<()>::default();
+        // a -> c, b -> d, with a and b in the opposite order of c and d
+        let (a, _, a_out) = add_node(&mut state, 0.0);
+        let (b, _, b_out) = add_node(&mut state, 100.0);
+        let (c, c_in, _) = add_node(&mut state, 300.0);
+        let (d, d_in, _) = add_node(&mut state, 200.0);
+        state.graph.add_connection(a_out, c_in);
+        state.graph.add_connection(b_out, d_in);
+
+        let settings = LayoutSettings {
+            animation_duration: 0.0,
+            ..Default::default()
+        };
+        state.auto_layout_all(&settings, &mut ());
+        let pos = |node| state.node_positions[node];
+
+        // Data flows from left to right, starting at the previous corner
+        assert_eq!(pos(a).x, 0.0);
+        assert_eq!(pos(c).x, 100.0 + settings.layer_spacing);
+        assert_eq!(pos(a).y.min(pos(b).y), 0.0);
+        // The wires don't cross, and nodes don't overlap
+        assert_eq!(pos(a).y < pos(b).y, pos(c).y < pos(d).y);
+        assert!((pos(a).y - pos(b).y).abs() >= 50.0 + settings.node_spacing);
+    }
+
+    #[test]
+    fn test_spine_layout() {
+        let mut state = TestState::<()>::default();
+        // The execution chain s1 -> s2, with x feeding s1, and y -> z
+        // feeding s2
+        let (s1, s1_in, _) = add_node(&mut state, 0.0);
+        let (s2, s2_in, _) = add_node(&mut state, 100.0);
+        let (x, _, x_out) = add_node(&mut state, 200.0);
+        let (y, _, y_out) = add_node(&mut state, 300.0);
+        let (z, z_in, z_out) = add_node(&mut state, 400.0);
+        let s1_next = state
+            .graph
+            .add_output_param(s1, "next".into(), TestType::Flow);
+        let s2_prev = state.graph.add_input_param(
+            s2,
+            "prev".into(),
+            TestType::Flow,
+            0,
+            InputParamKind::ConnectionOnly,
+            true,
+        );
+        state.graph.add_connection(s1_next, s2_prev);
+        state.graph.add_connection(x_out, s1_in);
+        state.graph.add_connection(y_out, z_in);
+        state.graph.add_connection(z_out, s2_in);
+
+        let settings = LayoutSettings {
+            style: LayoutStyle::ExecutionSpine,
+            animation_duration: 0.0,
+            ..Default::default()
+        };
+        state.auto_layout_all(&settings, &mut ());
+        let pos = |node| state.node_positions[node];
+
+        // The execution nodes are in a single row
+        assert_eq!(pos(s1).y, pos(s2).y);
+        assert_eq!(pos(s2).x, pos(s1).x + 100.0 + settings.layer_spacing);
+        // Each data block is below the node it feeds, and right-aligned
+        // with it
+        for node in [x, y, z] {
+            assert!(pos(node).y >= pos(s1).y + 50.0 + settings.node_spacing);
+        }
+        assert_eq!(pos(x).x, pos(s1).x);
+        assert_eq!(pos(z).x, pos(s2).x);
+        assert_eq!(pos(y).x, pos(z).x - 100.0 - settings.layer_spacing);
+    }
+
+    #[test]
+    fn test_layout_animation() {
+        let mut state = TestState::<()>::default();
+        let (a, _, a_out) = add_node(&mut state, 0.0);
+        let (b, b_in, _) = add_node(&mut state, 0.0);
+        state.graph.add_connection(a_out, b_in);
+
+        state.auto_layout(&[a, b], &LayoutSettings::default(), &mut ());
+        assert!(state.step_layout_animation(0.1));
+        assert!(state.node_positions[b].x > 0.0);
+        assert!(!state.step_layout_animation(1.0));
+        assert!(state.layout_animation.is_none());
+        assert_eq!(state.node_positions[b].x, 180.0);
+    }
+}
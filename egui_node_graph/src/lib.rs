@@ -19,29 +19,140 @@ pub mod index_impls;
 /// Implementing the main methods for the `Graph`
 pub mod graph_impls;
 
+/// Traversal algorithms for the `Graph`: topological sorting, cycle detection,
+/// strongly connected components and reachability.
+pub mod graph_algorithms;
+pub use graph_algorithms::*;
+
+/// A journal of the changes made to the `Graph`, to keep external models in
+/// sync with it
+pub mod change;
+pub use change::*;
+
+/// Consistency checks and repair of the `Graph`, for graphs loaded from old
+/// or corrupted files
+pub mod validation;
+pub use validation::*;
+
 /// Custom error types, crate-wide
 pub mod error;
 pub use error::*;
 
+// Everything below draws the graph with egui. The graph model above builds
+// without it, for headless tools loading and editing graphs.
+
 /// The main struct in the library, contains all the necessary state to draw the
 /// UI graph
+#[cfg(feature = "editor")]
 pub mod ui_state;
+#[cfg(feature = "editor")]
 pub use ui_state::*;
 
+/// Undo and redo support for the graph editor
+#[cfg(feature = "editor")]
+pub mod undo;
+#[cfg(feature = "editor")]
+pub use undo::*;
+
+/// Group nodes, collapsing several nodes into one
+#[cfg(feature = "editor")]
+pub mod group;
+#[cfg(feature = "editor")]
+pub use group::*;
+
+/// Reroute knots to organize the wires
+#[cfg(feature = "editor")]
+pub mod reroute;
+#[cfg(feature = "editor")]
+pub use reroute::*;
+
+/// Comment frames and sticky notes to annotate the graph
+#[cfg(feature = "editor")]
+pub mod comment;
+#[cfg(feature = "editor")]
+pub use comment::*;
+
+/// Copying and pasting of nodes between graphs
+#[cfg(feature = "editor")]
+pub mod clipboard;
+#[cfg(feature = "editor")]
+pub use clipboard::*;
+
+/// The context menus of the nodes, wires and background of the editor
+#[cfg(feature = "editor")]
+pub mod context_menu;
+#[cfg(feature = "editor")]
+pub use context_menu::*;
+
+/// Inserting nodes in existing wires
+#[cfg(feature = "editor")]
+pub mod splice;
+#[cfg(feature = "editor")]
+pub use splice::*;
+
+/// Automatic layout of the nodes
+#[cfg(feature = "editor")]
+pub mod layout;
+#[cfg(feature = "editor")]
+pub use layout::*;
+
+/// A minimap overlay to navigate large graphs
+#[cfg(feature = "editor")]
+pub mod minimap;
+#[cfg(feature = "editor")]
+pub use minimap::*;
+
+/// The shape of the wires, and the geometry to draw them
+#[cfg(feature = "editor")]
+pub mod wire;
+#[cfg(feature = "editor")]
+pub use wire::*;
+
+/// Custom data attached to the connections, and the hook drawing their wires
+#[cfg(feature = "editor")]
+pub mod edge;
+#[cfg(feature = "editor")]
+pub use edge::*;
+
+/// A spatial index to find the nodes and ports near a position
+#[cfg(feature = "editor")]
+pub mod spatial;
+#[cfg(feature = "editor")]
+pub use spatial::*;
+
+/// Colors and measurements of the graph editor, with dark and light presets
+#[cfg(feature = "editor")]
+pub mod style;
+#[cfg(feature = "editor")]
+pub use style::*;
+
 /// The node finder is a tiny widget allowing to create new node types
+#[cfg(feature = "editor")]
 pub mod node_finder;
+#[cfg(feature = "editor")]
 pub use node_finder::*;
 
 /// The inner details of the egui implementation. Most egui code lives here.
+#[cfg(feature = "editor")]
 pub mod editor_ui;
+#[cfg(feature = "editor")]
 pub use editor_ui::*;
 
 /// Several traits that must be implemented by the user to customize the
 /// behavior of this library.
+#[cfg(feature = "editor")]
 pub mod traits;
+#[cfg(feature = "editor")]
 pub use traits::*;
 
+#[cfg(feature = "editor")]
 mod utils;
 
+#[cfg(feature = "editor")]
 mod color_hex_utils;
+#[cfg(feature = "editor")]
 mod scale;
+
+/// Graphs and editor states shared by the unit tests
+#[cfg(test)]
+pub(crate) mod test_utils;
@@ -0,0 +1,129 @@
+use egui::*;
+
+use crate::GraphEditorStyle;
+
+#[cfg(feature = "persistence")]
+use serde::{Deserialize, Serialize};
+
+/// The corner of the graph editor the minimap is shown in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
+pub enum MinimapCorner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+/// Configuration of the minimap drawn over the graph editor.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
+pub struct MinimapSettings {
+    pub visible: bool,
+    pub corner: MinimapCorner,
+    /// The size of the minimap on screen, in points.
+    pub size: Vec2,
+}
+
+impl Default for MinimapSettings {
+    fn default() -> Self {
+        Self {
+            visible: false,
+            corner: MinimapCorner::BottomRight,
+            size: vec2(200.0, 150.0),
+        }
+    }
+}
+
+impl MinimapSettings {
+    const MARGIN: f32 = 10.0;
+
+    /// The rect covered by the minimap in an editor covering `editor_rect`,
+    /// or `None` if it is hidden.
+    pub fn rect(&self, editor_rect: Rect) -> Option<Rect> {
+        if !self.visible {
+            return None;
+        }
+        let inner = editor_rect.shrink(Self::MARGIN);
+        let size = self.size.min(inner.size()).max(Vec2::ZERO);
+        let min = match self.corner {
+            MinimapCorner::TopLeft => inner.min,
+            MinimapCorner::TopRight => pos2(inner.max.x - size.x, inner.min.y),
+            MinimapCorner::BottomLeft => pos2(inner.min.x, inner.max.y - size.y),
+            MinimapCorner::BottomRight => inner.max - size,
+        };
+        Some(Rect::from_min_size(min, size))
+    }
+}
+
+pub(crate) struct MinimapWidget<'a> {
+    pub id: Id,
+    /// Where the minimap is drawn on screen.
+    pub rect: Rect,
+    /// The visible part of the graph, in node coordinates.
+    pub viewport: Rect,
+    /// The nodes to draw, in node coordinates, and whether they are selected.
+    pub nodes: &'a [(Rect, bool)],
+    /// The wires to draw, in node coordinates, with their color.
+    pub wires: &'a [(Pos2, Pos2, Color32)],
+    pub style: &'a GraphEditorStyle,
+}
+
+impl<'a> MinimapWidget<'a> {
+    /// Draws the minimap. Returns the center of the viewport requested by
+    /// clicking or dragging on the minimap, in node coordinates.
+    pub fn show(self, ui: &mut Ui) -> Option<Pos2> {
+        let response = ui.interact(self.rect, self.id, Sense::click_and_drag());
+
+        // The area shown by the minimap is frozen while dragging, so it does
+        // not move under the cursor as the viewport moves.
+        let bounds = match ui.data(|d| d.get_temp::<Rect>(self.id)) {
+            Some(bounds) if response.dragged() => bounds,
+            _ => {
+                let bounds = self
+                    .nodes
+                    .iter()
+                    .map(|(rect, _)| *rect)
+                    .fold(self.viewport, |a, b| a.union(b))
+                    .expand(20.0);
+                ui.data_mut(|d| d.insert_temp(self.id, bounds));
+                bounds
+            }
+        };
+        let inner = self.rect.shrink(4.0);
+        let scale = (inner.width() / bounds.width()).min(inner.height() / bounds.height());
+        let to_map = |pos: Pos2| inner.center() + (pos - bounds.center()) * scale;
+        let to_map_rect = |rect: Rect| Rect::from_min_max(to_map(rect.min), to_map(rect.max));
+
+        let painter = ui.painter().with_clip_rect(self.rect);
+        painter.rect(
+            self.rect,
+            Rounding::same(3.0),
+            self.style.minimap_fill,
+            Stroke::new(1.0, self.style.minimap_stroke),
+        );
+        for (src, dst, color) in self.wires {
+            painter.line_segment([to_map(*src), to_map(*dst)], Stroke::new(1.0, *color));
+        }
+        for (rect, selected) in self.nodes {
+            let fill = if *selected {
+                self.style.minimap_selected_node
+            } else {
+                self.style.minimap_node
+            };
+            painter.rect_filled(to_map_rect(*rect), Rounding::same(1.0), fill);
+        }
+        painter.rect_stroke(
+            to_map_rect(self.viewport),
+            Rounding::none(),
+            Stroke::new(1.5, self.style.minimap_viewport),
+        );
+
+        if response.clicked() || response.dragged() {
+            let pointer = response.interact_pointer_pos()?;
+            Some(bounds.center() + (pointer - inner.center()) / scale)
+        } else {
+            None
+        }
+    }
+}
@@ -1,9 +1,24 @@
 use std::{collections::BTreeMap, marker::PhantomData};
 
-use crate::{color_hex_utils::*, CategoryTrait, NodeTemplateIter, NodeTemplateTrait};
+use crate::{
+    AnyParameterId, CategoryTrait, ConnectionCompatibility, DataTypeTrait, Graph, GraphEditorStyle,
+    InputParamKind, NodeId, NodeTemplateIter, NodeTemplateTrait,
+};
 
 use egui::*;
 
+/// The number of templates kept in [`GraphEditorState::recent_templates`].
+///
+/// [`GraphEditorState::recent_templates`]: crate::GraphEditorState::recent_templates
+pub const MAX_RECENT_TEMPLATES: usize = 5;
+
+/// Score bonus of the recently used templates when searching.
+const RECENT_BONUS: i32 = 50;
+/// Score penalty of a match on a keyword rather than on the label.
+const KEYWORD_PENALTY: i32 = 100;
+/// Score of a match on the description.
+const DESCRIPTION_SCORE: i32 = 100;
+
 #[derive(Clone)]
 #[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
 pub struct NodeFinder<NodeTemplate> {
@@ -11,13 +26,32 @@ pub struct NodeFinder<NodeTemplate> {
     /// Reset every frame. When set, the node finder will be moved at that position
     pub position: Option<Pos2>,
     pub just_spawned: bool,
+    /// The port a wire was dragged from when the finder was opened by
+    /// dropping the wire on the canvas. Only the templates with a port that
+    /// can be connected to it are listed, and the created node is connected
+    /// to it.
+    pub pending_connection: Option<(NodeId, AnyParameterId)>,
+    /// The entry highlighted with the arrow keys, picked when pressing enter.
+    pub selected: usize,
+    /// The templates accepting `filtered_for`, the last pending connection.
+    /// Building every template to filter them is too slow to do every frame.
+    #[cfg_attr(feature = "persistence", serde(skip, default = "Option::default"))]
+    filtered_for: Option<(NodeId, AnyParameterId)>,
+    #[cfg_attr(feature = "persistence", serde(skip, default = "Vec::new"))]
+    compatible_templates: Vec<NodeTemplate>,
     _phantom: PhantomData<NodeTemplate>,
 }
 
-impl<NodeTemplate, NodeData, UserState, CategoryType> NodeFinder<NodeTemplate>
+impl<NodeTemplate, NodeData, DataType, ValueType, UserState, CategoryType> NodeFinder<NodeTemplate>
 where
-    NodeTemplate:
-        NodeTemplateTrait<NodeData = NodeData, UserState = UserState, CategoryType = CategoryType>,
+    NodeTemplate: NodeTemplateTrait<
+        NodeData = NodeData,
+        DataType = DataType,
+        ValueType = ValueType,
+        UserState = UserState,
+        CategoryType = CategoryType,
+    >,
+    DataType: DataTypeTrait<UserState>,
     CategoryType: CategoryTrait,
 {
     pub fn new_at(pos: Pos2) -> Self {
@@ -25,34 +59,113 @@ where
             query: "".into(),
             position: Some(pos),
             just_spawned: true,
+            pending_connection: None,
+            selected: 0,
+            filtered_for: None,
+            compatible_templates: Vec::new(),
             _phantom: Default::default(),
         }
     }
 
+    /// Opens the finder at `pos` for a wire dragged from `port` of `node`.
+    pub fn new_for_connection(pos: Pos2, node: NodeId, port: AnyParameterId) -> Self {
+        NodeFinder {
+            pending_connection: Some((node, port)),
+            ..Self::new_at(pos)
+        }
+    }
+
+    /// Returns whether the node built by `template` has a port that can be
+    /// connected to the pending connection, if any. The node is built in a
+    /// scratch graph to find out.
+    fn accepts(
+        &self,
+        template: &NodeTemplate,
+        graph: &Graph<NodeData, DataType, ValueType>,
+        user_state: &mut UserState,
+    ) -> bool {
+        let Some((_, port)) = self.pending_connection else {
+            return true;
+        };
+        let Ok(port_type) = graph.any_param_type(port) else {
+            return true;
+        };
+        let mut scratch = Graph::new();
+        let label = template.node_graph_label(user_state);
+        let user_data = template.user_data(user_state);
+        let node = scratch.add_node(label, user_data, |scratch, node| {
+            template.build_node(scratch, user_state, node)
+        });
+        find_compatible_port(&scratch, node, port, port_type, user_state).is_some()
+    }
+
+    /// The templates that can be connected to the pending connection, or all
+    /// of them if there is none. They are filtered once per connection.
+    fn compatible_kinds(
+        &mut self,
+        all_kinds: impl NodeTemplateIter<Item = NodeTemplate>,
+        graph: &Graph<NodeData, DataType, ValueType>,
+        user_state: &mut UserState,
+    ) -> Vec<NodeTemplate> {
+        if self.pending_connection.is_none() {
+            return all_kinds.all_kinds();
+        }
+        if self.filtered_for != self.pending_connection {
+            let mut kinds = all_kinds.all_kinds();
+            kinds.retain(|kind| self.accepts(kind, graph, user_state));
+            self.compatible_templates = kinds;
+            self.filtered_for = self.pending_connection;
+        }
+        self.compatible_templates.clone()
+    }
+
+    /// Scores `template` against the query with [`fuzzy_score`]. Its
+    /// keywords and description are matched too, with a lower score. Returns
+    /// `None` if it does not match.
+    fn score(
+        &self,
+        template: &NodeTemplate,
+        label: &str,
+        user_state: &mut UserState,
+    ) -> Option<i32> {
+        let query = self.query.trim();
+        let keyword_score = template
+            .node_finder_keywords(user_state)
+            .iter()
+            .filter_map(|keyword| fuzzy_score(query, keyword))
+            .max()
+            .map(|score| score - KEYWORD_PENALTY);
+        let description_score = template
+            .node_finder_description(user_state)
+            .to_lowercase()
+            .contains(&query.to_lowercase())
+            .then_some(DESCRIPTION_SCORE);
+        fuzzy_score(query, label)
+            .max(keyword_score)
+            .max(description_score)
+    }
+
     /// Shows the node selector panel with a search bar. Returns whether a node
     /// archetype was selected and, in that case, the finder should be hidden on
     /// the next frame.
+    ///
+    /// While the search bar is empty, the `recent` templates are listed
+    /// first. Otherwise, the matching templates are ranked by score. The
+    /// highlighted entry can be moved with the arrow keys and picked with
+    /// enter.
     pub fn show(
         &mut self,
         ui: &mut Ui,
+        style: &GraphEditorStyle,
         all_kinds: impl NodeTemplateIter<Item = NodeTemplate>,
+        recent: &[NodeTemplate],
+        graph: &Graph<NodeData, DataType, ValueType>,
         user_state: &mut UserState,
     ) -> Option<NodeTemplate> {
-        let background_color;
-        let text_color;
-
-        if ui.visuals().dark_mode {
-            background_color = color_from_hex("#3f3f3f").unwrap();
-            text_color = color_from_hex("#fefefe").unwrap();
-        } else {
-            background_color = color_from_hex("#fefefe").unwrap();
-            text_color = color_from_hex("#3f3f3f").unwrap();
-        }
-
-        ui.visuals_mut().widgets.noninteractive.fg_stroke = Stroke::new(2.0, text_color);
+        ui.visuals_mut().widgets.noninteractive.fg_stroke = Stroke::new(2.0, style.finder_text);
 
         let frame = Frame::dark_canvas(ui.style())
-            .fill(background_color)
+            .fill(style.finder_fill)
             .inner_margin(vec2(5.0, 5.0));
 
         // The archetype that will be returned.
@@ -64,25 +177,80 @@ where
                     resp.request_focus();
                     self.just_spawned = false;
                 }
-                let update_open = resp.changed();
+                if resp.changed() {
+                    self.selected = 0;
+                }
 
-                let mut query_submit = resp.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter));
+                let query_submit = resp.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter));
+                let (key_down, key_up) =
+                    ui.input(|i| (i.key_pressed(Key::ArrowDown), i.key_pressed(Key::ArrowUp)));
 
                 let max_height = ui.input(|i| i.screen_rect.height() * 0.5);
                 let scroll_area_width = resp.rect.width() - 30.0;
 
-                let all_kinds = all_kinds.all_kinds();
-                let mut categories: BTreeMap<String, Vec<&NodeTemplate>> = Default::default();
-                let mut orphan_kinds = Vec::new();
+                let all_kinds = self.compatible_kinds(all_kinds, graph, user_state);
+                let labels: Vec<String> = all_kinds
+                    .iter()
+                    .map(|kind| kind.node_finder_label(user_state).into_owned())
+                    .collect();
+                let recent: Vec<usize> = recent
+                    .iter()
+                    .filter_map(|template| {
+                        let label = template.node_finder_label(user_state);
+                        labels.iter().position(|l| *l == label)
+                    })
+                    .collect();
+
+                // The entries that can be picked with the keyboard: the
+                // search results, or the recent templates if there is no
+                // query. They are indices in `all_kinds`.
+                let searching = !self.query.trim().is_empty();
+                let entries: Vec<usize> = if searching {
+                    let mut ranked: Vec<(i32, usize)> = (0..all_kinds.len())
+                        .filter_map(|index| {
+                            let score =
+                                self.score(&all_kinds[index], &labels[index], user_state)?;
+                            let bonus = if recent.contains(&index) {
+                                RECENT_BONUS
+                            } else {
+                                0
+                            };
+                            Some((score + bonus, index))
+                        })
+                        .collect();
+                    ranked
+                        .sort_by(|a, b| b.0.cmp(&a.0).then_with(|| labels[a.1].cmp(&labels[b.1])));
+                    ranked.into_iter().map(|(_, index)| index).collect()
+                } else {
+                    recent
+                };
 
-                for kind in &all_kinds {
-                    let kind_categories = kind.node_finder_categories(user_state);
+                let last_entry = entries.len().saturating_sub(1);
+                if key_down {
+                    self.selected += 1;
+                }
+                if key_up {
+                    self.selected = self.selected.saturating_sub(1);
+                }
+                self.selected = self.selected.min(last_entry);
+                if query_submit {
+                    if let Some(index) = entries.get(self.selected) {
+                        submitted_archetype = Some(all_kinds[*index].clone());
+                    }
+                }
 
-                    if kind_categories.is_empty() {
-                        orphan_kinds.push(kind);
-                    } else {
-                        for category in kind_categories {
-                            categories.entry(category.name()).or_default().push(kind);
+                let mut categories: BTreeMap<String, Vec<usize>> = Default::default();
+                let mut orphan_kinds = Vec::new();
+                if !searching {
+                    for (index, kind) in all_kinds.iter().enumerate() {
+                        let kind_categories = kind.node_finder_categories(user_state);
+
+                        if kind_categories.is_empty() {
+                            orphan_kinds.push(index);
+                        } else {
+                            for category in kind_categories {
+                                categories.entry(category.name()).or_default().push(index);
+                            }
                         }
                     }
                 }
@@ -94,52 +262,54 @@ where
                             .max_height(max_height)
                             .show(ui, |ui| {
                                 ui.set_width(scroll_area_width);
-                                for (category, kinds) in categories {
-                                    let filtered_kinds: Vec<_> = kinds
-                                        .into_iter()
-                                        .map(|kind| {
-                                            let kind_name =
-                                                kind.node_finder_label(user_state).to_string();
-                                            (kind, kind_name)
-                                        })
-                                        .filter(|(_kind, kind_name)| {
-                                            kind_name
-                                                .to_lowercase()
-                                                .contains(self.query.to_lowercase().as_str())
-                                        })
-                                        .collect();
-
-                                    if !filtered_kinds.is_empty() {
-                                        let default_open = !self.query.is_empty();
-
-                                        CollapsingHeader::new(&category)
-                                            .default_open(default_open)
-                                            .open(update_open.then_some(default_open))
-                                            .show(ui, |ui| {
-                                                for (kind, kind_name) in filtered_kinds {
-                                                    if ui
-                                                        .selectable_label(false, kind_name)
-                                                        .clicked()
-                                                    {
-                                                        submitted_archetype = Some(kind.clone());
-                                                    } else if query_submit {
-                                                        submitted_archetype = Some(kind.clone());
-                                                        query_submit = false;
-                                                    }
-                                                }
-                                            });
+                                let mut show_entry =
+                                    |ui: &mut Ui, index: usize, highlighted: bool| {
+                                        let kind = &all_kinds[index];
+                                        let description = kind.node_finder_description(user_state);
+                                        let mut response =
+                                            ui.selectable_label(highlighted, &labels[index]);
+                                        if highlighted && (key_down || key_up) {
+                                            response.scroll_to_me(None);
+                                        }
+                                        if !description.is_empty() {
+                                            response = response.on_hover_text(description);
+                                        }
+                                        if response.clicked() {
+                                            submitted_archetype = Some(kind.clone());
+                                        }
+                                    };
+
+                                if searching {
+                                    for (position, index) in entries.iter().enumerate() {
+                                        show_entry(ui, *index, position == self.selected);
+                                    }
+                                    if entries.is_empty() {
+                                        ui.label(RichText::new("No results").weak());
                                     }
+                                    return;
                                 }
 
-                                for kind in orphan_kinds {
-                                    let kind_name = kind.node_finder_label(user_state).to_string();
-
-                                    if ui.selectable_label(false, kind_name).clicked() {
-                                        submitted_archetype = Some(kind.clone());
-                                    } else if query_submit {
-                                        submitted_archetype = Some(kind.clone());
-                                        query_submit = false;
+                                if !entries.is_empty() {
+                                    ui.label(RichText::new("Recent").weak());
+                                    for (position, index) in entries.iter().enumerate() {
+                                        show_entry(ui, *index, position == self.selected);
                                     }
+                                    ui.separator();
+                                }
+
+                                for (category, kinds) in categories {
+                                    CollapsingHeader::new(&category).default_open(false).show(
+                                        ui,
+                                        |ui| {
+                                            for index in kinds {
+                                                show_entry(ui, index, false);
+                                            }
+                                        },
+                                    );
+                                }
+
+                                for index in orphan_kinds {
+                                    show_entry(ui, index, false);
                                 }
                             });
                     });
@@ -149,3 +319,231 @@ where
         submitted_archetype
     }
 }
+
+/// Finds the first parameter of `node` that can be connected to `port`, a
+/// parameter of type `port_type` in another node: an input of `node` when
+/// `port` is an output, and an output otherwise. Inputs that only take a
+/// constant are skipped. `port` does not need to be in `graph`.
+pub(crate) fn find_compatible_port<NodeData, DataType, ValueType, UserState>(
+    graph: &Graph<NodeData, DataType, ValueType>,
+    node: NodeId,
+    port: AnyParameterId,
+    port_type: &DataType,
+    user_state: &mut UserState,
+) -> Option<(AnyParameterId, ConnectionCompatibility)>
+where
+    DataType: DataTypeTrait<UserState>,
+{
+    let node = graph.nodes.get(node)?;
+    match port {
+        AnyParameterId::Output(_) => node.input_ids().find_map(|input| {
+            if matches!(graph[input].kind(), InputParamKind::ConstantOnly) {
+                return None;
+            }
+            let compatibility = port_type.connection_compatibility(&graph[input].typ, user_state);
+            compatibility
+                .is_allowed()
+                .then_some((AnyParameterId::Input(input), compatibility))
+        }),
+        AnyParameterId::Input(_) => node.output_ids().find_map(|output| {
+            let compatibility = graph[output]
+                .typ
+                .connection_compatibility(port_type, user_state);
+            compatibility
+                .is_allowed()
+                .then_some((AnyParameterId::Output(output), compatibility))
+        }),
+    }
+}
+
+/// Returns whether each character of `text` starts a word: it follows a
+/// separator, a lowercase letter followed by an uppercase one (camelCase), or
+/// a letter followed by a digit.
+fn word_starts(text: &[char]) -> Vec<bool> {
+    (0..text.len())
+        .map(|i| {
+            let current = text[i];
+            match i.checked_sub(1).map(|j| text[j]) {
+                None => true,
+                Some(previous) => {
+                    (!previous.is_alphanumeric() && current.is_alphanumeric())
+                        || (previous.is_lowercase() && current.is_uppercase())
+                        || (previous.is_alphabetic() && current.is_numeric())
+                }
+            }
+        })
+        .collect()
+}
+
+/// Scores how well `query` matches `text`, ignoring case. Returns `None` when
+/// the characters of the query don't all appear in order in the text.
+///
+/// From best to worst: exact matches, prefixes, matches at the start of a
+/// word, acronyms (`"mn"` for `"Make Number"`), other substrings, and any
+/// other subsequence. Shorter texts score higher, all else being equal.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let lowercase = |c: char| c.to_lowercase().next().unwrap_or(c);
+    let query: Vec<char> = query.trim().chars().map(lowercase).collect();
+    if query.is_empty() {
+        return Some(0);
+    }
+    let original: Vec<char> = text.chars().collect();
+    let text: Vec<char> = original.iter().copied().map(lowercase).collect();
+    let starts = word_starts(&original);
+    let length_penalty = (text.len() - query.len().min(text.len())).min(50) as i32;
+    let matches_at = |i: usize| text[i..].starts_with(&query);
+
+    if text == query {
+        return Some(1000);
+    }
+    if matches_at(0) {
+        return Some(900 - length_penalty);
+    }
+    if let Some(i) = (1..text.len()).find(|i| starts[*i] && matches_at(*i)) {
+        return Some(800 - (i as i32).min(50) - length_penalty);
+    }
+    let initials: Vec<char> = (0..text.len())
+        .filter(|i| starts[*i] && text[*i].is_alphanumeric())
+        .map(|i| text[i])
+        .collect();
+    if initials.starts_with(&query) {
+        return Some(700 - length_penalty);
+    }
+    if let Some(i) = (1..text.len()).find(|i| matches_at(*i)) {
+        return Some(500 - (i as i32).min(50) - length_penalty);
+    }
+
+    // Subsequence: consecutive characters and word starts score higher,
+    // gaps score lower.
+    let mut score = 300;
+    let mut next = 0;
+    let mut previous: Option<usize> = None;
+    for c in query {
+        let found = next + text[next..].iter().position(|t| *t == c)?;
+        if starts[found] {
+            score += 15;
+        }
+        match previous {
+            Some(previous) if previous + 1 == found => score += 10,
+            Some(previous) => score -= ((found - previous - 1) as i32).min(10),
+            None => score -= (found as i32).min(10),
+        }
+        previous = Some(found);
+        next = found + 1;
+    }
+    Some(score.min(450) - length_penalty)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    #[test]
+    fn test_fuzzy_score() {
+        let score = |query, text| fuzzy_score(query, text);
+        assert_eq!(score("", "Anything"), Some(0));
+        assert_eq!(score("xyz", "Add Number"), None);
+        assert_eq!(score("ba", "Add Number"), None);
+
+        // Case is ignored
+        assert_eq!(score("add number", "Add Number"), Some(1000));
+        assert!(score("ADD", "Add Number").is_some());
+
+        // Each kind of match ranks above the next one
+        let ranked = [
+            score("add", "Add"),
+            score("add", "Add Number"),
+            score("num", "Add Number"),
+            score("an", "Add Number"),
+            score("umb", "Add Number"),
+            score("adnb", "Add Number"),
+        ];
+        assert!(ranked.iter().all(Option::is_some));
+        assert!(
+            ranked.windows(2).all(|pair| pair[0] > pair[1]),
+            "{:?}",
+            ranked
+        );
+
+        // camelCase and digits start words
+        assert!(score("ns", "addNumberString") > score("ds", "addNumberString"));
+        assert!(score("2", "Vec2") > score("e", "Vec2"));
+
+        // Shorter texts first
+        assert!(score("add", "Add") > score("add", "Add Number"));
+        assert!(score("prt", "Print") > score("prt", "Print Number"));
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    /// The user state counts the built nodes.
+    struct TestTemplate(TestType);
+
+    impl NodeTemplateTrait for TestTemplate {
+        type NodeData = ();
+        type DataType = TestType;
+        type ValueType = u32;
+        type UserState = usize;
+        type CategoryType = ();
+
+        fn node_finder_label(&self, _user_state: &mut usize) -> std::borrow::Cow<'_, str> {
+            format!("{:?}", self.0).into()
+        }
+
+        fn node_graph_label(&self, _user_state: &mut usize) -> String {
+            format!("{:?}", self.0)
+        }
+
+        fn user_data(&self, _user_state: &mut usize) {}
+
+        fn build_node(&self, graph: &mut TestGraph, built: &mut usize, node: NodeId) {
+            *built += 1;
+            graph.add_input_param(
+                node,
+                "in".into(),
+                self.0,
+                0,
+                InputParamKind::ConnectionOnly,
+                true,
+            );
+        }
+    }
+
+    struct AllTemplates;
+
+    impl NodeTemplateIter for AllTemplates {
+        type Item = TestTemplate;
+
+        fn all_kinds(&self) -> Vec<TestTemplate> {
+            vec![TestTemplate(TestType::Flow), TestTemplate(TestType::Data)]
+        }
+    }
+
+    #[test]
+    fn test_compatible_kinds_cached() {
+        let mut graph = TestGraph::new();
+        let node = graph.add_node("node".into(), (), |_, _| {});
+        let flow = graph.add_output_param(node, "out".into(), TestType::Flow);
+        let data = graph.add_output_param(node, "out".into(), TestType::Data);
+        let mut finder = NodeFinder::new_at(Pos2::ZERO);
+        let mut built = 0;
+
+        // Without a pending connection, nothing is built
+        let kinds = finder.compatible_kinds(AllTemplates, &graph, &mut built);
+        assert_eq!(kinds.len(), 2);
+        assert_eq!(built, 0);
+
+        // The templates are built once per connection
+        finder.pending_connection = Some((node, AnyParameterId::Output(flow)));
+        for _ in 0..3 {
+            let kinds = finder.compatible_kinds(AllTemplates, &graph, &mut built);
+            assert_eq!(kinds, vec![TestTemplate(TestType::Flow)]);
+        }
+        assert_eq!(built, 2);
+
+        finder.pending_connection = Some((node, AnyParameterId::Output(data)));
+        let kinds = finder.compatible_kinds(AllTemplates, &graph, &mut built);
+        assert_eq!(kinds, vec![TestTemplate(TestType::Data)]);
+        assert_eq!(built, 4);
+    }
+}
@@ -0,0 +1,286 @@
+use std::collections::HashSet;
+
+use super::*;
+
+#[cfg(feature = "persistence")]
+use serde::{Deserialize, Serialize};
+
+/// A pass-through knot placed along a connection to organize the wires.
+///
+/// Reroutes only exist in the editor: the [`Graph`] still connects the input
+/// directly to the output, so [`Graph::connection`] always returns the real
+/// upstream [`OutputId`] and evaluating the graph does not need to know about
+/// them. Several wires can leave the same knot, all carrying the data of its
+/// [`Reroute::source`].
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
+pub struct Reroute {
+    /// The output whose data goes through this knot.
+    pub source: OutputId,
+    /// The knot before this one, or `None` if the wire comes directly from
+    /// the source output.
+    pub previous: Option<RerouteId>,
+    /// The position of the knot, in the same coordinates as the node positions.
+    pub position: egui::Pos2,
+    /// The group the knot was created in. Knots are only shown while editing
+    /// that group.
+    pub group: Option<GroupId>,
+}
+
+/// The reroute knots of a [`GraphEditorState`], and the knots each connection
+/// goes through.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
+pub struct Reroutes {
+    pub knots: SlotMap<RerouteId, Reroute>,
+    /// The last knot before each input. Connections to inputs that are not in
+    /// this map go straight from the output to the input.
+    pub routes: SecondaryMap<InputId, RerouteId>,
+}
+
+/// A segment of a wire, identified by its downstream end. Every wire goes
+/// from the previous knot (or the source output) to this end.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum WireSegment {
+    Knot(RerouteId),
+    Input(InputId),
+}
+
+impl Reroutes {
+    /// The knots a connection goes through, from the output to the input.
+    /// A chain of knots looping back on itself is cut where it does.
+    pub fn route(&self, input: InputId) -> Vec<RerouteId> {
+        let mut route = Vec::new();
+        let mut visited = HashSet::new();
+        let mut current = self.routes.get(input).copied();
+        while let Some(knot) = current {
+            if !visited.insert(knot) {
+                break;
+            }
+            route.push(knot);
+            current = self.knots.get(knot).and_then(|knot| knot.previous);
+        }
+        route.reverse();
+        route
+    }
+
+    /// The knot at the upstream end of `segment`, if any.
+    pub fn previous(&self, segment: WireSegment) -> Option<RerouteId> {
+        match segment {
+            WireSegment::Knot(knot) => self.knots.get(knot).and_then(|knot| knot.previous),
+            WireSegment::Input(input) => self.routes.get(input).copied(),
+        }
+    }
+
+    /// The input the wire `segment` is part of. A segment leading to a knot
+    /// shared by several wires is reported with the first of their inputs.
+    pub fn segment_input(&self, segment: WireSegment) -> Option<InputId> {
+        match segment {
+            WireSegment::Input(input) => Some(input),
+            WireSegment::Knot(knot) => self
+                .routes
+                .keys()
+                .find(|input| self.route(*input).contains(&knot)),
+        }
+    }
+}
+
+impl<NodeData, DataType, ValueType, NodeTemplate, UserState, EdgeData>
+    GraphEditorState<NodeData, DataType, ValueType, NodeTemplate, UserState, EdgeData>
+{
+    /// Splits `segment` in two with a new knot at `position`. Returns `None`
+    /// if the segment is not part of a connection.
+    pub fn insert_reroute(
+        &mut self,
+        segment: WireSegment,
+        position: egui::Pos2,
+    ) -> Option<RerouteId> {
+        let source = match segment {
+            WireSegment::Knot(knot) => self.reroutes.knots.get(knot)?.source,
+            WireSegment::Input(input) => self.rerouted_source(input)?,
+        };
+        let knot = self.reroutes.knots.insert(Reroute {
+            source,
+            previous: self.reroutes.previous(segment),
+            position,
+            group: self.current_group,
+        });
+        match segment {
+            WireSegment::Knot(next) => self.reroutes.knots[next].previous = Some(knot),
+            WireSegment::Input(input) => {
+                self.reroutes.routes.insert(input, knot);
+            }
+        }
+        Some(knot)
+    }
+
+    /// The output of the wire going into `input` that can go through knots.
+    /// Inputs with several connections only have one such wire: the one
+    /// already going through knots, or else the first connection.
+    pub fn rerouted_source(&self, input: InputId) -> Option<OutputId> {
+        match self.reroutes.routes.get(input) {
+            Some(knot) => self.reroutes.knots.get(*knot).map(|knot| knot.source),
+            None => self.graph.connection(input),
+        }
+    }
+
+    /// Removes a knot. The wires going through it are joined back together.
+    pub fn remove_reroute(&mut self, knot: RerouteId) {
+        let Some(removed) = self.reroutes.knots.remove(knot) else {
+            return;
+        };
+        for other in self.reroutes.knots.values_mut() {
+            if other.previous == Some(knot) {
+                other.previous = removed.previous;
+            }
+        }
+        let routed: Vec<InputId> = self
+            .reroutes
+            .routes
+            .iter()
+            .filter(|(_, last)| **last == knot)
+            .map(|(input, _)| input)
+            .collect();
+        for input in routed {
+            match removed.previous {
+                Some(previous) => {
+                    self.reroutes.routes.insert(input, previous);
+                }
+                None => {
+                    self.reroutes.routes.remove(input);
+                }
+            }
+        }
+        if self.reroute_drag == Some(knot) {
+            self.reroute_drag = removed.previous;
+        }
+    }
+
+    /// Removes the routes of connections that no longer exist, and the knots
+    /// that no wire goes through anymore. Chains of knots looping back on
+    /// themselves, which only a corrupt file can contain, are cut. Called by
+    /// the editor every frame, so user code can freely modify the graph
+    /// connections.
+    pub fn prune_reroutes(&mut self) {
+        let mut acyclic = HashSet::new();
+        let starts: Vec<RerouteId> = self.reroutes.knots.keys().collect();
+        for start in starts {
+            let mut chain = Vec::new();
+            let mut current = Some(start);
+            while let Some(knot) = current {
+                if acyclic.contains(&knot) {
+                    break;
+                }
+                if chain.contains(&knot) {
+                    // The last knot of the chain closes the loop.
+                    let last = *chain.last().unwrap();
+                    self.reroutes.knots[last].previous = None;
+                    break;
+                }
+                chain.push(knot);
+                current = self.reroutes.knots.get(knot).and_then(|k| k.previous);
+            }
+            acyclic.extend(chain);
+        }
+
+        let graph = &self.graph;
+        let knots = &self.reroutes.knots;
+        self.reroutes.routes.retain(|input, knot| {
+            matches!(
+                knots.get(*knot),
+                Some(knot) if graph.connections(input).contains(&knot.source)
+            )
+        });
+
+        // A knot is used when an input is routed through it, or while a new
+        // wire is being dragged from it.
+        let mut used = HashSet::new();
+        let ends = self
+            .reroutes
+            .routes
+            .values()
+            .copied()
+            .chain(self.reroute_drag);
+        for end in ends {
+            let mut current = Some(end);
+            while let Some(knot) = current {
+                if !used.insert(knot) {
+                    break;
+                }
+                current = self.reroutes.knots.get(knot).and_then(|k| k.previous);
+            }
+        }
+        self.reroutes.knots.retain(|knot, _| used.contains(&knot));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    #[test]
+    fn test_reroutes() {
+        let mut state = TestState::<()>::default();
+        let (_, _, a_out) = add_placed_node(&mut state, egui::Pos2::ZERO);
+        let (_, b_in, _) = add_placed_node(&mut state, egui::Pos2::ZERO);
+        let (_, c_in, _) = add_placed_node(&mut state, egui::Pos2::ZERO);
+        state.graph.add_connection(a_out, b_in);
+        state.graph.add_connection(a_out, c_in);
+
+        let first = state
+            .insert_reroute(WireSegment::Input(b_in), egui::Pos2::ZERO)
+            .unwrap();
+        let second = state
+            .insert_reroute(WireSegment::Knot(first), egui::Pos2::ZERO)
+            .unwrap();
+        assert_eq!(state.reroutes.route(b_in), vec![second, first]);
+
+        // Fan out from the first knot.
+        state.reroutes.routes.insert(c_in, first);
+        assert_eq!(state.reroutes.route(c_in), vec![second, first]);
+        // The graph is not affected.
+        assert_eq!(state.graph.connection(b_in), Some(a_out));
+
+        state.remove_reroute(second);
+        assert_eq!(state.reroutes.route(b_in), vec![first]);
+        assert_eq!(state.reroutes.knots[first].previous, None);
+
+        // Knots without connections are pruned.
+        state.graph.remove_connection(b_in, a_out);
+        state.prune_reroutes();
+        assert_eq!(state.reroutes.route(b_in), vec![]);
+        assert_eq!(state.reroutes.knots.len(), 1);
+        state.graph.remove_connection(c_in, a_out);
+        state.prune_reroutes();
+        assert!(state.reroutes.knots.is_empty());
+    }
+
+    #[test]
+    fn test_reroute_cycles() {
+        let mut state = TestState::<()>::default();
+        let (_, _, a_out) = add_placed_node(&mut state, egui::Pos2::ZERO);
+        let (_, b_in, _) = add_placed_node(&mut state, egui::Pos2::ZERO);
+        state.graph.add_connection(a_out, b_in);
+        let first = state
+            .insert_reroute(WireSegment::Input(b_in), egui::Pos2::ZERO)
+            .unwrap();
+        let second = state
+            .insert_reroute(WireSegment::Input(b_in), egui::Pos2::ZERO)
+            .unwrap();
+
+        // As a corrupt file could contain
+        state.reroutes.knots[first].previous = Some(second);
+        assert_eq!(state.reroutes.route(b_in), vec![first, second]);
+        assert_eq!(
+            state.reroutes.segment_input(WireSegment::Knot(first)),
+            Some(b_in)
+        );
+
+        // The loop is cut, and the knots left out of the wire are removed.
+        state.prune_reroutes();
+        assert_eq!(state.reroutes.route(b_in), vec![second]);
+        assert_eq!(state.reroutes.knots[second].previous, None);
+        assert_eq!(state.reroutes.knots.len(), 1);
+    }
+}
@@ -0,0 +1,179 @@
+use std::collections::HashMap;
+
+use egui::{Pos2, Rect};
+
+/// A uniform grid bucketing items by the cells their rect overlaps, to find
+/// the items near a position without testing all of them. The editor rebuilds
+/// one for the nodes and one for the ports every frame.
+#[derive(Clone, Debug)]
+pub struct SpatialIndex<T> {
+    cell_size: f32,
+    /// The indices in `items` of the items overlapping each cell, in
+    /// insertion order.
+    cells: HashMap<(i32, i32), Vec<usize>>,
+    items: Vec<(T, Rect)>,
+}
+
+impl<T: Copy> SpatialIndex<T> {
+    /// An empty index. The cells should be about the size of the items.
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size: cell_size.max(1.0),
+            cells: HashMap::new(),
+            items: Vec::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Adds an item covering `rect`. Points are inserted as empty rects.
+    /// Items inserted later are considered to be on top.
+    pub fn insert(&mut self, item: T, rect: Rect) {
+        let index = self.items.len();
+        self.items.push((item, rect));
+        let (min, max) = self.cell_range(rect);
+        for x in min.0..=max.0 {
+            for y in min.1..=max.1 {
+                self.cells.entry((x, y)).or_default().push(index);
+            }
+        }
+    }
+
+    /// The items whose rect intersects `rect`, in insertion order.
+    pub fn query(&self, rect: Rect) -> Vec<T> {
+        self.candidates(rect)
+            .into_iter()
+            .map(|index| self.items[index])
+            .filter(|(_, item_rect)| item_rect.intersects(rect))
+            .map(|(item, _)| item)
+            .collect()
+    }
+
+    /// The last inserted item whose rect contains `pos`.
+    pub fn topmost_at(&self, pos: Pos2) -> Option<T> {
+        self.cells
+            .get(&self.cell(pos))?
+            .iter()
+            .rev()
+            .map(|index| self.items[*index])
+            .find(|(_, rect)| rect.contains(pos))
+            .map(|(item, _)| item)
+    }
+
+    /// The item closest to `pos`, no further than `max_distance`, among those
+    /// accepted by `filter`. Returns it along with its rect.
+    pub fn nearest(
+        &self,
+        pos: Pos2,
+        max_distance: f32,
+        mut filter: impl FnMut(T) -> bool,
+    ) -> Option<(T, Rect)> {
+        let area = Rect::from_center_size(pos, egui::Vec2::splat(2.0 * max_distance));
+        self.candidates(area)
+            .into_iter()
+            .map(|index| self.items[index])
+            .map(|(item, rect)| (item, rect, rect.distance_to_pos(pos)))
+            .filter(|(item, _, distance)| *distance <= max_distance && filter(*item))
+            .min_by(|a, b| a.2.total_cmp(&b.2))
+            .map(|(item, rect, _)| (item, rect))
+    }
+
+    /// The indices of the items in the cells overlapping `rect`, sorted and
+    /// without duplicates.
+    fn candidates(&self, rect: Rect) -> Vec<usize> {
+        let (min, max) = self.cell_range(rect);
+        let cell_count = (max.0 - min.0 + 1) as usize * (max.1 - min.1 + 1) as usize;
+        let mut candidates: Vec<usize> = if cell_count > self.items.len() {
+            // Visiting the cells would be slower than testing every item
+            (0..self.items.len()).collect()
+        } else {
+            (min.0..=max.0)
+                .flat_map(|x| (min.1..=max.1).map(move |y| (x, y)))
+                .filter_map(|cell| self.cells.get(&cell))
+                .flatten()
+                .copied()
+                .collect()
+        };
+        candidates.sort_unstable();
+        candidates.dedup();
+        candidates
+    }
+
+    fn cell(&self, pos: Pos2) -> (i32, i32) {
+        (
+            (pos.x / self.cell_size).floor() as i32,
+            (pos.y / self.cell_size).floor() as i32,
+        )
+    }
+
+    fn cell_range(&self, rect: Rect) -> ((i32, i32), (i32, i32)) {
+        (self.cell(rect.min), self.cell(rect.max))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::{pos2, vec2};
+
+    #[test]
+    fn test_spatial_index() {
+        let mut index = SpatialIndex::new(100.0);
+        index.insert(0, Rect::from_min_size(pos2(0.0, 0.0), vec2(150.0, 80.0)));
+        index.insert(
+            1,
+            Rect::from_min_size(pos2(120.0, 40.0), vec2(100.0, 100.0)),
+        );
+        index.insert(
+            2,
+            Rect::from_min_size(pos2(-500.0, -500.0), vec2(10.0, 10.0)),
+        );
+        assert_eq!(index.len(), 3);
+
+        // Overlapping items are on top of the ones inserted before them
+        assert_eq!(index.topmost_at(pos2(130.0, 50.0)), Some(1));
+        assert_eq!(index.topmost_at(pos2(10.0, 10.0)), Some(0));
+        assert_eq!(index.topmost_at(pos2(300.0, 300.0)), None);
+
+        assert_eq!(
+            index.query(Rect::from_min_size(pos2(100.0, 0.0), vec2(500.0, 500.0))),
+            vec![0, 1]
+        );
+        assert_eq!(
+            index.query(Rect::from_min_size(
+                pos2(-1000.0, -1000.0),
+                vec2(2000.0, 2000.0)
+            )),
+            vec![0, 1, 2]
+        );
+    }
+
+    #[test]
+    fn test_nearest_point() {
+        let mut index = SpatialIndex::new(50.0);
+        for (item, x) in [(0, 0.0), (1, 8.0), (2, 20.0), (3, 95.0)] {
+            index.insert(item, Rect::from_min_max(pos2(x, 0.0), pos2(x, 0.0)));
+        }
+        assert_eq!(index.nearest(pos2(6.0, 1.0), 10.0, |_| true).unwrap().0, 1);
+        // The nearest accepted item wins
+        assert_eq!(
+            index
+                .nearest(pos2(6.0, 1.0), 10.0, |item| item != 1)
+                .unwrap()
+                .0,
+            0
+        );
+        assert_eq!(index.nearest(pos2(6.0, 1.0), 10.0, |item| item > 1), None);
+        // Items in neighbouring cells are found
+        assert_eq!(
+            index.nearest(pos2(102.0, 3.0), 10.0, |_| true).unwrap().0,
+            3
+        );
+    }
+}
@@ -0,0 +1,154 @@
+use super::*;
+
+/// The connections changed by [`GraphEditorState::splice_node`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SplicedNode {
+    /// The removed connections, as input-output pairs: the wire the node was
+    /// inserted in, followed by those replaced by the new connections.
+    pub disconnected: Vec<(InputId, OutputId)>,
+    /// The new connections, from the output of the wire to the node and from
+    /// the node to the input of the wire, with their compatibility.
+    pub connected: Vec<(OutputId, InputId, ConnectionCompatibility)>,
+}
+
+impl<NodeData, DataType, ValueType, NodeTemplate, UserState, EdgeData>
+    GraphEditorState<NodeData, DataType, ValueType, NodeTemplate, UserState, EdgeData>
+where
+    DataType: DataTypeTrait<UserState>,
+{
+    /// Inserts `node` in the wire going from `output` to `input`: the output
+    /// is connected to the first input of the node accepting it, and the
+    /// first output of the node accepted by the input is connected to it. The
+    /// data of the wire is moved to the connection from its output to the
+    /// node.
+    ///
+    /// Returns `None`, leaving the graph untouched, when there is no such
+    /// wire, when the node is one of its ends, or when it has no compatible
+    /// ports.
+    pub fn splice_node(
+        &mut self,
+        node: NodeId,
+        output: OutputId,
+        input: InputId,
+        user_state: &mut UserState,
+    ) -> Option<SplicedNode> {
+        if !self.graph.connections(input).contains(&output)
+            || self.graph[output].node == node
+            || self.graph[input].node == node
+        {
+            return None;
+        }
+        let output_type = &self.graph[output].typ;
+        let node_input = match find_compatible_port(
+            &self.graph,
+            node,
+            AnyParameterId::Output(output),
+            output_type,
+            user_state,
+        )? {
+            (AnyParameterId::Input(node_input), compatibility) => (node_input, compatibility),
+            _ => return None,
+        };
+        let input_type = &self.graph[input].typ;
+        let node_output = match find_compatible_port(
+            &self.graph,
+            node,
+            AnyParameterId::Input(input),
+            input_type,
+            user_state,
+        )? {
+            (AnyParameterId::Output(node_output), compatibility) => (node_output, compatibility),
+            _ => return None,
+        };
+
+        self.graph.remove_connection(input, output);
+        let edge_data = self.remove_edge_data(output, input);
+        let mut disconnected = vec![(input, output)];
+        disconnected.extend(self.graph.add_connection(output, node_input.0));
+        disconnected.extend(self.graph.add_connection(node_output.0, input));
+        if let Some(data) = edge_data {
+            let _ = self.set_edge_data(output, node_input.0, data);
+        }
+        Some(SplicedNode {
+            disconnected,
+            connected: vec![
+                (output, node_input.0, node_input.1),
+                (node_output.0, input, node_output.1),
+            ],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    #[test]
+    fn test_splice_node() {
+        use TestType::*;
+
+        let mut state = TestState::<&str>::default();
+        let (_, _, a_out) = add_node_with_ports(&mut state.graph, &[], &[Flow]);
+        let (_, b_in, _) = add_node_with_ports(&mut state.graph, &[Flow], &[]);
+        let (c, c_in, c_out) = add_node_with_ports(&mut state.graph, &[Data, Flow], &[Data, Flow]);
+        let (d, _, _) = add_node_with_ports(&mut state.graph, &[Data], &[Data]);
+        state.graph.add_connection(a_out[0], b_in[0]);
+
+        // Nodes without compatible ports are left alone
+        assert_eq!(state.splice_node(d, a_out[0], b_in[0], &mut ()), None);
+
+        let spliced = state.splice_node(c, a_out[0], b_in[0], &mut ()).unwrap();
+        assert_eq!(spliced.disconnected, vec![(b_in[0], a_out[0])]);
+        assert_eq!(
+            spliced.connected,
+            vec![
+                (a_out[0], c_in[1], ConnectionCompatibility::Allowed),
+                (c_out[1], b_in[0], ConnectionCompatibility::Allowed),
+            ]
+        );
+        assert_eq!(state.graph.connections(b_in[0]), &[c_out[1]]);
+        assert_eq!(state.graph.connections(c_in[1]), &[a_out[0]]);
+        assert_eq!(state.graph.connections(c_in[0]), &[]);
+
+        // The wire is gone, and nodes are not spliced in their own wires
+        assert_eq!(state.splice_node(c, a_out[0], b_in[0], &mut ()), None);
+        assert_eq!(state.splice_node(c, c_out[1], b_in[0], &mut ()), None);
+    }
+
+    #[test]
+    fn test_splice_skips_constant_inputs() {
+        use TestType::*;
+
+        let mut state = TestState::<&str>::default();
+        let (_, _, a_out) = add_node_with_ports(&mut state.graph, &[], &[Flow]);
+        let (_, b_in, _) = add_node_with_ports(&mut state.graph, &[Flow], &[]);
+        let (c, c_in, c_out) = add_node_with_ports(&mut state.graph, &[Flow, Flow], &[Flow]);
+        state.graph.inputs[c_in[0]].kind = InputParamKind::ConstantOnly;
+        state.graph.add_connection(a_out[0], b_in[0]);
+
+        state.splice_node(c, a_out[0], b_in[0], &mut ()).unwrap();
+        assert_eq!(state.graph.connections(c_in[0]), &[]);
+        assert_eq!(state.graph.connections(c_in[1]), &[a_out[0]]);
+        assert_eq!(state.graph.connections(b_in[0]), &[c_out[0]]);
+    }
+
+    #[test]
+    fn test_splice_keeps_edge_data() {
+        use TestType::*;
+
+        let mut state = TestState::<&str>::default();
+        let (_, _, a_out) = add_node_with_ports(&mut state.graph, &[], &[Flow]);
+        let (_, b_in, _) = add_node_with_ports(&mut state.graph, &[Flow], &[]);
+        let (c, c_in, c_out) = add_node_with_ports(&mut state.graph, &[Flow], &[Flow]);
+        state.graph.add_connection(a_out[0], b_in[0]);
+        state.set_edge_data(a_out[0], b_in[0], "a to b").unwrap();
+
+        state.splice_node(c, a_out[0], b_in[0], &mut ()).unwrap();
+        assert_eq!(state.edge_data(a_out[0], b_in[0]), None);
+        assert_eq!(state.edge_data(a_out[0], c_in[0]), Some(&"a to b"));
+        assert_eq!(state.edge_data(c_out[0], b_in[0]), None);
+        state.prune_edge_data();
+        assert_eq!(state.edge_data(a_out[0], c_in[0]), Some(&"a to b"));
+    }
+}
@@ -0,0 +1,181 @@
+use egui::{Color32, Visuals};
+
+use crate::color_hex_utils::color_from_hex;
+use crate::utils::ColorUtils;
+use crate::WireStyle;
+
+#[cfg(feature = "persistence")]
+use serde::{Deserialize, Serialize};
+
+/// The colors and measurements used to draw the graph editor. Sizes are in
+/// points at a zoom level of 1, and are scaled along with the graph.
+///
+/// Use [`GraphEditorStyle::dark`] or [`GraphEditorStyle::light`] as a starting
+/// point, and set it in [`GraphEditorState::style`](crate::GraphEditorState::style).
+///
+/// When loading a saved style, the missing fields are taken from the dark
+/// preset, so styles saved by older versions keep loading.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "persistence", serde(default))]
+pub struct GraphEditorStyle {
+    /// The background of the nodes and group nodes.
+    pub node_fill: Color32,
+    /// The color of the node labels and of the widgets inside the nodes.
+    pub node_text: Color32,
+    /// The background of the node title bars. Nodes can override it with
+    /// [`NodeDataTrait::titlebar_color`](crate::NodeDataTrait::titlebar_color).
+    pub titlebar_fill: Color32,
+    /// The corner radius of the nodes.
+    pub node_rounding: f32,
+    /// Below this zoom level, nodes are drawn as plain boxes without their
+    /// label and widgets, which is much faster for large graphs. Zero always
+    /// draws the whole nodes.
+    pub simplified_zoom: f32,
+    /// The outline of selected nodes, groups, comments and hovered knots.
+    pub selection_outline: Color32,
+    pub selection_outline_width: f32,
+    /// The cross icon deleting nodes and comments.
+    pub close_button: Color32,
+    pub close_button_hovered: Color32,
+    pub close_button_pressed: Color32,
+    pub wire_style: WireStyle,
+    pub wire_width: f32,
+    /// How far the wire curves bend out of the ports, as a fraction of the
+    /// horizontal distance between both ends. Zero draws straight wires.
+    pub wire_curvature: f32,
+    /// The width of the wires of the selected and hovered nodes.
+    pub highlighted_wire_width: f32,
+    /// The opacity of the other wires while some nodes are selected or
+    /// hovered.
+    pub dimmed_wire_opacity: f32,
+    /// The dashes moving along the wires of datatypes with
+    /// [`DataTypeTrait::animated_flow`](crate::DataTypeTrait::animated_flow).
+    pub flow_color: Color32,
+    pub flow_dash_length: f32,
+    /// How fast the dashes move from the output to the input, in points per
+    /// second.
+    pub flow_speed: f32,
+    pub port_radius: f32,
+    /// Whether ports without connections are drawn hollow.
+    pub hollow_unconnected_ports: bool,
+    /// The color of a port when a connection can be dropped on it.
+    pub port_highlight: Color32,
+    /// The color of the background grid. Transparent hides the grid.
+    pub grid_color: Color32,
+    /// The distance between two lines of the background grid.
+    pub grid_spacing: f32,
+    pub box_selection_fill: Color32,
+    pub box_selection_stroke: Color32,
+    /// The text of comments and notes.
+    pub comment_text: Color32,
+    /// The colors comments cycle through when clicking their color swatch.
+    /// New frames use the first one, and new notes the second one.
+    pub comment_colors: [Color32; 6],
+    pub minimap_fill: Color32,
+    pub minimap_stroke: Color32,
+    pub minimap_node: Color32,
+    pub minimap_selected_node: Color32,
+    /// The outline of the visible part of the graph on the minimap.
+    pub minimap_viewport: Color32,
+    pub finder_fill: Color32,
+    pub finder_text: Color32,
+}
+
+impl GraphEditorStyle {
+    /// The default style on a dark background.
+    pub fn dark() -> Self {
+        let node_fill = color_from_hex("#3f3f3f").unwrap();
+        Self {
+            node_fill,
+            node_text: color_from_hex("#fefefe").unwrap(),
+            titlebar_fill: node_fill.lighten(0.8),
+            node_rounding: 4.0,
+            simplified_zoom: 0.4,
+            selection_outline: Color32::WHITE.lighten(0.8),
+            selection_outline_width: 1.0,
+            close_button: color_from_hex("#aaaaaa").unwrap(),
+            close_button_hovered: color_from_hex("#aaaaaa").unwrap(),
+            close_button_pressed: color_from_hex("#ffffff").unwrap(),
+            wire_style: WireStyle::Bezier,
+            wire_width: 5.0,
+            wire_curvature: 0.75,
+            highlighted_wire_width: 7.0,
+            dimmed_wire_opacity: 0.35,
+            flow_color: Color32::from_rgba_unmultiplied(255, 255, 255, 160),
+            flow_dash_length: 8.0,
+            flow_speed: 40.0,
+            port_radius: 5.0,
+            hollow_unconnected_ports: true,
+            port_highlight: Color32::WHITE,
+            grid_color: Color32::from_rgba_unmultiplied(255, 255, 255, 8),
+            grid_spacing: 40.0,
+            box_selection_fill: Color32::from_rgba_unmultiplied(200, 200, 200, 20),
+            box_selection_stroke: Color32::from_rgba_unmultiplied(200, 200, 200, 180),
+            comment_text: Color32::from_gray(230),
+            comment_colors: [
+                Color32::from_rgb(120, 120, 120),
+                Color32::from_rgb(230, 200, 90),
+                Color32::from_rgb(90, 160, 220),
+                Color32::from_rgb(110, 190, 110),
+                Color32::from_rgb(220, 110, 100),
+                Color32::from_rgb(170, 120, 210),
+            ],
+            minimap_fill: Color32::from_rgba_unmultiplied(20, 20, 20, 200),
+            minimap_stroke: Color32::from_gray(80),
+            minimap_node: Color32::from_gray(110),
+            minimap_selected_node: Color32::from_gray(200),
+            minimap_viewport: Color32::WHITE,
+            finder_fill: color_from_hex("#3f3f3f").unwrap(),
+            finder_text: color_from_hex("#fefefe").unwrap(),
+        }
+    }
+
+    /// The default style on a light background.
+    pub fn light() -> Self {
+        let node_fill = color_from_hex("#ffffff").unwrap();
+        Self {
+            node_fill,
+            node_text: color_from_hex("#505050").unwrap(),
+            titlebar_fill: node_fill.lighten(0.8),
+            close_button: color_from_hex("#555555").unwrap(),
+            close_button_hovered: color_from_hex("#222222").unwrap(),
+            close_button_pressed: color_from_hex("#000000").unwrap(),
+            grid_color: Color32::from_rgba_unmultiplied(0, 0, 0, 12),
+            box_selection_fill: Color32::from_rgba_unmultiplied(60, 60, 60, 20),
+            box_selection_stroke: Color32::from_rgba_unmultiplied(60, 60, 60, 180),
+            comment_text: Color32::from_gray(30),
+            comment_colors: [
+                Color32::from_rgb(150, 150, 150),
+                Color32::from_rgb(240, 210, 100),
+                Color32::from_rgb(100, 160, 225),
+                Color32::from_rgb(120, 195, 120),
+                Color32::from_rgb(230, 120, 110),
+                Color32::from_rgb(175, 130, 215),
+            ],
+            minimap_fill: Color32::from_rgba_unmultiplied(240, 240, 240, 220),
+            minimap_stroke: Color32::from_gray(170),
+            minimap_node: Color32::from_gray(160),
+            minimap_selected_node: Color32::from_gray(60),
+            minimap_viewport: Color32::BLACK,
+            finder_fill: color_from_hex("#fefefe").unwrap(),
+            finder_text: color_from_hex("#3f3f3f").unwrap(),
+            ..Self::dark()
+        }
+    }
+
+    /// The dark or light preset, matching egui's `visuals`.
+    pub fn from_visuals(visuals: &Visuals) -> Self {
+        if visuals.dark_mode {
+            Self::dark()
+        } else {
+            Self::light()
+        }
+    }
+}
+
+impl Default for GraphEditorStyle {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
@@ -0,0 +1,79 @@
+use crate::*;
+
+#[cfg(feature = "persistence")]
+use serde::{Deserialize, Serialize};
+
+/// The port types of the test graphs. `Flow` ports are execution ports.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
+#[cfg_attr(not(feature = "editor"), allow(dead_code))]
+pub(crate) enum TestType {
+    Data,
+    Flow,
+}
+
+#[cfg(feature = "editor")]
+impl<UserState> DataTypeTrait<UserState> for TestType {
+    fn data_type_color(&self, _user_state: &mut UserState) -> egui::Color32 {
+        egui::Color32::WHITE
+    }
+
+    fn name(&self) -> std::borrow::Cow<'_, str> {
+        format!("{self:?}").into()
+    }
+
+    fn is_execution(&self, _user_state: &mut UserState) -> bool {
+        *self == TestType::Flow
+    }
+}
+
+pub(crate) type TestGraph = Graph<(), TestType, u32>;
+
+#[cfg(feature = "editor")]
+pub(crate) type TestState<EdgeData> = GraphEditorState<(), TestType, u32, (), (), EdgeData>;
+
+/// Adds a node with the given input and output ports. The inputs only accept
+/// connections.
+pub(crate) fn add_node_with_ports(
+    graph: &mut TestGraph,
+    inputs: &[TestType],
+    outputs: &[TestType],
+) -> (NodeId, Vec<InputId>, Vec<OutputId>) {
+    let node = graph.add_node("node".into(), (), |_, _| {});
+    let inputs = inputs
+        .iter()
+        .map(|typ| {
+            graph.add_input_param(
+                node,
+                "in".into(),
+                *typ,
+                0,
+                InputParamKind::ConnectionOnly,
+                true,
+            )
+        })
+        .collect();
+    let outputs = outputs
+        .iter()
+        .map(|typ| graph.add_output_param(node, "out".into(), *typ))
+        .collect();
+    (node, inputs, outputs)
+}
+
+/// Adds a node with a single data input and a single data output.
+pub(crate) fn add_node(graph: &mut TestGraph) -> (NodeId, InputId, OutputId) {
+    let (node, inputs, outputs) = add_node_with_ports(graph, &[TestType::Data], &[TestType::Data]);
+    (node, inputs[0], outputs[0])
+}
+
+/// Adds a node like [`add_node`], shown in the editor at `position`.
+#[cfg(feature = "editor")]
+pub(crate) fn add_placed_node<EdgeData>(
+    state: &mut TestState<EdgeData>,
+    position: egui::Pos2,
+) -> (NodeId, InputId, OutputId) {
+    let ports = add_node(&mut state.graph);
+    state.node_positions.insert(ports.0, position);
+    state.node_order.push(ports.0);
+    ports
+}
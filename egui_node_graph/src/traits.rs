@@ -51,6 +51,102 @@ pub trait WidgetValueTrait: Default {
     }
 }
 
+/// The result of asking a [`DataTypeTrait`] whether an output of its type can
+/// be connected to a given input. See
+/// [`DataTypeTrait::connection_compatibility`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConnectionCompatibility {
+    /// The connection is allowed and the data can be passed as-is.
+    Allowed,
+    /// The connection is allowed, but the data must be converted on the way.
+    /// The string describes the conversion. It is shown to the user while
+    /// dragging the connection, and passed along in
+    /// [`NodeResponse::ConnectEventEnded`].
+    Converted(String),
+    /// The connection is not allowed. The string is the reason, and is shown
+    /// to the user while dragging the connection.
+    Rejected(String),
+}
+
+impl ConnectionCompatibility {
+    /// Returns true unless the connection is [`ConnectionCompatibility::Rejected`].
+    pub fn is_allowed(&self) -> bool {
+        !matches!(self, ConnectionCompatibility::Rejected(_))
+    }
+
+    /// Returns the description of the conversion, if any.
+    pub fn conversion(&self) -> Option<&str> {
+        match self {
+            ConnectionCompatibility::Converted(conversion) => Some(conversion),
+            _ => None,
+        }
+    }
+}
+
+/// The shape of the ports of a datatype. See [`DataTypeTrait::port_shape`].
+#[derive(Clone, Copy, Debug)]
+pub enum PortShape {
+    Circle,
+    /// A triangle pointing towards the inputs, often used for the execution
+    /// flow.
+    Triangle,
+    Square,
+    Diamond,
+    /// Draws the port with a custom function, given the painter, the center
+    /// and radius of the port on screen, its color, and whether it should be
+    /// filled.
+    Custom(fn(&egui::Painter, egui::Pos2, f32, egui::Color32, bool)),
+}
+
+impl PortShape {
+    /// Paints the port centered on `center`. Hollow ports are outlined, and
+    /// cover the same area as filled ones.
+    pub fn paint(
+        &self,
+        painter: &egui::Painter,
+        center: egui::Pos2,
+        radius: f32,
+        color: egui::Color32,
+        filled: bool,
+    ) {
+        use egui::{vec2, Color32, Shape, Stroke};
+
+        let (fill, stroke, radius) = if filled {
+            (color, Stroke::NONE, radius)
+        } else {
+            let width = radius * 0.4;
+            (
+                Color32::TRANSPARENT,
+                Stroke::new(width, color),
+                radius - width / 2.0,
+            )
+        };
+        let polygon = |points: &[egui::Vec2]| {
+            let points = points.iter().map(|p| center + *p * radius).collect();
+            painter.add(Shape::convex_polygon(points, fill, stroke));
+        };
+        match self {
+            PortShape::Circle => {
+                painter.circle(center, radius, fill, stroke);
+            }
+            PortShape::Triangle => polygon(&[vec2(-0.8, -1.0), vec2(1.0, 0.0), vec2(-0.8, 1.0)]),
+            PortShape::Square => polygon(&[
+                vec2(-0.9, -0.9),
+                vec2(0.9, -0.9),
+                vec2(0.9, 0.9),
+                vec2(-0.9, 0.9),
+            ]),
+            PortShape::Diamond => polygon(&[
+                vec2(0.0, -1.2),
+                vec2(1.2, 0.0),
+                vec2(0.0, 1.2),
+                vec2(-1.2, 0.0),
+            ]),
+            PortShape::Custom(paint) => paint(painter, center, radius, color, filled),
+        }
+    }
+}
+
 /// This trait must be implemented by the `DataType` generic parameter of the
 /// [`Graph`]. This trait tells the library how to visually expose data types
 /// to the user.
@@ -58,6 +154,27 @@ pub trait DataTypeTrait<UserState>: PartialEq + Eq {
     /// The associated port color of this datatype
     fn data_type_color(&self, user_state: &mut UserState) -> egui::Color32;
 
+    /// Decides whether an output of this datatype can be connected to an input
+    /// of `input_type`. The editor calls this while the user is dragging a
+    /// connection, and only lets it be dropped on allowed ports.
+    ///
+    /// Defaults to only allowing connections between equal datatypes.
+    fn connection_compatibility(
+        &self,
+        input_type: &Self,
+        _user_state: &mut UserState,
+    ) -> ConnectionCompatibility {
+        if self == input_type {
+            ConnectionCompatibility::Allowed
+        } else {
+            ConnectionCompatibility::Rejected(format!(
+                "Cannot connect {} to {}",
+                self.name(),
+                input_type.name()
+            ))
+        }
+    }
+
     /// The name of this datatype. Return type is specified as Cow<str> because
     /// some implementations will need to allocate a new string to provide an
     /// answer while others won't.
@@ -86,7 +203,32 @@ pub trait DataTypeTrait<UserState>: PartialEq + Eq {
     ///     }
     /// }
     /// ```
-    fn name(&self) -> std::borrow::Cow<str>;
+    fn name(&self) -> std::borrow::Cow<'_, str>;
+
+    /// Whether connections of this datatype carry the execution flow rather
+    /// than data. Used by [`LayoutStyle::ExecutionSpine`] to find the
+    /// execution chain of the graph.
+    ///
+    /// Defaults to false.
+    fn is_execution(&self, _user_state: &mut UserState) -> bool {
+        false
+    }
+
+    /// Whether the wires of this datatype are drawn with dashes moving from
+    /// the output to the input, showing which way the data flows. The editor
+    /// repaints continuously while such wires are shown.
+    ///
+    /// Defaults to false.
+    fn animated_flow(&self, _user_state: &mut UserState) -> bool {
+        false
+    }
+
+    /// The shape of the ports of this datatype.
+    ///
+    /// Defaults to [`PortShape::Circle`].
+    fn port_shape(&self, _user_state: &mut UserState) -> PortShape {
+        PortShape::Circle
+    }
 }
 
 /// This trait must be implemented for the `NodeData` generic parameter of the
@@ -186,6 +328,45 @@ where
     ) -> bool {
         true
     }
+
+    /// Entries to add to the context menu of the node, opened by
+    /// right-clicking it. They are drawn after the built-in entries. Returns
+    /// the responses of the entries that were clicked, which are reported as
+    /// [`NodeResponse::User`]. The menu closes when a response is returned.
+    fn node_context_menu(
+        &self,
+        _ui: &mut egui::Ui,
+        _node_id: NodeId,
+        _graph: &Graph<Self, Self::DataType, Self::ValueType>,
+        _user_state: &mut Self::UserState,
+    ) -> Vec<Self::Response> {
+        Vec::new()
+    }
+
+    /// Like [`NodeDataTrait::node_context_menu`], for the menu opened by
+    /// right-clicking the background of the editor. `position` is where it
+    /// was opened, in the coordinates of
+    /// [`GraphEditorState::node_positions`](crate::GraphEditorState::node_positions).
+    fn canvas_context_menu(
+        _ui: &mut egui::Ui,
+        _position: egui::Pos2,
+        _graph: &Graph<Self, Self::DataType, Self::ValueType>,
+        _user_state: &mut Self::UserState,
+    ) -> Vec<Self::Response> {
+        Vec::new()
+    }
+
+    /// Like [`NodeDataTrait::node_context_menu`], for the menu of the wire
+    /// going from `output` to `input`.
+    fn wire_context_menu(
+        _ui: &mut egui::Ui,
+        _output: OutputId,
+        _input: InputId,
+        _graph: &Graph<Self, Self::DataType, Self::ValueType>,
+        _user_state: &mut Self::UserState,
+    ) -> Vec<Self::Response> {
+        Vec::new()
+    }
 }
 
 /// This trait can be implemented by any user type. The trait tells the library
@@ -216,7 +397,7 @@ impl CategoryTrait for () {
     }
 }
 
-impl<'a> CategoryTrait for &'a str {
+impl CategoryTrait for &str {
     fn name(&self) -> String {
         self.to_string()
     }
@@ -253,7 +434,7 @@ pub trait NodeTemplateTrait: Clone {
     /// The return type is Cow<str> to allow returning owned or borrowed values
     /// more flexibly. Refer to the documentation for `DataTypeTrait::name` for
     /// more information
-    fn node_finder_label(&self, user_state: &mut Self::UserState) -> std::borrow::Cow<str>;
+    fn node_finder_label(&self, user_state: &mut Self::UserState) -> std::borrow::Cow<'_, str>;
 
     /// Vec of categories to which the node belongs.
     ///
@@ -264,6 +445,23 @@ pub trait NodeTemplateTrait: Clone {
         Vec::default()
     }
 
+    /// A short description of the node kind. The node finder shows it when
+    /// hovering the node kind, and also matches the search query against it.
+    ///
+    /// Defaults to no description.
+    fn node_finder_description(
+        &self,
+        _user_state: &mut Self::UserState,
+    ) -> std::borrow::Cow<'_, str> {
+        "".into()
+    }
+
+    /// Additional words matching this node kind in the node finder search,
+    /// like synonyms of its label.
+    fn node_finder_keywords(&self, _user_state: &mut Self::UserState) -> Vec<String> {
+        Vec::default()
+    }
+
     /// Returns a descriptive name for the node kind, used in the graph.
     fn node_graph_label(&self, user_state: &mut Self::UserState) -> String;
 
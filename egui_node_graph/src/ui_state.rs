@@ -1,5 +1,6 @@
 use super::*;
 use egui::{Rect, Style, Ui, Vec2};
+use std::collections::HashSet;
 use std::marker::PhantomData;
 use std::sync::Arc;
 
@@ -12,7 +13,9 @@ const MAX_ZOOM: f32 = 2.0;
 
 #[derive(Clone)]
 #[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
-pub struct GraphEditorState<NodeData, DataType, ValueType, NodeTemplate, UserState> {
+// The derived implementations are wrapped below, to repair loaded states.
+#[cfg_attr(feature = "persistence", serde(remote = "Self"))]
+pub struct GraphEditorState<NodeData, DataType, ValueType, NodeTemplate, UserState, EdgeData = ()> {
     pub graph: Graph<NodeData, DataType, ValueType>,
     /// Nodes are drawn in this order. Draw order is important because nodes
     /// that are drawn last are on top.
@@ -23,19 +26,93 @@ pub struct GraphEditorState<NodeData, DataType, ValueType, NodeTemplate, UserSta
     /// The currently selected node. Some interface actions depend on the
     /// currently selected node.
     pub selected_nodes: Vec<NodeId>,
+    /// The group nodes that are currently selected
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub selected_groups: Vec<GroupId>,
+    /// The comments that are currently selected
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub selected_comments: Vec<CommentId>,
     /// The mouse drag start position for an ongoing box selection.
     pub ongoing_box_selection: Option<egui::Pos2>,
     /// The position of each node.
     pub node_positions: SecondaryMap<NodeId, egui::Pos2>,
+    /// The size of each node when it was last drawn.
+    #[cfg_attr(feature = "persistence", serde(skip, default))]
+    pub node_sizes: SecondaryMap<NodeId, egui::Vec2>,
+    /// The position of the ports of each node relative to the node, when it
+    /// was last drawn. Nodes outside of the editor are not drawn, but their
+    /// wires still need to reach their ports.
+    #[cfg_attr(feature = "persistence", serde(skip, default))]
+    pub port_offsets: SecondaryMap<NodeId, Vec<(AnyParameterId, egui::Vec2)>>,
+    /// How each node is displayed. Nodes without an entry are expanded and
+    /// fit their contents.
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub node_views: SecondaryMap<NodeId, NodeView>,
     /// The node finder is used to create new nodes.
     pub node_finder: Option<NodeFinder<NodeTemplate>>,
+    /// The context menu currently open, if any.
+    #[cfg_attr(feature = "persistence", serde(skip, default))]
+    pub context_menu: Option<ContextMenu>,
+    /// Templates recently picked in the node finder, most recent first.
+    #[cfg_attr(feature = "persistence", serde(skip, default = "Vec::new"))]
+    pub recent_templates: Vec<NodeTemplate>,
+    /// The group nodes of the graph.
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub groups: NodeGroups,
+    /// The group whose contents are being edited, or `None` for the root of
+    /// the graph.
+    pub current_group: Option<GroupId>,
+    /// The reroute knots placed along the connections.
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub reroutes: Reroutes,
+    /// The knot a new wire is being dragged from, if any.
+    #[cfg_attr(feature = "persistence", serde(skip, default))]
+    pub reroute_drag: Option<RerouteId>,
+    /// The data attached to the connections going into each input, along
+    /// with their output. See [`EdgeDataTrait`].
+    #[cfg_attr(feature = "persistence", serde(default = "SecondaryMap::default"))]
+    pub edge_data: SecondaryMap<InputId, SVec<(OutputId, EdgeData)>>,
+    /// The data of the wire being dragged away from an input. It is attached
+    /// again to the connection made when dropping the wire.
+    #[cfg_attr(feature = "persistence", serde(skip, default = "Option::default"))]
+    pub edge_drag: Option<EdgeData>,
+    /// The node being dragged, if any. It is spliced into the wire it is
+    /// dropped on.
+    #[cfg_attr(feature = "persistence", serde(skip, default))]
+    pub node_drag: Option<NodeId>,
+    /// A node just created by the node finder. It is spliced into the wire
+    /// under it once it has been drawn.
+    #[cfg_attr(feature = "persistence", serde(skip, default))]
+    pub pending_splice: Option<NodeId>,
+    /// The comment frames and sticky notes drawn beneath the nodes.
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub comments: SlotMap<CommentId, Comment>,
+    /// The comment whose text is being edited.
+    #[cfg_attr(feature = "persistence", serde(skip, default))]
+    pub editing_comment: Option<CommentId>,
     /// The panning of the graph viewport.
     pub pan_zoom: PanZoom,
+    /// The configuration of the minimap shown over the editor.
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub minimap: MinimapSettings,
+    /// The style the editor is drawn with. When unset, the dark or light
+    /// preset is picked to match egui's visuals.
+    pub style: Option<GraphEditorStyle>,
+    /// The transition started by the last automatic layout, if still running.
+    #[cfg_attr(feature = "persistence", serde(skip, default))]
+    pub layout_animation: Option<LayoutAnimation>,
+    /// The undo and redo history. It is not persisted.
+    #[cfg_attr(feature = "persistence", serde(skip, default = "UndoHistory::default"))]
+    pub history: UndoHistory<NodeData, DataType, ValueType, EdgeData>,
+    /// The last nodes copied in this editor. Used to paste when the system
+    /// clipboard does not contain copied nodes.
+    #[cfg_attr(feature = "persistence", serde(skip, default = "Option::default"))]
+    pub clipboard: Option<GraphClipboard<NodeData, DataType, ValueType, EdgeData>>,
     pub _user_state: PhantomData<fn() -> UserState>,
 }
 
-impl<NodeData, DataType, ValueType, NodeKind, UserState>
-    GraphEditorState<NodeData, DataType, ValueType, NodeKind, UserState>
+impl<NodeData, DataType, ValueType, NodeKind, UserState, EdgeData>
+    GraphEditorState<NodeData, DataType, ValueType, NodeKind, UserState, EdgeData>
 {
     pub fn new(default_zoom: f32) -> Self {
         Self {
@@ -43,9 +120,110 @@ impl<NodeData, DataType, ValueType, NodeKind, UserState>
             ..Default::default()
         }
     }
+
+    /// Whether the node is collapsed to its title bar.
+    pub fn is_node_collapsed(&self, node: NodeId) -> bool {
+        matches!(self.node_views.get(node), Some(view) if view.collapsed)
+    }
+
+    /// Collapses a node to its title bar, or expands it back.
+    pub fn set_node_collapsed(&mut self, node: NodeId, collapsed: bool) {
+        if let Some(entry) = self.node_views.entry(node) {
+            entry.or_default().collapsed = collapsed;
+        }
+    }
+
+    /// Repairs the graph with [`Graph::repair`], and makes the editor agree
+    /// with it: references to missing nodes, groups and parameters are
+    /// removed, and nodes missing from the editor are placed at the origin.
+    /// Returns the inconsistencies found in the graph.
+    ///
+    /// This is done when loading a saved editor, so files written by older
+    /// versions or corrupted can still be opened.
+    pub fn repair(&mut self) -> Vec<GraphViolation> {
+        let violations = self.graph.repair();
+        let graph = &self.graph;
+        let mut ordered = HashSet::new();
+        self.node_order
+            .retain(|node| graph.nodes.contains_key(*node) && ordered.insert(*node));
+        for node in graph.iter_nodes() {
+            if !ordered.contains(&node) {
+                self.node_order.push(node);
+            }
+            if !self.node_positions.contains_key(node) {
+                self.node_positions.insert(node, egui::Pos2::ZERO);
+            }
+        }
+        self.node_positions
+            .retain(|node, _| graph.nodes.contains_key(node));
+        self.node_sizes
+            .retain(|node, _| graph.nodes.contains_key(node));
+        self.port_offsets
+            .retain(|node, _| graph.nodes.contains_key(node));
+        self.node_views
+            .retain(|node, _| graph.nodes.contains_key(node));
+        self.selected_nodes
+            .retain(|node| graph.nodes.contains_key(*node));
+
+        let group_ids: HashSet<GroupId> = self.groups.groups.keys().collect();
+        self.groups
+            .node_groups
+            .retain(|node, group| graph.nodes.contains_key(node) && group_ids.contains(group));
+        for group in self.groups.groups.values_mut() {
+            if matches!(group.parent, Some(parent) if !group_ids.contains(&parent)) {
+                group.parent = None;
+            }
+            group
+                .inputs
+                .retain(|input| graph.inputs.contains_key(*input));
+            group
+                .outputs
+                .retain(|output| graph.outputs.contains_key(*output));
+        }
+        self.selected_groups
+            .retain(|group| group_ids.contains(group));
+        if matches!(self.current_group, Some(group) if !group_ids.contains(&group)) {
+            self.current_group = None;
+        }
+        self.prune_reroutes();
+        self.prune_edge_data();
+        violations
+    }
 }
-impl<NodeData, DataType, ValueType, NodeKind, UserState> Default
-    for GraphEditorState<NodeData, DataType, ValueType, NodeKind, UserState>
+
+#[cfg(feature = "persistence")]
+impl<NodeData, DataType, ValueType, NodeTemplate, UserState, EdgeData> Serialize
+    for GraphEditorState<NodeData, DataType, ValueType, NodeTemplate, UserState, EdgeData>
+where
+    NodeData: Serialize,
+    DataType: Serialize,
+    ValueType: Serialize,
+    NodeTemplate: Serialize,
+    EdgeData: Serialize,
+{
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Self::serialize(self, serializer)
+    }
+}
+
+#[cfg(feature = "persistence")]
+impl<'de, NodeData, DataType, ValueType, NodeTemplate, UserState, EdgeData> Deserialize<'de>
+    for GraphEditorState<NodeData, DataType, ValueType, NodeTemplate, UserState, EdgeData>
+where
+    NodeData: Deserialize<'de>,
+    DataType: Deserialize<'de>,
+    ValueType: Deserialize<'de>,
+    NodeTemplate: Deserialize<'de>,
+    EdgeData: Deserialize<'de>,
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut state = Self::deserialize(deserializer)?;
+        state.repair();
+        Ok(state)
+    }
+}
+impl<NodeData, DataType, ValueType, NodeKind, UserState, EdgeData> Default
+    for GraphEditorState<NodeData, DataType, ValueType, NodeKind, UserState, EdgeData>
 {
     fn default() -> Self {
         Self {
@@ -53,15 +231,51 @@ impl<NodeData, DataType, ValueType, NodeKind, UserState> Default
             node_order: Default::default(),
             connection_in_progress: Default::default(),
             selected_nodes: Default::default(),
+            selected_groups: Default::default(),
+            selected_comments: Default::default(),
             ongoing_box_selection: Default::default(),
             node_positions: Default::default(),
+            node_sizes: Default::default(),
+            port_offsets: Default::default(),
+            node_views: Default::default(),
             node_finder: Default::default(),
+            context_menu: Default::default(),
+            recent_templates: Default::default(),
+            groups: Default::default(),
+            current_group: Default::default(),
+            reroutes: Default::default(),
+            reroute_drag: Default::default(),
+            edge_data: Default::default(),
+            edge_drag: Default::default(),
+            node_drag: Default::default(),
+            pending_splice: Default::default(),
+            comments: Default::default(),
+            editing_comment: Default::default(),
             pan_zoom: Default::default(),
+            minimap: Default::default(),
+            style: Default::default(),
+            layout_animation: Default::default(),
+            history: Default::default(),
+            clipboard: Default::default(),
             _user_state: Default::default(),
         }
     }
 }
 
+/// How a node is displayed in the editor.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
+pub struct NodeView {
+    /// Collapsed nodes only show their title bar. Their wires meet at a single
+    /// port on each side of it.
+    pub collapsed: bool,
+    /// The size the node was resized to, scaled with the zoom like the node
+    /// positions. Nodes without one fit their contents, up to
+    /// [`GraphNodeWidget::MAX_NODE_SIZE`]. Taller contents still grow the
+    /// node.
+    pub size: Option<Vec2>,
+}
+
 #[cfg(feature = "persistence")]
 fn _default_clip_rect() -> Rect {
     Rect::NOTHING
@@ -127,3 +341,88 @@ where
 
     response
 }
+
+#[cfg(all(test, feature = "persistence"))]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    #[test]
+    fn test_repair_on_load() {
+        let mut state = TestState::<()>::default();
+        let (a, _, a_out) = add_placed_node(&mut state, egui::Pos2::ZERO);
+        let (b, _, _) = add_placed_node(&mut state, egui::Pos2::ZERO);
+        // A node missing from the node order and positions
+        let (c, c_in, _) = add_node(&mut state.graph);
+        state.graph.add_connection(a_out, c_in);
+        // The node was removed, but not its parameters
+        state.graph.nodes.remove(a);
+        state.selected_nodes.push(a);
+
+        let json = serde_json::to_string(&state).unwrap();
+        let loaded: TestState<()> = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.graph.validate(), vec![]);
+        assert_eq!(loaded.graph.connections(c_in), &[]);
+        assert_eq!(loaded.node_order, vec![b, c]);
+        assert!(loaded.node_positions.contains_key(c));
+        assert!(!loaded.node_positions.contains_key(a));
+        assert!(loaded.selected_nodes.is_empty());
+    }
+
+    /// States saved by older versions, without the fields added since, still
+    /// load.
+    #[test]
+    fn test_load_older_state() {
+        let state = TestState::<()>::default();
+        let mut json = serde_json::to_value(&state).unwrap();
+        let fields = json.as_object_mut().unwrap();
+        for key in [
+            "selected_groups",
+            "groups",
+            "current_group",
+            "selected_comments",
+            "comments",
+            "reroutes",
+            "minimap",
+        ] {
+            assert!(fields.remove(key).is_some());
+        }
+        // Styles saved before the minimap and comment colors
+        let mut style = serde_json::to_value(GraphEditorStyle::light()).unwrap();
+        let style_fields = style.as_object_mut().unwrap();
+        for key in ["comment_colors", "minimap_fill", "minimap_viewport"] {
+            assert!(style_fields.remove(key).is_some());
+        }
+        fields.insert("style".into(), style);
+
+        let loaded: TestState<()> = serde_json::from_value(json).unwrap();
+        assert!(loaded.groups.groups.is_empty());
+        assert_eq!(loaded.current_group, None);
+        let style = loaded.style.unwrap();
+        assert_eq!(style.node_fill, GraphEditorStyle::light().node_fill);
+        assert_eq!(style.minimap_fill, GraphEditorStyle::dark().minimap_fill);
+    }
+
+    /// Graphs saved before inputs could have several connections store a
+    /// single output per input.
+    #[test]
+    fn test_load_single_connections() {
+        let mut state = TestState::<()>::default();
+        let (_, _, output) = add_node(&mut state.graph);
+        let (_, input, _) = add_node(&mut state.graph);
+        state.graph.add_connection(output, input);
+
+        let mut json = serde_json::to_value(&state).unwrap();
+        let mut singles = 0;
+        for slot in json["graph"]["connections"].as_array_mut().unwrap() {
+            if let Some(value) = slot.get_mut("value").filter(|value| value.is_array()) {
+                *value = value[0].take();
+                singles += 1;
+            }
+        }
+        assert_eq!(singles, 1);
+
+        let loaded: TestState<()> = serde_json::from_value(json).unwrap();
+        assert_eq!(loaded.graph.connections(input), &[output]);
+    }
+}
@@ -0,0 +1,427 @@
+use super::*;
+
+/// The parts of a [`GraphEditorState`] that are saved and restored by undo
+/// and redo.
+#[derive(Clone, Debug)]
+pub struct EditorSnapshot<NodeData, DataType, ValueType, EdgeData = ()> {
+    pub graph: Graph<NodeData, DataType, ValueType>,
+    pub node_positions: SecondaryMap<NodeId, egui::Pos2>,
+    pub node_views: SecondaryMap<NodeId, NodeView>,
+    pub node_order: Vec<NodeId>,
+    pub groups: NodeGroups,
+    pub comments: SlotMap<CommentId, Comment>,
+    pub reroutes: Reroutes,
+    pub edge_data: SecondaryMap<InputId, SVec<(OutputId, EdgeData)>>,
+}
+
+/// Consecutive undo steps with the same merge key are merged into a single
+/// step, until the user releases the mouse. This avoids recording one step
+/// per frame while dragging nodes or editing a value. The editor only takes a
+/// snapshot of its state once the gesture ends.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UndoMergeKey {
+    MoveNodes,
+    EditValue(InputId),
+    ResizeComment(CommentId),
+    ResizeNode(NodeId),
+    EditComment(CommentId),
+    AutoLayout,
+}
+
+#[derive(Clone, Debug)]
+struct UndoStep<NodeData, DataType, ValueType, EdgeData> {
+    label: String,
+    /// The state before the step was performed.
+    snapshot: EditorSnapshot<NodeData, DataType, ValueType, EdgeData>,
+}
+
+#[derive(Clone, Debug)]
+struct PendingStep {
+    label: String,
+    merge_key: Option<UndoMergeKey>,
+    end_merge: bool,
+}
+
+/// The undo and redo stacks of a [`GraphEditorState`].
+///
+/// Steps performed through [`GraphEditorState::draw_graph_editor`] are
+/// recorded automatically. They are committed at the beginning of the next
+/// call, so any changes made by user code while handling the returned
+/// [`NodeResponse`]s become part of the same step. Gestures spanning several
+/// frames, like dragging nodes, are committed once they end. Changes made by user code
+/// on their own should be recorded with
+/// [`GraphEditorState::record_undo_step`] or [`GraphEditorState::undoable`].
+/// Otherwise, they get merged into the next recorded step.
+#[derive(Clone, Debug)]
+pub struct UndoHistory<NodeData, DataType, ValueType, EdgeData = ()> {
+    undo_stack: Vec<UndoStep<NodeData, DataType, ValueType, EdgeData>>,
+    redo_stack: Vec<UndoStep<NodeData, DataType, ValueType, EdgeData>>,
+    /// The state after the last committed step. This becomes the undo
+    /// snapshot of the next step.
+    current: Option<EditorSnapshot<NodeData, DataType, ValueType, EdgeData>>,
+    /// A step that was performed but not yet committed.
+    pending: Option<PendingStep>,
+    merge_key: Option<UndoMergeKey>,
+    /// Maximum number of undo steps to keep. The oldest steps are discarded
+    /// first.
+    pub max_steps: usize,
+}
+
+impl<NodeData, DataType, ValueType, EdgeData> Default
+    for UndoHistory<NodeData, DataType, ValueType, EdgeData>
+{
+    fn default() -> Self {
+        Self {
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            current: None,
+            pending: None,
+            merge_key: None,
+            max_steps: 100,
+        }
+    }
+}
+
+impl<NodeData, DataType, ValueType, EdgeData> UndoHistory<NodeData, DataType, ValueType, EdgeData> {
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty() || self.pending.is_some()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    /// The label of the step that would be reverted by an undo.
+    pub fn undo_label(&self) -> Option<&str> {
+        match &self.pending {
+            Some(pending) => Some(&pending.label),
+            None => self.undo_stack.last().map(|step| step.label.as_str()),
+        }
+    }
+
+    /// The label of the step that would be performed again by a redo.
+    pub fn redo_label(&self) -> Option<&str> {
+        self.redo_stack.last().map(|step| step.label.as_str())
+    }
+
+    /// Discards all the undo and redo steps.
+    pub fn clear(&mut self) {
+        *self = Self {
+            max_steps: self.max_steps,
+            ..Default::default()
+        };
+    }
+
+    /// Marks that a step has been performed. It will be committed on the next
+    /// call to [`UndoHistory::commit`]. When several steps are performed
+    /// before committing, they are recorded as a single step with the label of
+    /// the first one.
+    pub fn defer(&mut self, label: impl Into<String>, merge_key: Option<UndoMergeKey>) {
+        match &mut self.pending {
+            Some(pending) => {
+                if pending.merge_key != merge_key {
+                    pending.merge_key = None;
+                }
+            }
+            None => {
+                self.pending = Some(PendingStep {
+                    label: label.into(),
+                    merge_key,
+                    end_merge: false,
+                })
+            }
+        }
+    }
+
+    /// Stops merging steps. If there is a pending step, it can still be merged
+    /// with the previous one.
+    pub fn end_merge(&mut self) {
+        match &mut self.pending {
+            Some(pending) => pending.end_merge = true,
+            None => self.merge_key = None,
+        }
+    }
+
+    /// Returns whether there is a pending step or no snapshot was taken yet,
+    /// meaning [`UndoHistory::commit`] needs a snapshot of the current state.
+    pub fn needs_commit(&self) -> bool {
+        self.pending.is_some() || self.current.is_none()
+    }
+
+    /// Returns whether the pending step is a gesture that is still going on,
+    /// such as a drag before the mouse is released.
+    pub fn gesture_in_progress(&self) -> bool {
+        matches!(&self.pending, Some(pending) if pending.merge_key.is_some() && !pending.end_merge)
+    }
+
+    /// Commits the pending step, if any. `now` must be a snapshot of the
+    /// current editor state.
+    pub fn commit(&mut self, now: EditorSnapshot<NodeData, DataType, ValueType, EdgeData>) {
+        let previous = self.current.replace(now);
+        let Some(pending) = self.pending.take() else {
+            return;
+        };
+        let Some(previous) = previous else {
+            return;
+        };
+
+        let merged = pending.merge_key.is_some() && pending.merge_key == self.merge_key;
+        if !merged {
+            self.undo_stack.push(UndoStep {
+                label: pending.label,
+                snapshot: previous,
+            });
+            if self.undo_stack.len() > self.max_steps {
+                let excess = self.undo_stack.len() - self.max_steps;
+                self.undo_stack.drain(..excess);
+            }
+        }
+        self.merge_key = if pending.end_merge {
+            None
+        } else {
+            pending.merge_key
+        };
+        self.redo_stack.clear();
+    }
+
+    /// Pops the last undo step. `now` must be a snapshot of the current editor
+    /// state, and is kept to redo the step. Returns the snapshot to restore.
+    ///
+    /// Any pending step must be committed first.
+    pub fn undo(
+        &mut self,
+        now: EditorSnapshot<NodeData, DataType, ValueType, EdgeData>,
+    ) -> Option<EditorSnapshot<NodeData, DataType, ValueType, EdgeData>>
+    where
+        EditorSnapshot<NodeData, DataType, ValueType, EdgeData>: Clone,
+    {
+        debug_assert!(self.pending.is_none());
+        let step = self.undo_stack.pop()?;
+        self.redo_stack.push(UndoStep {
+            label: step.label,
+            snapshot: now,
+        });
+        self.current = Some(step.snapshot.clone());
+        self.merge_key = None;
+        Some(step.snapshot)
+    }
+
+    /// The opposite of [`UndoHistory::undo`].
+    pub fn redo(
+        &mut self,
+        now: EditorSnapshot<NodeData, DataType, ValueType, EdgeData>,
+    ) -> Option<EditorSnapshot<NodeData, DataType, ValueType, EdgeData>>
+    where
+        EditorSnapshot<NodeData, DataType, ValueType, EdgeData>: Clone,
+    {
+        debug_assert!(self.pending.is_none());
+        let step = self.redo_stack.pop()?;
+        self.undo_stack.push(UndoStep {
+            label: step.label,
+            snapshot: now,
+        });
+        self.current = Some(step.snapshot.clone());
+        self.merge_key = None;
+        Some(step.snapshot)
+    }
+}
+
+impl<NodeData, DataType, ValueType, NodeTemplate, UserState, EdgeData>
+    GraphEditorState<NodeData, DataType, ValueType, NodeTemplate, UserState, EdgeData>
+where
+    NodeData: Clone,
+    DataType: Clone,
+    ValueType: Clone,
+    EdgeData: Clone,
+{
+    /// Takes a snapshot of the parts of the editor affected by undo and redo.
+    pub fn snapshot(&self) -> EditorSnapshot<NodeData, DataType, ValueType, EdgeData> {
+        let mut graph = self.graph.clone();
+        // The changes belong to the live graph, not to its history
+        graph.journal = ChangeJournal::default();
+        EditorSnapshot {
+            graph,
+            node_positions: self.node_positions.clone(),
+            node_views: self.node_views.clone(),
+            node_order: self.node_order.clone(),
+            groups: self.groups.clone(),
+            comments: self.comments.clone(),
+            reroutes: self.reroutes.clone(),
+            edge_data: self.edge_data.clone(),
+        }
+    }
+
+    /// Restores a snapshot taken with [`GraphEditorState::snapshot`].
+    pub fn restore(&mut self, snapshot: EditorSnapshot<NodeData, DataType, ValueType, EdgeData>) {
+        let journal = std::mem::take(&mut self.graph.journal);
+        self.graph = snapshot.graph;
+        self.graph.journal = journal;
+        self.graph.journal.record(GraphChange::Replaced);
+        self.node_positions = snapshot.node_positions;
+        self.node_views = snapshot.node_views;
+        self.node_order = snapshot.node_order;
+        self.groups = snapshot.groups;
+        self.comments = snapshot.comments;
+        self.reroutes = snapshot.reroutes;
+        self.reroute_drag = None;
+        self.edge_data = snapshot.edge_data;
+        self.edge_drag = None;
+        self.node_drag = None;
+        self.pending_splice = None;
+        // Make sure to not leave references to nodes that no longer exist.
+        let graph = &self.graph;
+        self.selected_nodes
+            .retain(|id| graph.nodes.contains_key(*id));
+        let groups = &self.groups.groups;
+        self.selected_groups.retain(|id| groups.contains_key(*id));
+        let comments = &self.comments;
+        self.selected_comments
+            .retain(|id| comments.contains_key(*id));
+        self.editing_comment = None;
+        if let Some(current) = self.current_group {
+            if !groups.contains_key(current) {
+                self.current_group = None;
+            }
+        }
+        self.connection_in_progress = None;
+    }
+
+    /// Commits the step performed by the editor during the last frame, unless
+    /// it is a gesture still going on. Its frames are then committed together
+    /// once it ends, to avoid taking a snapshot every frame.
+    pub(crate) fn commit_frame_undo_step(&mut self) {
+        if !self.history.gesture_in_progress() {
+            self.commit_undo_step();
+        }
+    }
+
+    /// Commits the pending step, if any.
+    pub(crate) fn commit_undo_step(&mut self) {
+        if self.history.needs_commit() {
+            let snapshot = self.snapshot();
+            self.history.commit(snapshot);
+        }
+    }
+
+    /// Records the changes made to the editor state since the last recorded
+    /// step as a new undo step. If the editor performed a step in the last
+    /// frame that was not committed yet, the changes are recorded together
+    /// with it under the new label.
+    pub fn record_undo_step(&mut self, label: impl Into<String>) {
+        let label = label.into();
+        match &mut self.history.pending {
+            Some(pending) => {
+                pending.label = label;
+                pending.merge_key = None;
+            }
+            None => self.history.defer(label, None),
+        }
+        self.commit_undo_step();
+    }
+
+    /// Runs `f` and records all the changes it makes as a single undo step.
+    pub fn undoable<R>(&mut self, label: impl Into<String>, f: impl FnOnce(&mut Self) -> R) -> R {
+        // Changes made before calling this function should not be part of the
+        // new step.
+        self.commit_undo_step();
+        let result = f(self);
+        self.record_undo_step(label);
+        result
+    }
+
+    /// Reverts the last undo step. Returns false if there was nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        self.commit_undo_step();
+        let now = self.snapshot();
+        match self.history.undo(now) {
+            Some(snapshot) => {
+                self.restore(snapshot);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Performs the last undone step again. Returns false if there was nothing
+    /// to redo.
+    pub fn redo(&mut self) -> bool {
+        self.commit_undo_step();
+        let now = self.snapshot();
+        match self.history.redo(now) {
+            Some(snapshot) => {
+                self.restore(snapshot);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    #[test]
+    fn test_undo_redo() {
+        let mut state = TestState::<()>::default();
+        state.graph.journal.set_enabled(true);
+        state.commit_undo_step();
+
+        let (node, input, output) =
+            state.undoable("Add node", |state| add_placed_node(state, egui::Pos2::ZERO));
+        assert_eq!(state.history.undo_label(), Some("Add node"));
+        assert_eq!(
+            state.graph.journal.drain().collect::<Vec<_>>(),
+            vec![
+                GraphChange::NodeAdded(node),
+                GraphChange::InputAdded { node, input },
+                GraphChange::OutputAdded { node, output },
+            ]
+        );
+
+        assert!(state.undo());
+        assert!(!state.graph.nodes.contains_key(node));
+        assert!(state.node_order.is_empty());
+        assert_eq!(state.graph.journal.changes(), &[GraphChange::Replaced]);
+        assert!(!state.undo());
+
+        assert!(state.redo());
+        assert!(state.graph.nodes.contains_key(node));
+        assert_eq!(state.node_order, vec![node]);
+        assert!(!state.redo());
+    }
+
+    #[test]
+    fn test_merged_steps() {
+        let mut state = TestState::<()>::default();
+        let (node, _, _) = add_placed_node(&mut state, egui::Pos2::ZERO);
+        state.commit_undo_step();
+
+        // Three frames of dragging, then the mouse is released. The drag is
+        // only committed once it ends.
+        for _ in 0..3 {
+            state.node_positions[node] += egui::vec2(10.0, 0.0);
+            state
+                .history
+                .defer("Move nodes", Some(UndoMergeKey::MoveNodes));
+            state.commit_frame_undo_step();
+            assert!(state.history.undo_stack.is_empty());
+        }
+        state.history.end_merge();
+        state.commit_frame_undo_step();
+        assert_eq!(state.history.undo_stack.len(), 1);
+
+        // A second drag is a separate step.
+        state.node_positions[node] += egui::vec2(10.0, 0.0);
+        state
+            .history
+            .defer("Move nodes", Some(UndoMergeKey::MoveNodes));
+
+        assert!(state.undo());
+        assert_eq!(state.node_positions[node], egui::pos2(30.0, 0.0));
+        assert!(state.undo());
+        assert_eq!(state.node_positions[node], egui::Pos2::ZERO);
+        assert!(!state.undo());
+    }
+}
@@ -0,0 +1,229 @@
+use super::*;
+
+/// An inconsistency in a [`Graph`]. The methods of the graph never create
+/// them, but they can be found in graphs loaded from old or corrupted files,
+/// or modified through their public fields. Indexing the graph with the ids
+/// involved may panic.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum GraphViolation {
+    #[error("Node {node:?} has the id {id:?}.")]
+    WrongNodeId { node: NodeId, id: NodeId },
+
+    #[error("Input {input:?} has the id {id:?}.")]
+    WrongInputId { input: InputId, id: InputId },
+
+    #[error("Output {output:?} has the id {id:?}.")]
+    WrongOutputId { output: OutputId, id: OutputId },
+
+    /// The node lists an input that does not exist, or that belongs to
+    /// another node.
+    #[error("Node {node:?} lists the input {input:?}, which is not one of its inputs.")]
+    DanglingNodeInput { node: NodeId, input: InputId },
+
+    #[error("Node {node:?} lists the output {output:?}, which is not one of its outputs.")]
+    DanglingNodeOutput { node: NodeId, output: OutputId },
+
+    /// [`InputParam::node`] is a missing node, or a node not listing the
+    /// input.
+    #[error("Input {input:?} belongs to node {node:?}, which does not list it.")]
+    OrphanInput { input: InputId, node: NodeId },
+
+    #[error("Output {output:?} belongs to node {node:?}, which does not list it.")]
+    OrphanOutput { output: OutputId, node: NodeId },
+
+    /// The input or the output of the connection does not exist.
+    #[error("The connection from {output:?} to {input:?} has a missing end.")]
+    DanglingConnection { output: OutputId, input: InputId },
+
+    #[error("The connection from {output:?} to {input:?} is listed several times.")]
+    DuplicateConnection { output: OutputId, input: InputId },
+}
+
+impl<NodeData, DataType, ValueType> Graph<NodeData, DataType, ValueType> {
+    /// Checks that all the references between the nodes, parameters and
+    /// connections of the graph are consistent. Returns the inconsistencies
+    /// found, if any.
+    pub fn validate(&self) -> Vec<GraphViolation> {
+        let mut violations = Vec::new();
+        for (key, node) in &self.nodes {
+            if node.id != key {
+                violations.push(GraphViolation::WrongNodeId {
+                    node: key,
+                    id: node.id,
+                });
+            }
+            for input in node.input_ids() {
+                if !matches!(self.inputs.get(input), Some(param) if param.node == key) {
+                    violations.push(GraphViolation::DanglingNodeInput { node: key, input });
+                }
+            }
+            for output in node.output_ids() {
+                if !matches!(self.outputs.get(output), Some(param) if param.node == key) {
+                    violations.push(GraphViolation::DanglingNodeOutput { node: key, output });
+                }
+            }
+        }
+
+        for (key, param) in &self.inputs {
+            if param.id != key {
+                violations.push(GraphViolation::WrongInputId {
+                    input: key,
+                    id: param.id,
+                });
+            }
+            let listed = matches!(
+                self.nodes.get(param.node),
+                Some(node) if node.input_ids().any(|i| i == key)
+            );
+            if !listed {
+                violations.push(GraphViolation::OrphanInput {
+                    input: key,
+                    node: param.node,
+                });
+            }
+        }
+        for (key, param) in &self.outputs {
+            if param.id != key {
+                violations.push(GraphViolation::WrongOutputId {
+                    output: key,
+                    id: param.id,
+                });
+            }
+            let listed = matches!(
+                self.nodes.get(param.node),
+                Some(node) if node.output_ids().any(|o| o == key)
+            );
+            if !listed {
+                violations.push(GraphViolation::OrphanOutput {
+                    output: key,
+                    node: param.node,
+                });
+            }
+        }
+
+        for (input, outputs) in &self.connections {
+            for (index, output) in outputs.iter().copied().enumerate() {
+                if !self.inputs.contains_key(input) || !self.outputs.contains_key(output) {
+                    violations.push(GraphViolation::DanglingConnection { output, input });
+                } else if outputs[..index].contains(&output) {
+                    violations.push(GraphViolation::DuplicateConnection { output, input });
+                }
+            }
+        }
+        violations
+    }
+
+    /// Fixes the inconsistencies reported by [`Graph::validate`], and returns
+    /// them. Wrong ids are corrected, and references to missing items are
+    /// removed along with the parameters not listed by their node.
+    pub fn repair(&mut self) -> Vec<GraphViolation> {
+        let violations = self.validate();
+        if violations.is_empty() {
+            return violations;
+        }
+        for violation in &violations {
+            match *violation {
+                GraphViolation::WrongNodeId { node, .. } => self.nodes[node].id = node,
+                GraphViolation::WrongInputId { input, .. } => self.inputs[input].id = input,
+                GraphViolation::WrongOutputId { output, .. } => self.outputs[output].id = output,
+                GraphViolation::DanglingNodeInput { node, input } => {
+                    self.nodes[node].inputs.retain(|(_, id)| *id != input);
+                }
+                GraphViolation::DanglingNodeOutput { node, output } => {
+                    self.nodes[node].outputs.retain(|(_, id)| *id != output);
+                }
+                GraphViolation::OrphanInput { input, .. } => {
+                    self.inputs.remove(input);
+                }
+                GraphViolation::OrphanOutput { output, .. } => {
+                    self.outputs.remove(output);
+                }
+                // Handled below, along with the connections of the removed
+                // parameters.
+                GraphViolation::DanglingConnection { .. }
+                | GraphViolation::DuplicateConnection { .. } => {}
+            }
+        }
+
+        let inputs = &self.inputs;
+        let outputs = &self.outputs;
+        self.connections.retain(|input, connected| {
+            let mut unique = SVec::new();
+            for output in connected.drain(..) {
+                if outputs.contains_key(output) && !unique.contains(&output) {
+                    unique.push(output);
+                }
+            }
+            *connected = unique;
+            inputs.contains_key(input) && !connected.is_empty()
+        });
+        self.journal.record(GraphChange::Replaced);
+        violations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    #[test]
+    fn test_valid_graph() {
+        let mut graph = TestGraph::new();
+        let (a, _, a_out) = add_node(&mut graph);
+        let (_, b_in, _) = add_node(&mut graph);
+        graph.add_connection(a_out, b_in);
+        graph.remove_node(a);
+        assert_eq!(graph.validate(), vec![]);
+        assert_eq!(graph.repair(), vec![]);
+    }
+
+    #[test]
+    fn test_repair() {
+        let mut graph = TestGraph::new();
+        let (a, a_in, a_out) = add_node(&mut graph);
+        let (b, b_in, b_out) = add_node(&mut graph);
+        let (c, c_in, _) = add_node(&mut graph);
+        graph.add_connection(a_out, b_in);
+        graph.add_connection(b_out, c_in);
+
+        // Corrupt the graph the way a bad file could
+        graph.nodes.remove(a);
+        graph.connections[c_in].push(b_out);
+        graph.nodes[b].inputs.push(("other".into(), c_in));
+        graph.nodes[c].id = b;
+
+        let violations = graph.validate();
+        assert!(violations.contains(&GraphViolation::WrongNodeId { node: c, id: b }));
+        assert!(violations.contains(&GraphViolation::DanglingNodeInput {
+            node: b,
+            input: c_in
+        }));
+        assert!(violations.contains(&GraphViolation::OrphanInput {
+            input: a_in,
+            node: a
+        }));
+        assert!(violations.contains(&GraphViolation::OrphanOutput {
+            output: a_out,
+            node: a
+        }));
+        // Listed by another node, but still owned by its node
+        assert!(!violations.contains(&GraphViolation::OrphanInput {
+            input: c_in,
+            node: c
+        }));
+        assert!(violations.contains(&GraphViolation::DuplicateConnection {
+            output: b_out,
+            input: c_in
+        }));
+
+        assert_eq!(graph.repair(), violations);
+        assert_eq!(graph.validate(), vec![]);
+        assert_eq!(graph[c].id, c);
+        assert_eq!(graph[b].input_ids().collect::<Vec<_>>(), vec![b_in]);
+        assert!(!graph.outputs.contains_key(a_out));
+        // The connection from the removed output is removed as well
+        assert_eq!(graph.connections(b_in), &[]);
+        assert_eq!(graph.connections(c_in), &[b_out]);
+    }
+}
@@ -0,0 +1,267 @@
+use egui::epaint::CubicBezierShape;
+use egui::*;
+
+use crate::GraphEditorStyle;
+
+#[cfg(feature = "persistence")]
+use serde::{Deserialize, Serialize};
+
+/// The shape of the wires drawn between ports.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
+pub enum WireStyle {
+    /// Curves leaving the ports horizontally. See
+    /// [`GraphEditorStyle::wire_curvature`].
+    #[default]
+    Bezier,
+    /// Straight lines from port to port.
+    Straight,
+    /// Horizontal and vertical lines only.
+    Orthogonal,
+}
+
+/// How far orthogonal wires go straight out of a port before turning.
+const ORTHOGONAL_MARGIN: f32 = 20.0;
+
+/// The control points of the bezier curve drawn for a wire.
+pub(crate) fn bezier_points(curvature: f32, src_pos: Pos2, dst_pos: Pos2) -> [Pos2; 4] {
+    // let control_scale = ((dst_pos.x - src_pos.x) / 2.0).max(30.0);
+    // let src_control = src_pos + Vec2::X * control_scale;
+    // let dst_control = dst_pos - Vec2::X * control_scale;
+
+    let control_scale = curvature;
+    let src_control = Pos2 {
+        x: src_pos.x - (-(f32::abs(src_pos.x - dst_pos.x)) * control_scale),
+        y: src_pos.y,
+    };
+    let dst_control = Pos2 {
+        x: dst_pos.x + (-(f32::abs(src_pos.x - dst_pos.x)) * control_scale),
+        y: dst_pos.y,
+    };
+    [src_pos, src_control, dst_control, dst_pos]
+}
+
+/// The path of a wire from an output at `src_pos` to an input at `dst_pos`,
+/// as a polyline. Bezier wires are flattened.
+pub(crate) fn wire_points(
+    style: &GraphEditorStyle,
+    zoom: f32,
+    src_pos: Pos2,
+    dst_pos: Pos2,
+) -> Vec<Pos2> {
+    match style.wire_style {
+        WireStyle::Bezier => CubicBezierShape::from_points_stroke(
+            bezier_points(style.wire_curvature, src_pos, dst_pos),
+            false,
+            Color32::TRANSPARENT,
+            Stroke::NONE,
+        )
+        .flatten(Some(0.5)),
+        WireStyle::Straight => vec![src_pos, dst_pos],
+        WireStyle::Orthogonal => {
+            let margin = ORTHOGONAL_MARGIN * zoom;
+            if dst_pos.x - src_pos.x >= 2.0 * margin {
+                let mid_x = (src_pos.x + dst_pos.x) / 2.0;
+                vec![
+                    src_pos,
+                    pos2(mid_x, src_pos.y),
+                    pos2(mid_x, dst_pos.y),
+                    dst_pos,
+                ]
+            } else {
+                // The input is behind the output: go around, between both
+                // ports.
+                let mid_y = (src_pos.y + dst_pos.y) / 2.0;
+                vec![
+                    src_pos,
+                    pos2(src_pos.x + margin, src_pos.y),
+                    pos2(src_pos.x + margin, mid_y),
+                    pos2(dst_pos.x - margin, mid_y),
+                    pos2(dst_pos.x - margin, dst_pos.y),
+                    dst_pos,
+                ]
+            }
+        }
+    }
+}
+
+/// Draws a wire from an output at `src_pos` to an input at `dst_pos`.
+pub(crate) fn paint_wire(
+    style: &GraphEditorStyle,
+    zoom: f32,
+    painter: &Painter,
+    src_pos: Pos2,
+    dst_pos: Pos2,
+    stroke: Stroke,
+) {
+    if style.wire_style == WireStyle::Bezier {
+        let bezier = CubicBezierShape::from_points_stroke(
+            bezier_points(style.wire_curvature, src_pos, dst_pos),
+            false,
+            Color32::TRANSPARENT,
+            stroke,
+        );
+        painter.add(bezier);
+    } else {
+        painter.add(Shape::line(
+            wire_points(style, zoom, src_pos, dst_pos),
+            stroke,
+        ));
+    }
+}
+
+/// A rect containing the whole wire from `src_pos` to `dst_pos`, without
+/// computing its path. Used to skip the wires outside of the editor.
+pub(crate) fn wire_bounds(
+    style: &GraphEditorStyle,
+    zoom: f32,
+    src_pos: Pos2,
+    dst_pos: Pos2,
+) -> Rect {
+    let bounds = match style.wire_style {
+        // A bezier curve stays within its control points.
+        WireStyle::Bezier => {
+            Rect::from_points(&bezier_points(style.wire_curvature, src_pos, dst_pos))
+        }
+        WireStyle::Straight => Rect::from_two_pos(src_pos, dst_pos),
+        WireStyle::Orthogonal => {
+            Rect::from_two_pos(src_pos, dst_pos).expand2(Vec2::X * ORTHOGONAL_MARGIN * zoom)
+        }
+    };
+    bounds.expand(style.highlighted_wire_width.max(style.wire_width) * zoom)
+}
+
+/// The distance from `pos` to the polyline `points`.
+pub(crate) fn distance_to_polyline(points: &[Pos2], pos: Pos2) -> f32 {
+    points
+        .windows(2)
+        .map(|segment| {
+            let (a, b) = (segment[0], segment[1]);
+            let ab = b - a;
+            let t = if ab.length_sq() > 0.0 {
+                ((pos - a).dot(ab) / ab.length_sq()).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            pos.distance(a + ab * t)
+        })
+        .fold(f32::INFINITY, f32::min)
+}
+
+/// Splits the polyline `points` into dashes of length `dash` separated by
+/// gaps of length `gap`. The pattern is moved forward along the line by
+/// `offset`, so increasing it over time animates the dashes.
+pub(crate) fn dash_polyline(points: &[Pos2], dash: f32, gap: f32, offset: f32) -> Vec<Vec<Pos2>> {
+    let period = dash + gap;
+    let mut dashes = Vec::new();
+    if period <= 0.0 {
+        return dashes;
+    }
+    let mut current = Vec::new();
+    // The position in the pattern at the start of the line. The first `dash`
+    // units of the pattern are drawn.
+    let mut phase = (period - offset.rem_euclid(period)) % period;
+    for segment in points.windows(2) {
+        let (a, b) = (segment[0], segment[1]);
+        let length = a.distance(b);
+        let mut travelled = 0.0;
+        while travelled < length {
+            let in_dash = phase < dash;
+            let boundary = if in_dash { dash } else { period };
+            let step = (boundary - phase).min(length - travelled);
+            if in_dash && current.is_empty() {
+                current.push(a.lerp(b, travelled / length));
+            }
+            travelled += step;
+            phase += step;
+            if in_dash {
+                current.push(a.lerp(b, travelled / length));
+                if phase >= dash {
+                    dashes.push(std::mem::take(&mut current));
+                }
+            }
+            if phase >= period {
+                phase -= period;
+            }
+        }
+    }
+    if current.len() >= 2 {
+        dashes.push(current);
+    }
+    dashes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dash_polyline() {
+        let line = [pos2(0.0, 0.0), pos2(10.0, 0.0), pos2(10.0, 10.0)];
+        let xs = |dashes: Vec<Vec<Pos2>>| -> Vec<Vec<(f32, f32)>> {
+            dashes
+                .into_iter()
+                .map(|dash| dash.into_iter().map(|p| (p.x, p.y)).collect())
+                .collect()
+        };
+
+        assert_eq!(
+            xs(dash_polyline(&line, 4.0, 4.0, 0.0)),
+            vec![
+                vec![(0.0, 0.0), (4.0, 0.0)],
+                vec![(8.0, 0.0), (10.0, 0.0), (10.0, 2.0)],
+                vec![(10.0, 6.0), (10.0, 10.0)],
+            ]
+        );
+        // Moving the pattern forward by a gap swaps dashes and gaps
+        assert_eq!(
+            xs(dash_polyline(&line, 4.0, 4.0, 4.0)),
+            vec![vec![(4.0, 0.0), (8.0, 0.0)], vec![(10.0, 2.0), (10.0, 6.0)]]
+        );
+        // A whole period brings the pattern back
+        assert_eq!(
+            dash_polyline(&line, 4.0, 4.0, 8.0),
+            dash_polyline(&line, 4.0, 4.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn test_orthogonal_wires() {
+        let style = GraphEditorStyle {
+            wire_style: WireStyle::Orthogonal,
+            ..GraphEditorStyle::dark()
+        };
+        let points = wire_points(&style, 1.0, pos2(0.0, 0.0), pos2(100.0, 50.0));
+        assert_eq!(points.len(), 4);
+        // Backwards wires go around
+        let points = wire_points(&style, 1.0, pos2(100.0, 0.0), pos2(0.0, 50.0));
+        assert_eq!(points.len(), 6);
+        assert!(points
+            .windows(2)
+            .all(|segment| segment[0].x == segment[1].x || segment[0].y == segment[1].y));
+        assert_eq!(distance_to_polyline(&points, pos2(50.0, 30.0)), 5.0);
+    }
+
+    #[test]
+    fn test_wire_bounds() {
+        for wire_style in [
+            WireStyle::Bezier,
+            WireStyle::Straight,
+            WireStyle::Orthogonal,
+        ] {
+            let style = GraphEditorStyle {
+                wire_style,
+                ..GraphEditorStyle::dark()
+            };
+            for (src, dst) in [
+                (pos2(0.0, 0.0), pos2(100.0, 50.0)),
+                (pos2(100.0, 0.0), pos2(0.0, 50.0)),
+            ] {
+                let bounds = wire_bounds(&style, 1.0, src, dst);
+                assert!(wire_points(&style, 1.0, src, dst)
+                    .iter()
+                    .all(|point| bounds.contains(*point)));
+            }
+        }
+    }
+}
@@ -6,10 +6,6 @@ use egui_node_graph::*;
 use egui_file::FileDialog;
 use std::path::PathBuf;
 
-use std::io::{ Read, Write };
-use std::fs::{ File, OpenOptions };
-use bincode;
-
 use serde::{ Deserialize, Serialize };
 
 use slotmap::SlotMap;
@@ -20,6 +16,7 @@ use crate::variables;
 use crate::compiler;
 use crate::nodes::{ self, MyNodeTemplate };
 use crate::types;
+use crate::project::ProjectFormat;
 
 pub const DISABLE_RECURSIVE_FUNCTIONS: bool = true;
 
@@ -48,6 +45,7 @@ pub struct MyGraphState {
 pub enum SaveOrLoad {
     Save,
     Load,
+    Export,
 }
 
 impl Default for SaveOrLoad {
@@ -90,39 +88,13 @@ impl NodeGraphExample {
     }
 }
 
-impl AppState {
-    // Save the struct to the specified location using bincode
-    pub fn save_to_file(&self, file_path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
-        let mut file = OpenOptions::new().write(true).create(true).truncate(true).open(file_path)?;
-
-        let encoded = bincode::serialize(self)?;
-        file.write_all(&encoded)?;
-
-        Ok(())
-    }
-
-    // Load the struct from the specified location using bincode
-    pub fn load_from_file(file_path: &PathBuf) -> Result<Self, Box<dyn std::error::Error>> {
-        let mut file = File::open(file_path)?;
-        let mut buffer = Vec::new();
-        file.read_to_end(&mut buffer)?;
-
-        let decoded: Self = bincode::deserialize(&buffer)?;
-
-        Ok(decoded)
-    }
-
-    pub fn load(&mut self, file_path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
-        *self = AppState::load_from_file(file_path)?;
-        Ok(())
-    }
-}
-
 pub struct App {
     pub save_load_actions: Option<PathBuf>,
     pub open_file_dialog: Option<(FileDialog, SaveOrLoad)>,
     pub new_function_window: Option<CreateFunctionDialog>,
     pub app_state: AppState,
+    /// The last error that occurred while saving or loading a project.
+    pub file_error: Option<String>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -205,6 +177,7 @@ impl Default for App {
             save_load_actions: None,
             open_file_dialog: None,
             new_function_window: None,
+            file_error: None,
             app_state: AppState {
                 main_graph_id: current_function,
                 current_function,
@@ -257,6 +230,68 @@ impl eframe::App for App {
                     dialog.open();
                     self.open_file_dialog = Some((dialog, SaveOrLoad::Save));
                 }
+                let export = ui
+                    .button("Export compact")
+                    .on_hover_text(
+                        "Compact exports can only be opened by this version of the app. Use Save to keep a project."
+                    );
+                if export.clicked() {
+                    let mut dialog = FileDialog::save_file(None);
+                    dialog.open();
+                    self.open_file_dialog = Some((dialog, SaveOrLoad::Export));
+                }
+                ui.checkbox(&mut self.app_state.graph.state.minimap.visible, "Minimap");
+                ui.menu_button("Layout", |ui| {
+                    let graph = &mut self.app_state.graph;
+                    let layered = LayoutSettings::default();
+                    let spine = LayoutSettings {
+                        style: LayoutStyle::ExecutionSpine,
+                        ..Default::default()
+                    };
+                    if ui.button("Layout graph").clicked() {
+                        graph.state.auto_layout_all(&layered, &mut graph.user_state);
+                        ui.close_menu();
+                    }
+                    if ui.button("Layout selection").clicked() {
+                        graph.state.auto_layout_selection(&layered, &mut graph.user_state);
+                        ui.close_menu();
+                    }
+                    if ui.button("Layout execution flow").clicked() {
+                        graph.state.auto_layout_all(&spine, &mut graph.user_state);
+                        ui.close_menu();
+                    }
+                });
+                ui.menu_button("Theme", |ui| {
+                    let style = &mut self.app_state.graph.state.style;
+                    if ui.radio(style.is_none(), "Follow egui").clicked() {
+                        *style = None;
+                        ui.close_menu();
+                    }
+                    let dark = GraphEditorStyle::dark();
+                    if ui.radio(*style == Some(dark), "Dark").clicked() {
+                        *style = Some(dark);
+                        ui.close_menu();
+                    }
+                    let light = GraphEditorStyle::light();
+                    if ui.radio(*style == Some(light), "Light").clicked() {
+                        *style = Some(light);
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    let current = style.unwrap_or_else(||
+                        GraphEditorStyle::from_visuals(ui.visuals())
+                    );
+                    for (wire_style, label) in [
+                        (WireStyle::Bezier, "Curved wires"),
+                        (WireStyle::Straight, "Straight wires"),
+                        (WireStyle::Orthogonal, "Orthogonal wires"),
+                    ] {
+                        if ui.radio(current.wire_style == wire_style, label).clicked() {
+                            *style = Some(GraphEditorStyle { wire_style, ..current });
+                            ui.close_menu();
+                        }
+                    }
+                });
                 if ui.button("Compile").clicked() {
                     self.app_state.graph = std::mem::replace(
                         &mut self.app_state.functions[self.app_state.current_function].graph,
@@ -292,20 +327,43 @@ impl eframe::App for App {
             if let Some(dialog) = &mut self.open_file_dialog {
                 if dialog.0.show(ctx).selected() {
                     if let Some(file) = dialog.0.path() {
-                        self.save_load_actions = Some(file.to_path_buf());
-                        match dialog.1 {
-                            SaveOrLoad::Load => {
-                                self.app_state.load(&file.to_path_buf());
-                                println!("Load : {:?}", file.to_path_buf());
-                            }
-                            SaveOrLoad::Save => {
-                                self.app_state.save_to_file(&file.to_path_buf());
-                                println!("Save : {:?}", file.to_path_buf());
+                        if dialog.1 != SaveOrLoad::Export {
+                            self.save_load_actions = Some(file.to_path_buf());
+                        }
+                        let result = match dialog.1 {
+                            SaveOrLoad::Load =>
+                                AppState::load_from_file(file).map(|state| {
+                                    self.app_state = state;
+                                }),
+                            SaveOrLoad::Save =>
+                                self.app_state.save_to_file(file, ProjectFormat::Json),
+                            SaveOrLoad::Export =>
+                                self.app_state.save_to_file(file, ProjectFormat::Compact),
+                        };
+                        match result {
+                            Ok(()) => println!("{:?} : {:?}", dialog.1, file),
+                            Err(err) => {
+                                self.file_error = Some(
+                                    format!("{:?} {} failed.\n{}", dialog.1, file.display(), err)
+                                );
                             }
                         }
                     }
                 }
             }
+            if let Some(error) = &self.file_error {
+                let mut open = true;
+                egui::Window
+                    ::new("Error")
+                    .collapsible(false)
+                    .open(&mut open)
+                    .show(ctx, |ui| {
+                        ui.label(error);
+                    });
+                if !open {
+                    self.file_error = None;
+                }
+            }
             if let Some(create_function) = &mut self.new_function_window {
                 if
                     !functions::show_function_window(
@@ -352,52 +410,41 @@ impl NodeGraphExample {
             // some use for them. For example, by playing a sound when a new
             // connection is created
             if let NodeResponse::User(user_event) = node_response {
+                // Rebuilding a node is a compound operation, recorded as a
+                // single undo step.
+                let user_state = &mut self.user_state;
                 match user_event {
                     MyResponse::AsignFunction(node, function) => {
-                        self.state.graph.nodes[node].user_data.template =
-                            nodes::MyNodeTemplate::Function(function);
-                        let _ = self.state.graph.rename_node(
-                            node,
-                            format!(
-                                "Function {}",
-                                function.map_or("", |x| { &self.user_state.functions[x].name })
-                            )
-                        );
-                        self.state.graph.remove_all_nodes_connections(node);
-                        self.state.graph.nodes[node].inputs.clear();
-                        self.state.graph.nodes[node].outputs.clear();
-                        let template = self.state.graph.nodes[node].user_data.template;
-                        template.build_node(&mut self.state.graph, &mut self.user_state, node);
+                        self.state.undoable("Assign function", |state| {
+                            state.graph.nodes[node].user_data.template =
+                                nodes::MyNodeTemplate::Function(function);
+                            let _ = state.graph.rename_node(
+                                node,
+                                format!(
+                                    "Function {}",
+                                    function.map_or("", |x| { &user_state.functions[x].name })
+                                )
+                            );
+                            state.graph.remove_all_nodes_connections(node);
+                            state.graph.nodes[node].inputs.clear();
+                            state.graph.nodes[node].outputs.clear();
+                            let template = state.graph.nodes[node].user_data.template;
+                            template.build_node(&mut state.graph, user_state, node);
+                        });
                     }
                     MyResponse::NodeShapeShiftFromCategory(node, template) => {
-                        self.state.graph.nodes[node].user_data.template = template;
-                        let _ = self.state.graph.rename_node(
-                            node,
-                            template.node_graph_label(&mut self.user_state)
-                        );
-                        self.state.graph.remove_all_nodes_connections(node);
-                        self.state.graph.nodes[node].inputs.clear();
-                        self.state.graph.nodes[node].outputs.clear();
-                        let template = self.state.graph.nodes[node].user_data.template;
-                        template.build_node(&mut self.state.graph, &mut self.user_state, node);
-                    }
-                }
-            }
-            if
-                let NodeResponse::ConnectEventEnded {
-                    output,
-                    input,
-                    node_input: _,
-                    node_output: _,
-                } = node_response
-            {
-                // Check if the output can be send to differents inputs
-                if let Some(out) = self.state.graph.outputs.get(output) {
-                    if out.typ == types::MyDataType::Execution {
-                        // Remove all the others connections with the same input.
-                        self.state.graph.connections.retain(
-                            |inp, out| !(inp != input && *out == output)
-                        );
+                        self.state.undoable("Change node type", |state| {
+                            state.graph.nodes[node].user_data.template = template;
+                            let _ = state.graph.rename_node(
+                                node,
+                                template.node_graph_label(user_state)
+                            );
+                            state.graph.remove_all_nodes_connections(node);
+                            state.graph.nodes[node].inputs.clear();
+                            state.graph.nodes[node].outputs.clear();
+                            let template = state.graph.nodes[node].user_data.template;
+                            template.build_node(&mut state.graph, user_state, node);
+                        });
                     }
                 }
             }
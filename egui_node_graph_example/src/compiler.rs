@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 
-use egui_node_graph::{ NodeId, OutputId, InputId, Node };
+use egui_node_graph::{ EguiGraphError, OutputId, InputId, Node };
 use slotmap::Key;
 
 use crate::app::{ self, MyGraph };
@@ -50,17 +50,21 @@ pub fn compile(
         });
     }
 
-    match evaluate_function(graph, enter_node, &mut HashMap::new()) {
-        Ok(prog) => Ok(format!("{} {:?}", result, prog)),
-        Err(err) => Err(format!("Error Of Compilation : \n{:?}", err)),
+    // The evaluation follows the connections recursively, so it must not
+    // meet a cycle.
+    if let Err(EguiGraphError::Cycle(output, input)) = graph.topological_order() {
+        return Err(format!("Error Of Compilation : \n{:?}", (output, input)));
     }
+
+    let prog = evaluate_function(graph, enter_node, &mut HashMap::new());
+    Ok(format!("{} {:?}", result, prog))
 }
 
 fn evaluate_function(
     graph: &MyGraph,
     next_node: &Node<nodes::MyNodeData>,
     outputs_cache: &mut HashMap<OutputId, String>
-) -> Result<String, (OutputId, InputId)> {
+) -> String {
     let mut inputs = vec![];
 
     for y in next_node.inputs(graph) {
@@ -75,15 +79,10 @@ fn evaluate_function(
                 .map_or(None, |x| Some(x.to_string()));
             if input.is_none() {
                 input = Some(
-                    evaluate_output(
-                        &graph,
-                        &graph[graph.get_output(z).node],
-                        outputs_cache,
-                        &mut vec![]
-                    )?
+                    evaluate_output(&graph, &graph[graph.get_output(z).node], outputs_cache)
                 );
             }
-            inputs.push(input);
+            inputs.push(input.map(|x| convert(graph, z, y.id, x)));
         } else {
             inputs.push(
                 Some(match &y.value {
@@ -117,7 +116,7 @@ fn evaluate_function(
             if x.1 == *y {
                 executions.push(
                     if let Some(z) = graph.nodes.get(graph[x.0].node) {
-                        evaluate_function(graph, z, outputs_cache)?
+                        evaluate_function(graph, z, outputs_cache)
                     } else {
                         String::new()
                     }
@@ -133,39 +132,25 @@ fn evaluate_function(
         next_node
     );
 
-    Ok(
-        format!(
-            "{} {}",
-            script_line,
-            executions.get(0).map_or("", |x| x)
-        )
+    format!(
+        "{} {}",
+        script_line,
+        executions.get(0).map_or("", |x| x)
     )
 }
 
 fn evaluate_output(
     graph: &MyGraph,
     output_node: &Node<nodes::MyNodeData>,
-    outputs_cache: &mut HashMap<OutputId, String>,
-    already_explored_nodes: &mut Vec<NodeId>
-) -> Result<String, (OutputId, InputId)> {
-    already_explored_nodes.push(output_node.id);
-
+    outputs_cache: &mut HashMap<OutputId, String>
+) -> String {
     let mut inputs = vec![];
 
     for x in output_node.input_ids() {
         if let Some(y) = graph.connection(x) {
-            if already_explored_nodes.contains(&graph[graph[y].node].id) {
-                return Err((y, x));
-            } else {
-                let output = evaluate_output(
-                    graph,
-                    &graph[graph[y].node],
-                    outputs_cache,
-                    already_explored_nodes
-                )?;
-                outputs_cache.insert(y, output.clone()); // technically, its not nessesary to put it in the cache, but if we dont want to recalculate it agin, thats preferable
-                inputs.push(output);
-            }
+            let output = evaluate_output(graph, &graph[graph[y].node], outputs_cache);
+            outputs_cache.insert(y, output.clone()); // technically, its not nessesary to put it in the cache, but if we dont want to recalculate it agin, thats preferable
+            inputs.push(convert(graph, y, x, output));
         } else {
             inputs.push(match &graph.get_input(x).value {
                 types::MyValueType::String { value } => format!("\"{value}\""),
@@ -176,7 +161,17 @@ fn evaluate_output(
             });
         }
     }
-    return Ok(
-        output_node.user_data.template.evaluate_data(graph, output_node, outputs_cache, &inputs)
-    );
+    return output_node.user_data.template.evaluate_data(graph, output_node, outputs_cache, &inputs);
+}
+
+/// Inserts the casts for the connections that `MyDataType::connection_compatibility`
+/// accepts with a conversion.
+fn convert(graph: &MyGraph, output: OutputId, input: InputId, value: String) -> String {
+    match (&graph[output].typ, &graph[input].typ) {
+        (from, types::MyDataType::String) if *from != types::MyDataType::String => {
+            format!("(tostring {})", value)
+        }
+        // Lua numbers are floats and integers at the same time.
+        _ => value,
+    }
 }
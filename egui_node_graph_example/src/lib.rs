@@ -9,4 +9,5 @@ mod utils;
 mod compiler;
 mod nodes;
 mod types;
+mod project;
 pub use app::App;
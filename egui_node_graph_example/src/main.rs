@@ -14,6 +14,7 @@ mod utils;
 mod compiler;
 mod nodes;
 mod types;
+mod project;
 
 
 
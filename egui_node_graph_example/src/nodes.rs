@@ -1,5 +1,6 @@
 use std::borrow::Cow;
 use std::collections::HashMap;
+use std::num::NonZeroUsize;
 
 use eframe::egui;
 use egui_node_graph::*;
@@ -14,7 +15,7 @@ use crate::app::{ self, MyGraph };
 /// The NodeData holds a custom data struct inside each node. It's useful to
 /// store additional information that doesn't live in parameters. For this
 /// example, the node data stores the template (i.e. the "type") of the node.
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 pub struct MyNodeData {
     pub template: MyNodeTemplate,
 }
@@ -183,6 +184,33 @@ impl NodeTemplateTrait for MyNodeTemplate {
         }
     }
 
+    fn node_finder_description(&self, _user_state: &mut Self::UserState) -> Cow<'_, str> {
+        Cow::Borrowed(match self {
+            MyNodeTemplate::Enter => "Where the program starts",
+            MyNodeTemplate::Print => "Writes a value to the console",
+            MyNodeTemplate::Ask => "Reads a line typed in the console",
+            MyNodeTemplate::If => "Runs the branch only when the condition is true",
+            MyNodeTemplate::CategoryAdd => "Adds two numbers or joins two strings",
+            MyNodeTemplate::AddNumber => "Adds two numbers",
+            MyNodeTemplate::AddString => "Joins two strings",
+            MyNodeTemplate::Function(_) => "Calls a function of the project",
+        })
+    }
+
+    fn node_finder_keywords(&self, _user_state: &mut Self::UserState) -> Vec<String> {
+        let keywords: &[&str] = match self {
+            MyNodeTemplate::Enter => &["start", "begin", "main"],
+            MyNodeTemplate::Print => &["log", "output", "console"],
+            MyNodeTemplate::Ask => &["input", "read"],
+            MyNodeTemplate::If => &["branch", "condition"],
+            MyNodeTemplate::CategoryAdd => &["sum", "plus"],
+            MyNodeTemplate::AddNumber => &["sum", "plus"],
+            MyNodeTemplate::AddString => &["concat", "join"],
+            MyNodeTemplate::Function(_) => &["call"],
+        };
+        keywords.iter().map(|keyword| keyword.to_string()).collect()
+    }
+
     fn node_graph_label(&self, user_state: &mut Self::UserState) -> String {
         // It's okay to delegate this to node_finder_label if you don't want to
         // show different names in the node finder and the node itself.
@@ -223,18 +251,26 @@ impl NodeTemplateTrait for MyNodeTemplate {
             graph.add_output_param(node_id, name.to_string(), typ);
         };
 
+        // Several execution paths can lead to the same node, but an execution
+        // output can only continue to a single node.
         let exe_input = |graph: &mut app::MyGraph, name: &str| {
-            graph.add_input_param(
+            graph.add_wide_input_param(
                 node_id,
                 name.to_string(),
                 types::MyDataType::Execution,
                 types::MyValueType::Execution,
                 InputParamKind::ConnectionOnly,
+                None,
                 true
             );
         };
         let exe_output = |graph: &mut app::MyGraph, name: &str| {
-            graph.add_output_param(node_id, name.to_string(), types::MyDataType::Execution);
+            let output = graph.add_output_param(
+                node_id,
+                name.to_string(),
+                types::MyDataType::Execution
+            );
+            graph.outputs[output].max_connections = NonZeroUsize::new(1);
         };
 
         match self.get_node_params().node_type {
@@ -0,0 +1,390 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::app::AppState;
+
+mod v0;
+
+/// The version of the project format written by this build. Increase it
+/// whenever a change to the saved types breaks older files, and add a step to
+/// `MIGRATIONS` converting the previous version.
+pub const PROJECT_VERSION: u64 = 2;
+
+/// Header of the compact binary export, followed by the format version and
+/// the bincode encoded `AppState`. Bincode does not describe the layout of the
+/// data, so compact exports cannot be migrated: they are a way to share a
+/// project with the same build, not to archive it.
+const COMPACT_MAGIC: &[u8; 8] = b"VSRSBIN\0";
+
+/// Converts a JSON project to the next version, or returns why it could not.
+type Migration = fn(&mut Value) -> Result<(), String>;
+
+/// Each migration converts a JSON project from version `index + 1` to
+/// version `index + 2`. They are run in order until the project reaches
+/// `PROJECT_VERSION`.
+const MIGRATIONS: &[Migration] = &[fan_in_connections];
+
+/// Version 2 lets an input have several connections: each slot of the graph
+/// `connections` maps holds a list of outputs instead of a single one. The
+/// ports also gained a connection limit, and the execution ports are built
+/// with unlimited inputs and single outputs, which older files would not get
+/// from the defaults. The project contains one graph per function, so they
+/// are searched for.
+fn fan_in_connections(project: &mut Value) -> Result<(), String> {
+    match project {
+        Value::Object(fields) => {
+            for (name, field) in fields.iter_mut() {
+                match field {
+                    Value::Array(slots) if name == "connections" => {
+                        for slot in slots {
+                            if let Some(value) = slot.get_mut("value") {
+                                if value.get("idx").is_some() {
+                                    *value = Value::Array(vec![value.take()]);
+                                }
+                            }
+                        }
+                    }
+                    Value::Array(slots) if name == "inputs" || name == "outputs" => {
+                        let max_connections = if name == "inputs" {
+                            Value::Null
+                        } else {
+                            Value::from(1)
+                        };
+                        for slot in slots {
+                            match slot.get_mut("value") {
+                                Some(Value::Object(param))
+                                    if param.get("typ")
+                                        == Some(&Value::from("Execution")) =>
+                                {
+                                    param.insert(
+                                        "max_connections".into(),
+                                        max_connections.clone(),
+                                    );
+                                }
+                                _ => fan_in_connections(slot)?,
+                            }
+                        }
+                    }
+                    _ => fan_in_connections(field)?,
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                fan_in_connections(item)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// The layout of JSON project files. The version comes first, so it can be
+/// read before the rest of the file is migrated.
+#[derive(Serialize)]
+struct ProjectFile<'a> {
+    version: u64,
+    project: &'a AppState,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProjectFormat {
+    /// Human-readable JSON, with an explicit schema version. Older versions
+    /// are migrated when loading.
+    Json,
+    /// Compact bincode export. It can only be loaded by a build using the
+    /// same format version, and is not an archival format: projects meant to
+    /// be kept should be saved as JSON.
+    Compact,
+}
+
+#[derive(Debug)]
+pub enum ProjectError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    Bincode(bincode::Error),
+    /// The JSON file has no `version` field.
+    MissingVersion,
+    /// The file is neither JSON nor a compact export, and cannot be read as
+    /// the raw bincode saved by the builds before the versioned format.
+    LegacyBinary(bincode::Error),
+    /// The file was written by a newer build, or by a version with no
+    /// migration path.
+    UnsupportedVersion(u64),
+    /// A compact export written for another format version. Compact exports
+    /// are not migrated.
+    CompactVersion(u64),
+    /// A migration step failed.
+    Migration {
+        from: u64,
+        reason: String,
+    },
+}
+
+impl fmt::Display for ProjectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProjectError::Io(err) => write!(f, "Cannot access the file: {}", err),
+            ProjectError::Json(err) => write!(f, "Invalid project file: {}", err),
+            ProjectError::Bincode(err) => write!(f, "Invalid compact project: {}", err),
+            ProjectError::MissingVersion => write!(f, "The project file has no version"),
+            ProjectError::LegacyBinary(err) => write!(f, "Invalid binary project: {}", err),
+            ProjectError::UnsupportedVersion(version) =>
+                write!(
+                    f,
+                    "Unsupported project version {} (this build reads versions up to {})",
+                    version,
+                    PROJECT_VERSION
+                ),
+            ProjectError::CompactVersion(version) =>
+                write!(
+                    f,
+                    "This compact export uses format version {} and this build only reads version {}. Compact exports are not archival: save projects as JSON to keep them.",
+                    version,
+                    PROJECT_VERSION
+                ),
+            ProjectError::Migration { from, reason } =>
+                write!(f, "Cannot upgrade the project from version {}: {}", from, reason),
+        }
+    }
+}
+
+impl std::error::Error for ProjectError {}
+
+impl From<std::io::Error> for ProjectError {
+    fn from(err: std::io::Error) -> Self {
+        ProjectError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for ProjectError {
+    fn from(err: serde_json::Error) -> Self {
+        ProjectError::Json(err)
+    }
+}
+
+impl From<bincode::Error> for ProjectError {
+    fn from(err: bincode::Error) -> Self {
+        ProjectError::Bincode(err)
+    }
+}
+
+impl AppState {
+    /// Encodes the project in the given format.
+    pub fn to_bytes(&self, format: ProjectFormat) -> Result<Vec<u8>, ProjectError> {
+        match format {
+            ProjectFormat::Json => {
+                let project = ProjectFile { version: PROJECT_VERSION, project: self };
+                Ok(serde_json::to_vec_pretty(&project)?)
+            }
+            ProjectFormat::Compact => {
+                let mut bytes = COMPACT_MAGIC.to_vec();
+                bytes.extend_from_slice(&PROJECT_VERSION.to_le_bytes());
+                bytes.extend(bincode::serialize(self)?);
+                Ok(bytes)
+            }
+        }
+    }
+
+    /// Decodes a project written by `AppState::to_bytes`, in either format.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ProjectError> {
+        if let Some(rest) = bytes.strip_prefix(COMPACT_MAGIC) {
+            let (version, data) = rest.split_at(rest.len().min(8));
+            let version = u64::from_le_bytes(
+                version.try_into().map_err(|_| ProjectError::MissingVersion)?
+            );
+            if version != PROJECT_VERSION {
+                return Err(ProjectError::CompactVersion(version));
+            }
+            return Ok(bincode::deserialize(data)?);
+        }
+
+        // Builds before the versioned format saved raw bincode, which is
+        // converted to the first JSON version.
+        let json_start = bytes.iter().find(|byte| !byte.is_ascii_whitespace());
+        let mut project: Value = if json_start == Some(&b'{') {
+            serde_json::from_slice(bytes)?
+        } else {
+            v0::to_v1(bytes)?
+        };
+        let version = project
+            .get("version")
+            .and_then(Value::as_u64)
+            .ok_or(ProjectError::MissingVersion)?;
+        if version == 0 || version > PROJECT_VERSION {
+            return Err(ProjectError::UnsupportedVersion(version));
+        }
+        for from in version..PROJECT_VERSION {
+            let migration = MIGRATIONS.get((from - 1) as usize).ok_or(
+                ProjectError::UnsupportedVersion(version)
+            )?;
+            migration(&mut project).map_err(|reason| ProjectError::Migration { from, reason })?;
+        }
+        let state = project.get_mut("project").map(Value::take).unwrap_or(Value::Null);
+        Ok(serde_json::from_value(state)?)
+    }
+
+    pub fn save_to_file(&self, file_path: &Path, format: ProjectFormat) -> Result<(), ProjectError> {
+        fs::write(file_path, self.to_bytes(format)?)?;
+        Ok(())
+    }
+
+    pub fn load_from_file(file_path: &Path) -> Result<Self, ProjectError> {
+        Self::from_bytes(&fs::read(file_path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroUsize;
+
+    use egui_node_graph::*;
+
+    use super::*;
+    use crate::app::App;
+    use crate::nodes::MyNodeTemplate;
+    use crate::types::{ MyDataType, MyValueType };
+
+    /// A project with an `Enter` node followed by two `Print` nodes.
+    fn sample_project() -> AppState {
+        let mut app_state = App::default().app_state;
+        let graph = &mut app_state.graph;
+        let mut add_node = |template: MyNodeTemplate| {
+            let user_state = &mut graph.user_state;
+            let node = graph.state.graph.add_node(
+                template.node_graph_label(user_state),
+                template.user_data(user_state),
+                |graph, node| template.build_node(graph, user_state, node)
+            );
+            graph.state.node_positions.insert(node, eframe::egui::Pos2::ZERO);
+            graph.state.node_order.push(node);
+            node
+        };
+        let enter = add_node(MyNodeTemplate::Enter);
+        let first = add_node(MyNodeTemplate::Print);
+        let second = add_node(MyNodeTemplate::Print);
+        let graph = &mut graph.state.graph;
+        graph.add_connection(graph[enter].outputs[0].1, graph[first].inputs[0].1);
+        graph.add_connection(graph[first].outputs[0].1, graph[second].inputs[0].1);
+        app_state
+    }
+
+    fn to_value(project: &AppState) -> Value {
+        serde_json::to_value(project).unwrap()
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let project = sample_project();
+        let bytes = project.to_bytes(ProjectFormat::Json).unwrap();
+        let file: Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(file["version"], PROJECT_VERSION);
+        let loaded = AppState::from_bytes(&bytes).unwrap();
+        assert_eq!(to_value(&loaded), to_value(&project));
+    }
+
+    #[test]
+    fn test_compact_round_trip() {
+        let project = sample_project();
+        let mut bytes = project.to_bytes(ProjectFormat::Compact).unwrap();
+        assert_eq!(&bytes[..8], COMPACT_MAGIC);
+        assert_eq!(&bytes[8..16], &PROJECT_VERSION.to_le_bytes());
+        let loaded = AppState::from_bytes(&bytes).unwrap();
+        assert_eq!(to_value(&loaded), to_value(&project));
+
+        // Compact exports are not migrated
+        bytes[8..16].copy_from_slice(&(PROJECT_VERSION - 1).to_le_bytes());
+        assert!(
+            matches!(
+                AppState::from_bytes(&bytes),
+                Err(ProjectError::CompactVersion(version)) if version == PROJECT_VERSION - 1
+            )
+        );
+    }
+
+    #[test]
+    fn test_unsupported_files() {
+        let project = sample_project();
+        assert!(
+            matches!(AppState::from_bytes(b"\x01\x02garbage"), Err(ProjectError::LegacyBinary(_)))
+        );
+
+        let mut file: Value = serde_json
+            ::from_slice(&project.to_bytes(ProjectFormat::Json).unwrap())
+            .unwrap();
+        file["version"] = (PROJECT_VERSION + 1).into();
+        let bytes = serde_json::to_vec(&file).unwrap();
+        assert!(
+            matches!(
+                AppState::from_bytes(&bytes),
+                Err(ProjectError::UnsupportedVersion(version)) if version == PROJECT_VERSION + 1
+            )
+        );
+        file.as_object_mut().unwrap().remove("version");
+        let bytes = serde_json::to_vec(&file).unwrap();
+        assert!(matches!(AppState::from_bytes(&bytes), Err(ProjectError::MissingVersion)));
+    }
+
+    /// A project saved by a version 1 build, before inputs could have
+    /// several connections.
+    const PROJECT_V1: &str = include_str!("../fixtures/project_v1.json");
+
+    /// A project saved as raw bincode before the versioned format, with the
+    /// same graph as `PROJECT_V1`.
+    const PROJECT_V0: &[u8] = include_bytes!("../fixtures/project_v0.bin");
+
+    /// Checks the `Enter` node followed by two `Print` nodes of the fixtures.
+    fn check_migrated_graph(project: &AppState) {
+        let graph = &project.graph.state.graph;
+        assert_eq!(graph.nodes.len(), 3);
+        let chain: Vec<_> = graph
+            .iter_connections()
+            .map(|(input, output)| (graph[output].node, graph[input].node))
+            .collect();
+        assert_eq!(chain.len(), 2);
+        for (output_node, input_node) in chain {
+            assert_eq!(graph[input_node].label, "Print");
+            assert_ne!(output_node, input_node);
+        }
+
+        // The execution ports get the limits of the new nodes
+        for input in graph.inputs.values() {
+            let expected = match input.typ {
+                MyDataType::Execution => None,
+                _ => NonZeroUsize::new(1),
+            };
+            assert_eq!(input.max_connections, expected);
+        }
+        for output in graph.outputs.values() {
+            if output.typ == MyDataType::Execution {
+                assert_eq!(output.max_connections, NonZeroUsize::new(1));
+            }
+        }
+    }
+
+    #[test]
+    fn test_migration() {
+        check_migrated_graph(&AppState::from_bytes(PROJECT_V1.as_bytes()).unwrap());
+    }
+
+    #[test]
+    fn test_legacy_binary() {
+        let project = AppState::from_bytes(PROJECT_V0).unwrap();
+        check_migrated_graph(&project);
+        let state = &project.graph.state;
+        assert_eq!(state.pan_zoom.pan, eframe::egui::vec2(10.0, 20.0));
+        let texts: Vec<_> = state.graph.inputs
+            .values()
+            .filter_map(|input| match &input.value {
+                MyValueType::String { value } => Some(value.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(texts, ["Hello", ""]);
+        assert_eq!(project.functions.len(), 1);
+    }
+}
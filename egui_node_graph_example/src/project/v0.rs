@@ -0,0 +1,195 @@
+//! The raw bincode files saved before the versioned format. Bincode does not
+//! describe the layout of the data, so these types are a frozen copy of the
+//! saved types of that time. They must never change.
+
+use eframe::egui::{ Pos2, Vec2 };
+use egui_node_graph::{ InputId, NodeId, OutputId };
+use serde::{ Deserialize, Serialize };
+use serde_json::Value;
+use slotmap::{ SecondaryMap, SlotMap };
+
+use super::ProjectError;
+use crate::functions::FunctionId;
+
+#[derive(Serialize, Deserialize)]
+struct AppState {
+    current_function: FunctionId,
+    functions: SlotMap<FunctionId, GraphFunction>,
+    graph: NodeGraphExample,
+    main_graph_id: FunctionId,
+}
+
+#[derive(Serialize, Deserialize)]
+struct GraphFunction {
+    graph: NodeGraphExample,
+    name: String,
+    removable: bool,
+    modifiable_name: bool,
+    variables_list: Vec<Variable>,
+    input: Vec<FunctionIO>,
+    output: Vec<FunctionIO>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct FunctionIO {
+    name: String,
+    value: VariableValue,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Variable {
+    name: String,
+    value: VariableValue,
+    removable: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+enum VariableValue {
+    String(String),
+    Integer(f64),
+    Float(f64),
+    Boolean(bool),
+    Execution,
+}
+
+#[derive(Serialize, Deserialize)]
+struct NodeGraphExample {
+    state: GraphEditorState,
+    user_state: MyGraphState,
+}
+
+#[derive(Serialize, Deserialize)]
+struct MyGraphState {
+    active_node: Option<NodeId>,
+    functions: SlotMap<FunctionId, GraphFunction>,
+    graph_id: FunctionId,
+    main_graph_id: FunctionId,
+}
+
+#[derive(Serialize, Deserialize)]
+struct GraphEditorState {
+    graph: Graph,
+    node_order: Vec<NodeId>,
+    connection_in_progress: Option<(NodeId, AnyParameterId)>,
+    selected_nodes: Vec<NodeId>,
+    ongoing_box_selection: Option<Pos2>,
+    node_positions: SecondaryMap<NodeId, Pos2>,
+    /// An open node finder is not restored.
+    #[serde(skip_serializing)]
+    node_finder: Option<NodeFinder>,
+    pan_zoom: PanZoom,
+    _user_state: (),
+}
+
+#[derive(Serialize, Deserialize)]
+enum AnyParameterId {
+    Input(InputId),
+    Output(OutputId),
+}
+
+#[derive(Serialize, Deserialize)]
+struct NodeFinder {
+    query: String,
+    position: Option<Pos2>,
+    just_spawned: bool,
+    _phantom: (),
+}
+
+#[derive(Serialize, Deserialize)]
+struct PanZoom {
+    pan: Vec2,
+    zoom: f32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Graph {
+    nodes: SlotMap<NodeId, Node>,
+    inputs: SlotMap<InputId, InputParam>,
+    outputs: SlotMap<OutputId, OutputParam>,
+    connections: SecondaryMap<InputId, OutputId>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Node {
+    id: NodeId,
+    label: String,
+    inputs: Vec<(String, InputId)>,
+    outputs: Vec<(String, OutputId)>,
+    user_data: MyNodeData,
+}
+
+#[derive(Serialize, Deserialize)]
+struct MyNodeData {
+    template: MyNodeTemplate,
+}
+
+#[derive(Serialize, Deserialize)]
+enum MyNodeTemplate {
+    Enter,
+    Print,
+    Ask,
+    If,
+    CategoryAdd,
+    AddNumber,
+    AddString,
+    Function(Option<FunctionId>),
+}
+
+#[derive(Serialize, Deserialize)]
+struct InputParam {
+    id: InputId,
+    typ: MyDataType,
+    value: MyValueType,
+    kind: InputParamKind,
+    node: NodeId,
+    shown_inline: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+enum InputParamKind {
+    ConnectionOnly,
+    ConstantOnly,
+    ConnectionOrConstant,
+}
+
+#[derive(Serialize, Deserialize)]
+struct OutputParam {
+    id: OutputId,
+    node: NodeId,
+    typ: MyDataType,
+}
+
+#[derive(Serialize, Deserialize)]
+enum MyDataType {
+    String,
+    Integer,
+    Float,
+    Boolean,
+    Execution,
+}
+
+#[derive(Serialize, Deserialize)]
+enum MyValueType {
+    String {
+        value: String,
+    },
+    Integer {
+        value: i32,
+    },
+    Float {
+        value: f64,
+    },
+    Boolean {
+        value: bool,
+    },
+    Execution,
+}
+
+/// Decodes a file saved before the versioned format, and converts it to a
+/// version 1 JSON project. Version 1 only added editor state (groups,
+/// comments, knots, the minimap and the style), which is left to its
+/// defaults.
+pub fn to_v1(bytes: &[u8]) -> Result<Value, ProjectError> {
+    let project: AppState = bincode::deserialize(bytes).map_err(ProjectError::LegacyBinary)?;
+    Ok(serde_json::json!({ "version": 1, "project": serde_json::to_value(project)? }))
+}
@@ -16,7 +16,7 @@ use crate::nodes;
 /// `DataType`s are what defines the possible range of connections when
 /// attaching two ports together. The graph UI will make sure to not allow
 /// attaching incompatible datatypes.
-#[derive(PartialEq, Eq, Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, serde::Serialize, serde::Deserialize)]
 pub enum MyDataType {
     String,
     Integer,
@@ -32,7 +32,7 @@ pub enum MyDataType {
 /// this library makes no attempt to check this consistency. For instance, it is
 /// up to the user code in this example to make sure no parameter is created
 /// with a DataType of Scalar and a ValueType of Vec2.
-#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
 pub enum MyValueType {
     String {
         value: String,
@@ -103,6 +103,26 @@ impl DataTypeTrait<app::MyGraphState> for MyDataType {
         }
     }
 
+    // Numbers and booleans can be plugged into string inputs, and integers
+    // into float inputs. The compiler inserts the conversions.
+    fn connection_compatibility(
+        &self,
+        input_type: &MyDataType,
+        _user_state: &mut app::MyGraphState
+    ) -> ConnectionCompatibility {
+        match (self, input_type) {
+            _ if self == input_type => ConnectionCompatibility::Allowed,
+            (MyDataType::Integer, MyDataType::Float) =>
+                ConnectionCompatibility::Converted("Integer to Float".to_string()),
+            (MyDataType::Integer | MyDataType::Float | MyDataType::Boolean, MyDataType::String) =>
+                ConnectionCompatibility::Converted(format!("{} to String", self.name())),
+            _ =>
+                ConnectionCompatibility::Rejected(
+                    format!("Cannot connect {} to {}", self.name(), input_type.name())
+                ),
+        }
+    }
+
     fn name(&self) -> Cow<'_, str> {
         match self {
             MyDataType::String => Cow::Borrowed("String"),
@@ -112,6 +132,24 @@ impl DataTypeTrait<app::MyGraphState> for MyDataType {
             MyDataType::Execution => Cow::Borrowed("Execution"),
         }
     }
+
+    fn is_execution(&self, _user_state: &mut app::MyGraphState) -> bool {
+        matches!(self, MyDataType::Execution)
+    }
+
+    fn animated_flow(&self, user_state: &mut app::MyGraphState) -> bool {
+        self.is_execution(user_state)
+    }
+
+    // Execution pins look like arrows, so they are not mistaken for data.
+    fn port_shape(&self, _user_state: &mut app::MyGraphState) -> PortShape {
+        match self {
+            MyDataType::Execution => PortShape::Triangle,
+            MyDataType::Boolean => PortShape::Diamond,
+            MyDataType::String => PortShape::Square,
+            MyDataType::Integer | MyDataType::Float => PortShape::Circle,
+        }
+    }
 }
 
 